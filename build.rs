@@ -0,0 +1,19 @@
+fn main() {
+    #[cfg(feature = "layer-shell")]
+    probe_layer_shell();
+}
+
+/// Links libgtk-layer-shell, which `src/layer_shell.rs` declares by hand.
+/// `gtk_layer_is_supported` was added in 0.6.
+#[cfg(feature = "layer-shell")]
+fn probe_layer_shell() {
+    if let Err(e) = pkg_config::Config::new()
+        .atleast_version("0.6")
+        .probe("gtk-layer-shell-0")
+    {
+        panic!(
+            "The layer-shell feature needs gtk-layer-shell 0.6 or newer: {}",
+            e
+        );
+    }
+}
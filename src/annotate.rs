@@ -0,0 +1,452 @@
+use crate::raster::RgbaImage;
+
+/// A position in image pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+/// One mark drawn over an image. Colors are straight RGBA.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Rectangle {
+        from: Point,
+        to: Point,
+        color: [u8; 4],
+        width: f64,
+    },
+    Arrow {
+        from: Point,
+        to: Point,
+        color: [u8; 4],
+        width: f64,
+    },
+    Freehand {
+        points: Vec<Point>,
+        color: [u8; 4],
+        width: f64,
+    },
+    /// Text rasterised by the caller (the viewer lays it out with Pango),
+    /// placed with its top-left corner at `at`.
+    Text {
+        at: Point,
+        text: String,
+        bitmap: RgbaImage,
+    },
+    /// Replaces the area with blocks of their average color.
+    Pixelate { from: Point, to: Point, block: u32 },
+    /// Box-blurs the area.
+    Blur { from: Point, to: Point, radius: u32 },
+}
+
+impl Annotation {
+    /// Draws the annotation onto `image`.
+    pub fn draw(&self, image: &mut RgbaImage) {
+        match self {
+            Annotation::Rectangle {
+                from,
+                to,
+                color,
+                width,
+            } => {
+                let corners = [
+                    *from,
+                    Point::new(to.x, from.y),
+                    *to,
+                    Point::new(from.x, to.y),
+                ];
+                let sides: Vec<_> = (0..4).map(|i| (corners[i], corners[(i + 1) % 4])).collect();
+                stroke(image, &sides, *width, *color);
+            }
+            Annotation::Arrow {
+                from,
+                to,
+                color,
+                width,
+            } => stroke(image, &arrow_segments(*from, *to, *width), *width, *color),
+            Annotation::Freehand {
+                points,
+                color,
+                width,
+            } => {
+                let segments: Vec<_> = match points.as_slice() {
+                    [] => Vec::new(),
+                    [point] => vec![(*point, *point)],
+                    points => points.windows(2).map(|pair| (pair[0], pair[1])).collect(),
+                };
+                stroke(image, &segments, *width, *color);
+            }
+            Annotation::Text { at, bitmap, .. } => {
+                image.draw_image(bitmap, at.x.round() as i64, at.y.round() as i64)
+            }
+            Annotation::Pixelate { from, to, block } => pixelate(image, *from, *to, *block),
+            Annotation::Blur { from, to, radius } => blur(image, *from, *to, *radius),
+        }
+    }
+
+    /// Follows a drag to `point`: shapes move their second corner,
+    /// freehand lines gain a point and text moves.
+    pub fn drag_to(&mut self, point: Point) {
+        match self {
+            Annotation::Rectangle { to, .. }
+            | Annotation::Arrow { to, .. }
+            | Annotation::Pixelate { to, .. }
+            | Annotation::Blur { to, .. } => *to = point,
+            Annotation::Freehand { points, .. } => points.push(point),
+            Annotation::Text { at, .. } => *at = point,
+        }
+    }
+
+    /// Whether the annotation would leave no mark, like a box dragged
+    /// out to zero size.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Annotation::Rectangle { from, to, .. }
+            | Annotation::Arrow { from, to, .. }
+            | Annotation::Pixelate { from, to, .. }
+            | Annotation::Blur { from, to, .. } => from == to,
+            Annotation::Freehand { points, .. } => points.is_empty(),
+            Annotation::Text { text, .. } => text.is_empty(),
+        }
+    }
+}
+
+/// Draws `annotations` in order over a copy of `base`.
+pub fn flatten(base: &RgbaImage, annotations: &[Annotation]) -> RgbaImage {
+    let mut image = base.clone();
+    for annotation in annotations {
+        annotation.draw(&mut image);
+    }
+    image
+}
+
+/// The shaft and the two sides of the head of an arrow pointing at `to`.
+fn arrow_segments(from: Point, to: Point, width: f64) -> Vec<(Point, Point)> {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        return vec![(from, to)];
+    }
+    let head = (width * 4.0).max(12.0).min(length * 0.6);
+    let angle = dy.atan2(dx);
+    let side = |offset: f64| {
+        let angle = angle + std::f64::consts::PI - offset;
+        Point::new(to.x + head * angle.cos(), to.y + head * angle.sin())
+    };
+    let spread = std::f64::consts::FRAC_PI_6;
+    vec![(from, to), (to, side(spread)), (to, side(-spread))]
+}
+
+fn distance_to_segment(point: Point, (a, b): (Point, Point)) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((point.x - a.x) * dx + (point.y - a.y) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    (point.x - (a.x + t * dx)).hypot(point.y - (a.y + t * dy))
+}
+
+/// Strokes segments with round caps and anti-aliased edges. Coverage is
+/// merged across segments first so overlapping joins are not drawn twice.
+fn stroke(image: &mut RgbaImage, segments: &[(Point, Point)], width: f64, color: [u8; 4]) {
+    if segments.is_empty() || image.width == 0 || image.height == 0 {
+        return;
+    }
+    let radius = width.max(1.0) / 2.0;
+    let margin = radius + 1.0;
+    let clamp_x = |x: f64| x.clamp(0.0, image.width as f64 - 1.0) as i64;
+    let clamp_y = |y: f64| y.clamp(0.0, image.height as f64 - 1.0) as i64;
+
+    let points = segments.iter().flat_map(|(a, b)| [a, b]);
+    let min_x = clamp_x(points.clone().map(|p| p.x).fold(f64::MAX, f64::min) - margin);
+    let max_x = clamp_x(points.clone().map(|p| p.x).fold(f64::MIN, f64::max) + margin);
+    let min_y = clamp_y(points.clone().map(|p| p.y).fold(f64::MAX, f64::min) - margin);
+    let max_y = clamp_y(points.map(|p| p.y).fold(f64::MIN, f64::max) + margin);
+    let box_width = (max_x - min_x + 1) as usize;
+    let mut coverage = vec![0f64; box_width * (max_y - min_y + 1) as usize];
+
+    for &(a, b) in segments {
+        let from_x = clamp_x(a.x.min(b.x) - margin);
+        let to_x = clamp_x(a.x.max(b.x) + margin);
+        let from_y = clamp_y(a.y.min(b.y) - margin);
+        let to_y = clamp_y(a.y.max(b.y) + margin);
+        for y in from_y..=to_y {
+            for x in from_x..=to_x {
+                let center = Point::new(x as f64 + 0.5, y as f64 + 0.5);
+                let value = (radius + 0.5 - distance_to_segment(center, (a, b))).clamp(0.0, 1.0);
+                let cell = &mut coverage[(y - min_y) as usize * box_width + (x - min_x) as usize];
+                *cell = cell.max(value);
+            }
+        }
+    }
+
+    for (index, value) in coverage.into_iter().enumerate() {
+        if value > 0.0 {
+            let x = min_x + (index % box_width) as i64;
+            let y = min_y + (index / box_width) as i64;
+            image.blend_pixel(x, y, color, value);
+        }
+    }
+}
+
+/// The pixel rectangle between two corners, clipped to the image, as
+/// `(x, y, width, height)`.
+pub fn clip_rect(image: &RgbaImage, from: Point, to: Point) -> Option<(u32, u32, u32, u32)> {
+    let clip = |value: f64, max: u32| value.round().clamp(0.0, max as f64) as u32;
+    let x0 = clip(from.x.min(to.x), image.width);
+    let x1 = clip(from.x.max(to.x), image.width);
+    let y0 = clip(from.y.min(to.y), image.height);
+    let y1 = clip(from.y.max(to.y), image.height);
+    (x1 > x0 && y1 > y0).then_some((x0, y0, x1 - x0, y1 - y0))
+}
+
+fn pixelate(image: &mut RgbaImage, from: Point, to: Point, block: u32) {
+    let Some((x0, y0, width, height)) = clip_rect(image, from, to) else {
+        return;
+    };
+    let block = block.max(1);
+    for by in (y0..y0 + height).step_by(block as usize) {
+        for bx in (x0..x0 + width).step_by(block as usize) {
+            let bw = block.min(x0 + width - bx);
+            let bh = block.min(y0 + height - by);
+            let mut sum = [0u64; 4];
+            for y in by..by + bh {
+                for x in bx..bx + bw {
+                    for (total, value) in sum.iter_mut().zip(image.pixel(x, y)) {
+                        *total += value as u64;
+                    }
+                }
+            }
+            let count = (bw * bh) as u64;
+            let average = sum.map(|total| ((total + count / 2) / count) as u8);
+            for y in by..by + bh {
+                for x in bx..bx + bw {
+                    image.set_pixel(x, y, average);
+                }
+            }
+        }
+    }
+}
+
+fn blur(image: &mut RgbaImage, from: Point, to: Point, radius: u32) {
+    let Some((x0, y0, width, height)) = clip_rect(image, from, to) else {
+        return;
+    };
+    if radius == 0 {
+        return;
+    }
+    // Two box passes (horizontal, then vertical) sampling only inside the area
+    for horizontal in [true, false] {
+        let source = image.clone();
+        for y in y0..y0 + height {
+            for x in x0..x0 + width {
+                let (start, end, position) = if horizontal {
+                    (x0, x0 + width - 1, x)
+                } else {
+                    (y0, y0 + height - 1, y)
+                };
+                let low = position.saturating_sub(radius).max(start);
+                let high = (position + radius).min(end);
+                let mut sum = [0u64; 4];
+                for sample in low..=high {
+                    let pixel = if horizontal {
+                        source.pixel(sample, y)
+                    } else {
+                        source.pixel(x, sample)
+                    };
+                    for (total, value) in sum.iter_mut().zip(pixel) {
+                        *total += value as u64;
+                    }
+                }
+                let count = (high - low + 1) as u64;
+                image.set_pixel(x, y, sum.map(|total| ((total + count / 2) / count) as u8));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    fn canvas() -> RgbaImage {
+        RgbaImage::filled(20, 20, WHITE)
+    }
+
+    #[test]
+    fn test_rectangle_outline() {
+        let image = flatten(
+            &canvas(),
+            &[Annotation::Rectangle {
+                from: Point::new(4.0, 4.0),
+                to: Point::new(15.0, 15.0),
+                color: RED,
+                width: 2.0,
+            }],
+        );
+        assert_eq!(image.pixel(4, 10), RED);
+        assert_eq!(image.pixel(10, 15), RED);
+        assert_eq!(image.pixel(10, 10), WHITE);
+        assert_eq!(image.pixel(0, 0), WHITE);
+    }
+
+    #[test]
+    fn test_arrow_has_head() {
+        let image = flatten(
+            &RgbaImage::filled(60, 40, WHITE),
+            &[Annotation::Arrow {
+                from: Point::new(5.0, 20.0),
+                to: Point::new(55.0, 20.0),
+                color: RED,
+                width: 2.0,
+            }],
+        );
+        assert_eq!(image.pixel(30, 20), RED);
+        // The head's sides run back from the tip above and below the shaft
+        assert_eq!(image.pixel(48, 16), RED);
+        assert_eq!(image.pixel(48, 23), RED);
+        assert_eq!(image.pixel(30, 10), WHITE);
+    }
+
+    #[test]
+    fn test_freehand_joins_are_not_double_blended() {
+        let half_red = [255, 0, 0, 128];
+        let image = flatten(
+            &canvas(),
+            &[Annotation::Freehand {
+                points: vec![
+                    Point::new(2.0, 10.0),
+                    Point::new(10.0, 10.0),
+                    Point::new(18.0, 10.0),
+                ],
+                color: half_red,
+                width: 3.0,
+            }],
+        );
+        assert_eq!(image.pixel(10, 10), image.pixel(6, 10));
+        assert_eq!(image.pixel(6, 10), [255, 127, 127, 255]);
+    }
+
+    #[test]
+    fn test_single_point_freehand_draws_a_dot() {
+        let image = flatten(
+            &canvas(),
+            &[Annotation::Freehand {
+                points: vec![Point::new(10.5, 10.5)],
+                color: RED,
+                width: 4.0,
+            }],
+        );
+        assert_eq!(image.pixel(10, 10), RED);
+        assert_eq!(image.pixel(15, 10), WHITE);
+    }
+
+    #[test]
+    fn test_text_bitmap_is_composited() {
+        let mut bitmap = RgbaImage::new(2, 1);
+        bitmap.set_pixel(1, 0, RED);
+        let image = flatten(
+            &canvas(),
+            &[Annotation::Text {
+                at: Point::new(3.0, 4.0),
+                text: "x".to_string(),
+                bitmap,
+            }],
+        );
+        assert_eq!(image.pixel(3, 4), WHITE);
+        assert_eq!(image.pixel(4, 4), RED);
+    }
+
+    #[test]
+    fn test_pixelate_averages_blocks() {
+        let mut base = RgbaImage::filled(4, 2, [0, 0, 0, 255]);
+        base.set_pixel(0, 0, [200, 100, 0, 255]);
+        let image = flatten(
+            &base,
+            &[Annotation::Pixelate {
+                from: Point::new(0.0, 0.0),
+                to: Point::new(2.0, 2.0),
+                block: 2,
+            }],
+        );
+        assert_eq!(image.pixel(1, 1), [50, 25, 0, 255]);
+        assert_eq!(image.pixel(0, 0), [50, 25, 0, 255]);
+        assert_eq!(image.pixel(2, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_blur_stays_inside_area() {
+        let mut base = RgbaImage::filled(9, 1, [0, 0, 0, 255]);
+        base.set_pixel(4, 0, [90, 90, 90, 255]);
+        let image = flatten(
+            &base,
+            &[Annotation::Blur {
+                from: Point::new(3.0, 0.0),
+                to: Point::new(6.0, 1.0),
+                radius: 1,
+            }],
+        );
+        assert_eq!(image.pixel(4, 0), [30, 30, 30, 255]);
+        assert_eq!(image.pixel(3, 0), [45, 45, 45, 255]);
+        assert_eq!(image.pixel(2, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_clip_rect() {
+        let image = canvas();
+        assert_eq!(
+            clip_rect(&image, Point::new(15.0, 25.0), Point::new(-5.0, 5.0)),
+            Some((0, 5, 15, 15))
+        );
+        assert_eq!(
+            clip_rect(&image, Point::new(3.0, 3.0), Point::new(3.0, 9.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_flatten_leaves_base_untouched() {
+        let base = canvas();
+        let annotations = [Annotation::Pixelate {
+            from: Point::new(0.0, 0.0),
+            to: Point::new(20.0, 20.0),
+            block: 4,
+        }];
+        let _ = flatten(&base, &annotations);
+        assert_eq!(base, canvas());
+    }
+
+    #[test]
+    fn test_drag_to() {
+        let mut arrow = Annotation::Arrow {
+            from: Point::new(1.0, 1.0),
+            to: Point::new(1.0, 1.0),
+            color: RED,
+            width: 2.0,
+        };
+        assert!(arrow.is_empty());
+        arrow.drag_to(Point::new(5.0, 3.0));
+        assert!(!arrow.is_empty());
+        assert!(matches!(arrow, Annotation::Arrow { to, .. } if to == Point::new(5.0, 3.0)));
+
+        let mut line = Annotation::Freehand {
+            points: vec![Point::new(0.0, 0.0)],
+            color: RED,
+            width: 2.0,
+        };
+        line.drag_to(Point::new(2.0, 2.0));
+        assert!(matches!(&line, Annotation::Freehand { points, .. } if points.len() == 2));
+    }
+}
@@ -0,0 +1,511 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::run_command;
+
+/// Access to a system clipboard. Viewers and `main` only talk to the
+/// clipboard through this trait, so the concrete tool can be swapped out.
+pub trait ClipboardBackend {
+    /// Newline-separated list of the MIME types currently offered.
+    fn list_types(&self) -> Result<String, String>;
+
+    /// Raw payload of a single MIME type.
+    fn read(&self, mime_type: &str) -> Result<Vec<u8>, String>;
+
+    /// Replaces the clipboard with the given `(mime_type, data)` pairs,
+    /// preferred type first. Backends that can't offer several types at once
+    /// (see [`offers_multiple_types`](Self::offers_multiple_types)) offer
+    /// only the first pair.
+    fn write(&self, contents: &[(&str, &[u8])]) -> Result<(), String>;
+
+    /// Whether [`write`](Self::write) offers every pair it is given. The
+    /// command-line tools own the selection for one type at a time, so this
+    /// is false unless a backend says otherwise.
+    fn offers_multiple_types(&self) -> bool {
+        false
+    }
+
+    /// Empties the clipboard.
+    fn clear(&self) -> Result<(), String>;
+
+    /// Blocks until this process no longer has to serve anything it copied.
+    /// Backends that serve copies from their own threads wait until other
+    /// clients have replaced each one; the command-line tools serve from
+    /// processes of their own, so by default this returns at once.
+    fn wait_for_copies(&self) {}
+
+    /// Blocks, calling `on_change` whenever the clipboard may have changed
+    /// (and once at the start) until it returns `false`. The default polls
+    /// every second, so callers must tolerate calls for unchanged content.
+    fn watch(&self, on_change: &mut dyn FnMut() -> bool) -> Result<(), String> {
+        while on_change() {
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        Ok(())
+    }
+}
+
+/// Which X11/Wayland selection a backend reads from and writes to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    #[default]
+    Clipboard,
+    /// The middle-click primary selection.
+    Primary,
+}
+
+impl Selection {
+    pub fn label(self) -> &'static str {
+        match self {
+            Selection::Clipboard => "Clipboard",
+            Selection::Primary => "Primary Selection",
+        }
+    }
+
+    pub fn other(self) -> Self {
+        match self {
+            Selection::Clipboard => Selection::Primary,
+            Selection::Primary => Selection::Clipboard,
+        }
+    }
+}
+
+/// Backend built on the `wl-paste`/`wl-copy` binaries from wl-clipboard.
+#[derive(Debug, Default, Clone)]
+pub struct WlClipboard {
+    selection: Selection,
+}
+
+impl WlClipboard {
+    pub fn new(selection: Selection) -> Self {
+        Self { selection }
+    }
+
+    fn command<'a>(&self, tool: &'a str, args: &[&'a str]) -> Vec<&'a str> {
+        let mut command = vec![tool];
+        if self.selection == Selection::Primary {
+            command.push("--primary");
+        }
+        command.extend_from_slice(args);
+        command
+    }
+
+    /// The `wl-copy` command and its input for [`ClipboardBackend::write`].
+    /// wl-copy serves a single MIME type per selection, so only the
+    /// preferred (first) representation is offered.
+    fn write_command<'a>(
+        &self,
+        contents: &[(&'a str, &'a [u8])],
+    ) -> Result<(Vec<&'a str>, &'a [u8]), String> {
+        let (mime_type, data) = contents
+            .first()
+            .ok_or_else(|| "Nothing to copy".to_string())?;
+        Ok((self.command("wl-copy", &["--type", mime_type]), data))
+    }
+}
+
+impl ClipboardBackend for WlClipboard {
+    fn list_types(&self) -> Result<String, String> {
+        run_command(&self.command("wl-paste", &["--list-types"]))
+            .map(|types| String::from_utf8_lossy(&types).into_owned())
+            .ok_or_else(|| "wl-paste --list-types failed".to_string())
+    }
+
+    fn read(&self, mime_type: &str) -> Result<Vec<u8>, String> {
+        run_command(&self.command("wl-paste", &["--no-newline", "--type", mime_type]))
+            .ok_or_else(|| format!("wl-paste failed for {}", mime_type))
+    }
+
+    fn write(&self, contents: &[(&str, &[u8])]) -> Result<(), String> {
+        let (command, data) = self.write_command(contents)?;
+        pipe_to_command(&command, data)
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        run_command(&self.command("wl-copy", &["--clear"]))
+            .map(|_| ())
+            .ok_or_else(|| "wl-copy --clear failed".to_string())
+    }
+
+    fn watch(&self, on_change: &mut dyn FnMut() -> bool) -> Result<(), String> {
+        // `wl-paste --watch` runs the command on every change; each `echo`
+        // shows up as one line on its stdout.
+        let args = self.command("wl-paste", &["--watch", "echo"]);
+        let mut child = Command::new(args[0])
+            .args(&args[1..])
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to spawn wl-paste: {}", e))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| "Failed to capture wl-paste output".to_string())?;
+
+        for line in BufReader::new(stdout).lines() {
+            if line.is_err() || !on_change() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(());
+            }
+        }
+
+        let _ = child.wait();
+        Err("wl-paste --watch exited".to_string())
+    }
+}
+
+/// Backend built on `xclip`, for X11 sessions without a Wayland compositor.
+#[derive(Debug, Default, Clone)]
+pub struct XClipboard {
+    selection: Selection,
+}
+
+impl XClipboard {
+    pub fn new(selection: Selection) -> Self {
+        Self { selection }
+    }
+
+    fn command<'a>(&self, args: &[&'a str]) -> Vec<&'a str> {
+        let selection = match self.selection {
+            Selection::Clipboard => "clipboard",
+            Selection::Primary => "primary",
+        };
+        let mut command = vec!["xclip", "-selection", selection];
+        command.extend_from_slice(args);
+        command
+    }
+
+    /// The `xclip` command and its input for [`ClipboardBackend::write`].
+    /// Like wl-copy, xclip owns the selection for one target at a time.
+    fn write_command<'a>(
+        &self,
+        contents: &[(&'a str, &'a [u8])],
+    ) -> Result<(Vec<&'a str>, &'a [u8]), String> {
+        let (mime_type, data) = contents
+            .first()
+            .ok_or_else(|| "Nothing to copy".to_string())?;
+        Ok((self.command(&["-t", mime_type, "-i"]), data))
+    }
+}
+
+impl ClipboardBackend for XClipboard {
+    fn list_types(&self) -> Result<String, String> {
+        run_command(&self.command(&["-t", "TARGETS", "-o"]))
+            .map(|types| String::from_utf8_lossy(&types).into_owned())
+            .ok_or_else(|| "xclip -t TARGETS failed".to_string())
+    }
+
+    fn read(&self, mime_type: &str) -> Result<Vec<u8>, String> {
+        run_command(&self.command(&["-t", mime_type, "-o"]))
+            .ok_or_else(|| format!("xclip failed for {}", mime_type))
+    }
+
+    fn write(&self, contents: &[(&str, &[u8])]) -> Result<(), String> {
+        let (command, data) = self.write_command(contents)?;
+        pipe_to_command(&command, data)
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        // xclip has no clear option; owning the selection with no data is equivalent.
+        pipe_to_command(&self.command(&["-i"]), b"")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Wayland,
+    X11,
+}
+
+impl BackendKind {
+    /// Picks a backend from the session's `WAYLAND_DISPLAY` and `DISPLAY`
+    /// values. Wayland wins when both are set, since XWayland exports both.
+    pub fn for_session(wayland_display: Option<&str>, display: Option<&str>) -> Option<Self> {
+        let is_set = |value: Option<&str>| value.is_some_and(|v| !v.is_empty());
+        if is_set(wayland_display) {
+            Some(BackendKind::Wayland)
+        } else if is_set(display) {
+            Some(BackendKind::X11)
+        } else {
+            None
+        }
+    }
+
+    pub fn create(self, selection: Selection) -> Box<dyn ClipboardBackend> {
+        match self {
+            BackendKind::Wayland => wayland_backend(selection),
+            BackendKind::X11 => Box::new(XClipboard::new(selection)),
+        }
+    }
+}
+
+/// Prefers the native data-control protocol and falls back to wl-clipboard
+/// on compositors that do not implement it (e.g. GNOME).
+#[cfg(feature = "data-control")]
+fn wayland_backend(selection: Selection) -> Box<dyn ClipboardBackend> {
+    match crate::data_control::DataControlClipboard::connect(selection) {
+        Ok(backend) => Box::new(backend),
+        Err(_) => Box::new(WlClipboard::new(selection)),
+    }
+}
+
+#[cfg(not(feature = "data-control"))]
+fn wayland_backend(selection: Selection) -> Box<dyn ClipboardBackend> {
+    Box::new(WlClipboard::new(selection))
+}
+
+/// Backend for `selection` in the current session, falling back to
+/// wl-clipboard when neither display variable is set.
+pub fn default_backend(selection: Selection) -> Box<dyn ClipboardBackend> {
+    let wayland_display = std::env::var("WAYLAND_DISPLAY").ok();
+    let display = std::env::var("DISPLAY").ok();
+    BackendKind::for_session(wayland_display.as_deref(), display.as_deref())
+        .unwrap_or(BackendKind::Wayland)
+        .create(selection)
+}
+
+/// The MIME types `backend` offers when it writes `contents`: all of them,
+/// or only the first on backends that offer one type at a time.
+pub fn offered_types<'a>(
+    backend: &dyn ClipboardBackend,
+    contents: &[(&'a str, &[u8])],
+) -> Vec<&'a str> {
+    let offered = if backend.offers_multiple_types() {
+        contents
+    } else {
+        &contents[..contents.len().min(1)]
+    };
+    offered.iter().map(|(mime_type, _)| *mime_type).collect()
+}
+
+/// In-memory backend for unit tests. `list_types` returns `types` as given,
+/// which may name types without data; `read` finds a payload in `contents`;
+/// and `write` replaces `contents` with everything it is given.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct FakeClipboard {
+    pub types: String,
+    pub contents: std::cell::RefCell<Vec<(String, Vec<u8>)>>,
+    /// What `offers_multiple_types` reports.
+    pub multiple_types: bool,
+}
+
+#[cfg(test)]
+impl FakeClipboard {
+    pub fn new(types: &str, contents: &[(&str, &[u8])]) -> Self {
+        Self {
+            types: types.to_string(),
+            contents: std::cell::RefCell::new(
+                contents
+                    .iter()
+                    .map(|(mime_type, data)| (mime_type.to_string(), data.to_vec()))
+                    .collect(),
+            ),
+            multiple_types: false,
+        }
+    }
+}
+
+#[cfg(test)]
+impl ClipboardBackend for FakeClipboard {
+    fn list_types(&self) -> Result<String, String> {
+        Ok(self.types.clone())
+    }
+
+    fn read(&self, mime_type: &str) -> Result<Vec<u8>, String> {
+        self.contents
+            .borrow()
+            .iter()
+            .find(|(mime, _)| mime == mime_type)
+            .map(|(_, data)| data.clone())
+            .ok_or_else(|| format!("{} not offered", mime_type))
+    }
+
+    fn write(&self, contents: &[(&str, &[u8])]) -> Result<(), String> {
+        if contents.is_empty() {
+            return Err("Nothing to copy".to_string());
+        }
+        *self.contents.borrow_mut() = contents
+            .iter()
+            .map(|(mime_type, data)| (mime_type.to_string(), data.to_vec()))
+            .collect();
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        self.contents.borrow_mut().clear();
+        Ok(())
+    }
+
+    fn offers_multiple_types(&self) -> bool {
+        self.multiple_types
+    }
+}
+
+/// Spawns `args` and feeds `data` to its stdin, waiting for it to exit.
+pub(crate) fn pipe_to_command(args: &[&str], data: &[u8]) -> Result<(), String> {
+    let mut child = Command::new(args[0])
+        .args(&args[1..])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn {}: {}", args[0], e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(data)
+            .map_err(|e| format!("Failed to write to {}: {}", args[0], e))?;
+    }
+
+    let exit_status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for {}: {}", args[0], e))?;
+
+    if exit_status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} command failed", args[0]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wl_clipboard_write_requires_content() {
+        let result = WlClipboard::default().write(&[]);
+        assert_eq!(result.unwrap_err(), "Nothing to copy");
+    }
+
+    #[test]
+    fn test_pipe_to_command_missing_binary() {
+        let result = pipe_to_command(&["this_command_does_not_exist_12345"], b"data");
+        assert!(result.unwrap_err().starts_with("Failed to spawn"));
+    }
+
+    #[test]
+    fn test_pipe_to_command_success() {
+        if let Err(err) = pipe_to_command(&["cat"], b"data") {
+            assert!(err.starts_with("Failed to spawn"));
+        }
+    }
+
+    #[test]
+    fn test_wl_clipboard_command_selection() {
+        let clipboard = WlClipboard::new(Selection::Clipboard);
+        assert_eq!(
+            clipboard.command("wl-paste", &["--list-types"]),
+            ["wl-paste", "--list-types"]
+        );
+
+        let primary = WlClipboard::new(Selection::Primary);
+        assert_eq!(
+            primary.command("wl-copy", &["--type", "text/plain"]),
+            ["wl-copy", "--primary", "--type", "text/plain"]
+        );
+    }
+
+    #[test]
+    fn test_x_clipboard_command_selection() {
+        let clipboard = XClipboard::default();
+        assert_eq!(
+            clipboard.command(&["-t", "TARGETS", "-o"]),
+            ["xclip", "-selection", "clipboard", "-t", "TARGETS", "-o"]
+        );
+
+        let primary = XClipboard::new(Selection::Primary);
+        assert_eq!(
+            primary.command(&["-i"]),
+            ["xclip", "-selection", "primary", "-i"]
+        );
+    }
+
+    #[test]
+    fn test_selection_label_and_other() {
+        assert_eq!(Selection::default(), Selection::Clipboard);
+        assert_eq!(Selection::Clipboard.label(), "Clipboard");
+        assert_eq!(Selection::Primary.label(), "Primary Selection");
+        assert_eq!(Selection::Clipboard.other(), Selection::Primary);
+        assert_eq!(Selection::Primary.other(), Selection::Clipboard);
+    }
+
+    #[test]
+    fn test_command_line_backends_offer_first_type() {
+        let contents: [(&str, &[u8]); 2] = [("text/html", b"<b>hi</b>"), ("text/plain", b"hi")];
+
+        let wayland = WlClipboard::default();
+        assert!(!wayland.offers_multiple_types());
+        assert_eq!(
+            wayland.write_command(&contents),
+            Ok((vec!["wl-copy", "--type", "text/html"], &b"<b>hi</b>"[..]))
+        );
+
+        let x11 = XClipboard::default();
+        assert!(!x11.offers_multiple_types());
+        assert_eq!(
+            x11.write_command(&contents),
+            Ok((
+                vec!["xclip", "-selection", "clipboard", "-t", "text/html", "-i"],
+                &b"<b>hi</b>"[..]
+            ))
+        );
+
+        assert_eq!(offered_types(&wayland, &contents), ["text/html"]);
+        assert!(offered_types(&x11, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_x_clipboard_write_requires_content() {
+        let result = XClipboard::default().write(&[]);
+        assert_eq!(result.unwrap_err(), "Nothing to copy");
+    }
+
+    #[test]
+    fn test_backend_kind_for_session() {
+        assert_eq!(
+            BackendKind::for_session(Some("wayland-0"), None),
+            Some(BackendKind::Wayland)
+        );
+        assert_eq!(
+            BackendKind::for_session(None, Some(":0")),
+            Some(BackendKind::X11)
+        );
+        assert_eq!(BackendKind::for_session(None, None), None);
+    }
+
+    #[test]
+    fn test_backend_kind_prefers_wayland() {
+        assert_eq!(
+            BackendKind::for_session(Some("wayland-1"), Some(":1")),
+            Some(BackendKind::Wayland)
+        );
+    }
+
+    #[test]
+    fn test_backend_kind_ignores_empty_values() {
+        assert_eq!(
+            BackendKind::for_session(Some(""), Some(":0")),
+            Some(BackendKind::X11)
+        );
+        assert_eq!(BackendKind::for_session(Some(""), Some("")), None);
+    }
+
+    #[test]
+    fn test_default_watch_stops_when_asked() {
+        let mut calls = 0;
+        FakeClipboard::default()
+            .watch(&mut || {
+                calls += 1;
+                false
+            })
+            .unwrap();
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_backend_as_trait_object() {
+        let backend: Box<dyn ClipboardBackend> = Box::new(WlClipboard::default());
+        assert!(backend.write(&[]).is_err());
+    }
+}
@@ -0,0 +1,515 @@
+use crate::convert::{ImageFormat, DEFAULT_JPEG_QUALITY};
+use crate::Selection;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Open a viewer for the clipboard content (the default).
+    View,
+    /// Print the offered MIME types and the detected content type.
+    ListTypes,
+    /// List every offered type with its size and a preview.
+    Inspect,
+    /// Record every clipboard change to the history store.
+    Daemon,
+    /// Browse the recorded history.
+    History,
+    /// Re-encode the clipboard image to the `--to` formats.
+    Convert,
+    /// Write the clipboard content to a file.
+    Save,
+    /// Put a file on the clipboard, then view it.
+    Load,
+    Help,
+    Version,
+}
+
+/// Viewer requested with `--text` or `--image`, overriding detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewerKind {
+    Text,
+    Image,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    pub selection: Selection,
+    pub mime_type: Option<String>,
+    pub viewer: Option<ViewerKind>,
+    /// Target formats of `convert`, in the order given.
+    pub formats: Vec<ImageFormat>,
+    /// File or directory `save` writes to, or the file `load` reads.
+    pub path: Option<PathBuf>,
+    /// Config file given with `--config`, read instead of the default one.
+    pub config_path: Option<PathBuf>,
+    /// `TABLE.KEY=VALUE` settings from `--set`, in the order given.
+    pub settings: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            command: Command::View,
+            selection: Selection::Clipboard,
+            mime_type: None,
+            viewer: None,
+            formats: Vec::new(),
+            path: None,
+            config_path: None,
+            settings: Vec::new(),
+        }
+    }
+}
+
+pub fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} [OPTIONS] [COMMAND]
+
+Shows the image or text currently on the clipboard.
+
+Commands:
+  list-types          Print the offered MIME types and the detected content type
+  inspect             Show every offered MIME type with its size and a preview,
+                      and copy or save a single type
+  daemon              Watch the clipboard and record its history
+  history             Browse the recorded history and copy an entry again
+  convert --to <MIME> Re-encode the clipboard image (PNG, JPEG, WebP or BMP);
+                      repeat --to to offer several formats at once
+  save <PATH>         Write the clipboard content to a file, or to a
+                      timestamped file when PATH is a directory
+  load <PATH>         Put a file on the clipboard (images by type, text as
+                      text, anything else as a file reference) and view it
+
+Options:
+  -p, --primary       Use the primary selection instead of the clipboard
+  -t, --type <MIME>   Read this MIME type instead of detecting one
+      --text          Force the text viewer
+      --image         Force the image viewer
+      --to <MIME>     Target format for convert
+      --quality <N>   JPEG quality for convert, 1-100 (default {DEFAULT_JPEG_QUALITY})
+      --config <PATH> Read this config file instead of
+                      $XDG_CONFIG_HOME/waypin/config.toml
+      --set <TABLE.KEY=VALUE>
+                      Override a config setting, e.g. --set window.margin=8
+  -V, --version       Print version information
+  -h, --help          Print this help"
+    )
+}
+
+/// Parses the arguments after the program name.
+pub fn parse_args<I, S>(args: I) -> Result<Options, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut options = Options::default();
+    let mut command = None;
+    let mut targets = Vec::new();
+    let mut quality = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "-h" | "--help" => return Ok(with_command(options, Command::Help)),
+            "-V" | "--version" => return Ok(with_command(options, Command::Version)),
+            "-p" | "--primary" => options.selection = Selection::Primary,
+            "-t" | "--type" => {
+                let mime_type = args
+                    .next()
+                    .ok_or_else(|| "--type requires a MIME type".to_string())?;
+                options.mime_type = Some(mime_type.as_ref().to_string());
+            }
+            "--to" => {
+                let mime_type = args
+                    .next()
+                    .ok_or_else(|| "--to requires a MIME type".to_string())?;
+                targets.push(mime_type.as_ref().to_string());
+            }
+            "--quality" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| "--quality requires a value".to_string())?;
+                quality = Some(parse_quality(value.as_ref())?);
+            }
+            "--config" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| "--config requires a path".to_string())?;
+                options.config_path = Some(PathBuf::from(path.as_ref()));
+            }
+            "--set" => {
+                let setting = args
+                    .next()
+                    .ok_or_else(|| "--set requires TABLE.KEY=VALUE".to_string())?;
+                options.settings.push(parse_setting(setting.as_ref())?);
+            }
+            "--text" => set_viewer(&mut options, ViewerKind::Text)?,
+            "--image" => set_viewer(&mut options, ViewerKind::Image)?,
+            "list-types" if command.is_none() => command = Some(Command::ListTypes),
+            "inspect" if command.is_none() => command = Some(Command::Inspect),
+            "daemon" if command.is_none() => command = Some(Command::Daemon),
+            "history" if command.is_none() => command = Some(Command::History),
+            "convert" if command.is_none() => command = Some(Command::Convert),
+            "save" if command.is_none() => command = Some(Command::Save),
+            "load" if command.is_none() => command = Some(Command::Load),
+            other if other.starts_with("--type=") => {
+                options.mime_type = Some(other["--type=".len()..].to_string());
+            }
+            other if other.starts_with("--to=") => {
+                targets.push(other["--to=".len()..].to_string());
+            }
+            other if other.starts_with("--quality=") => {
+                quality = Some(parse_quality(&other["--quality=".len()..])?);
+            }
+            other if other.starts_with("--config=") => {
+                options.config_path = Some(PathBuf::from(&other["--config=".len()..]));
+            }
+            other if other.starts_with("--set=") => {
+                options
+                    .settings
+                    .push(parse_setting(&other["--set=".len()..])?);
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option: {}", other)),
+            other
+                if matches!(command, Some(Command::Save | Command::Load))
+                    && options.path.is_none() =>
+            {
+                options.path = Some(PathBuf::from(other));
+            }
+            other => return Err(format!("Unexpected argument: {}", other)),
+        }
+    }
+
+    if options.mime_type.as_deref() == Some("") {
+        return Err("--type requires a MIME type".to_string());
+    }
+
+    let command = command.unwrap_or(Command::View);
+    match command {
+        Command::Save if options.path.is_none() => return Err("save requires a path".to_string()),
+        Command::Load if options.path.is_none() => return Err("load requires a path".to_string()),
+        _ => {}
+    }
+    if command == Command::Convert {
+        if targets.is_empty() {
+            return Err("convert requires --to <MIME>".to_string());
+        }
+        let quality = quality.unwrap_or(DEFAULT_JPEG_QUALITY);
+        for target in &targets {
+            let format = ImageFormat::from_mime_type(target, quality).ok_or_else(|| {
+                format!(
+                    "Unsupported target format: {} (expected image/png, image/jpeg, image/webp or image/bmp)",
+                    target
+                )
+            })?;
+            if !options.formats.contains(&format) {
+                options.formats.push(format);
+            }
+        }
+    } else if !targets.is_empty() || quality.is_some() {
+        return Err("--to and --quality can only be used with convert".to_string());
+    }
+
+    Ok(with_command(options, command))
+}
+
+/// The `--config` and `--set` arguments that load the same configuration
+/// as `options`, for passing on to another waypin.
+pub fn config_args(options: &Options) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(path) = &options.config_path {
+        args.push(format!("--config={}", path.display()));
+    }
+    for setting in &options.settings {
+        args.push(format!("--set={}", setting));
+    }
+    args
+}
+
+fn parse_quality(value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(quality) if (1..=100).contains(&quality) => Ok(quality),
+        _ => Err(format!("Invalid quality: {} (expected 1-100)", value)),
+    }
+}
+
+fn parse_setting(setting: &str) -> Result<String, String> {
+    if setting.contains('=') {
+        Ok(setting.to_string())
+    } else {
+        Err(format!(
+            "Invalid setting: {} (expected TABLE.KEY=VALUE)",
+            setting
+        ))
+    }
+}
+
+fn with_command(options: Options, command: Command) -> Options {
+    Options { command, ..options }
+}
+
+fn set_viewer(options: &mut Options, viewer: ViewerKind) -> Result<(), String> {
+    match options.viewer {
+        Some(existing) if existing != viewer => {
+            Err("--text and --image cannot be used together".to_string())
+        }
+        _ => {
+            options.viewer = Some(viewer);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args_default() {
+        let options = parse_args(Vec::<String>::new()).unwrap();
+        assert_eq!(options, Options::default());
+        assert_eq!(options.command, Command::View);
+    }
+
+    #[test]
+    fn test_parse_args_help_and_version() {
+        assert_eq!(parse_args(["--help"]).unwrap().command, Command::Help);
+        assert_eq!(parse_args(["-h"]).unwrap().command, Command::Help);
+        assert_eq!(parse_args(["--version"]).unwrap().command, Command::Version);
+        assert_eq!(parse_args(["-V"]).unwrap().command, Command::Version);
+    }
+
+    #[test]
+    fn test_parse_args_help_wins_over_invalid_arguments() {
+        assert_eq!(
+            parse_args(["--help", "bogus"]).unwrap().command,
+            Command::Help
+        );
+    }
+
+    #[test]
+    fn test_parse_args_primary() {
+        assert_eq!(
+            parse_args(["--primary"]).unwrap().selection,
+            Selection::Primary
+        );
+        assert_eq!(parse_args(["-p"]).unwrap().selection, Selection::Primary);
+    }
+
+    #[test]
+    fn test_parse_args_type() {
+        let options = parse_args(["--type", "image/webp"]).unwrap();
+        assert_eq!(options.mime_type.as_deref(), Some("image/webp"));
+
+        let options = parse_args(["--type=text/html"]).unwrap();
+        assert_eq!(options.mime_type.as_deref(), Some("text/html"));
+
+        let options = parse_args(["-t", "text/plain"]).unwrap();
+        assert_eq!(options.mime_type.as_deref(), Some("text/plain"));
+    }
+
+    #[test]
+    fn test_parse_args_type_requires_value() {
+        assert!(parse_args(["--type"]).is_err());
+        assert!(parse_args(["--type="]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_viewer() {
+        assert_eq!(
+            parse_args(["--text"]).unwrap().viewer,
+            Some(ViewerKind::Text)
+        );
+        assert_eq!(
+            parse_args(["--image"]).unwrap().viewer,
+            Some(ViewerKind::Image)
+        );
+        assert_eq!(
+            parse_args(["--text", "--image"]).unwrap_err(),
+            "--text and --image cannot be used together"
+        );
+    }
+
+    #[test]
+    fn test_parse_args_list_types() {
+        let options = parse_args(["list-types", "--primary"]).unwrap();
+        assert_eq!(options.command, Command::ListTypes);
+        assert_eq!(options.selection, Selection::Primary);
+    }
+
+    #[test]
+    fn test_parse_args_inspect() {
+        let options = parse_args(["-p", "inspect"]).unwrap();
+        assert_eq!(options.command, Command::Inspect);
+        assert_eq!(options.selection, Selection::Primary);
+        assert!(parse_args(["inspect", "history"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_daemon() {
+        assert_eq!(parse_args(["daemon"]).unwrap().command, Command::Daemon);
+        assert!(parse_args(["daemon", "list-types"]).is_err());
+        assert_eq!(parse_args(["history"]).unwrap().command, Command::History);
+    }
+
+    #[test]
+    fn test_parse_args_convert() {
+        let options = parse_args(["convert", "--to", "image/png"]).unwrap();
+        assert_eq!(options.command, Command::Convert);
+        assert_eq!(options.formats, [ImageFormat::Png]);
+
+        let options = parse_args([
+            "convert",
+            "--to=image/jpg",
+            "--to",
+            "image/webp",
+            "--quality",
+            "75",
+        ])
+        .unwrap();
+        assert_eq!(
+            options.formats,
+            [ImageFormat::Jpeg { quality: 75 }, ImageFormat::Webp]
+        );
+
+        // Duplicates are offered once
+        let options = parse_args(["convert", "--to", "image/bmp", "--to=image/x-bmp"]).unwrap();
+        assert_eq!(options.formats, [ImageFormat::Bmp]);
+    }
+
+    #[test]
+    fn test_parse_args_convert_errors() {
+        assert_eq!(
+            parse_args(["convert"]).unwrap_err(),
+            "convert requires --to <MIME>"
+        );
+        assert!(parse_args(["convert", "--to"]).is_err());
+        assert!(parse_args(["convert", "--to", "image/gif"])
+            .unwrap_err()
+            .starts_with("Unsupported target format: image/gif"));
+        assert_eq!(
+            parse_args(["convert", "--to", "image/jpeg", "--quality", "101"]).unwrap_err(),
+            "Invalid quality: 101 (expected 1-100)"
+        );
+        assert_eq!(
+            parse_args(["--to", "image/png"]).unwrap_err(),
+            "--to and --quality can only be used with convert"
+        );
+    }
+
+    #[test]
+    fn test_parse_args_save() {
+        let options = parse_args(["save", "/tmp/out.png", "--primary"]).unwrap();
+        assert_eq!(options.command, Command::Save);
+        assert_eq!(options.path, Some(PathBuf::from("/tmp/out.png")));
+        assert_eq!(options.selection, Selection::Primary);
+
+        assert_eq!(parse_args(["save"]).unwrap_err(), "save requires a path");
+        assert!(parse_args(["save", "a", "b"]).is_err());
+        assert!(parse_args(["out.png"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_load() {
+        let options = parse_args(["load", "shot.png"]).unwrap();
+        assert_eq!(options.command, Command::Load);
+        assert_eq!(options.path, Some(PathBuf::from("shot.png")));
+        assert_eq!(parse_args(["load"]).unwrap_err(), "load requires a path");
+        assert_eq!(
+            parse_args(["load", "save"]).unwrap().path,
+            Some(PathBuf::from("save"))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_config() {
+        let options = parse_args([
+            "--config",
+            "/tmp/waypin.toml",
+            "--set",
+            "window.margin=8",
+            "--set=window.backend=wayland",
+        ])
+        .unwrap();
+        assert_eq!(options.config_path, Some(PathBuf::from("/tmp/waypin.toml")));
+        assert_eq!(
+            options.settings,
+            ["window.margin=8", "window.backend=wayland"]
+        );
+        assert_eq!(
+            parse_args(["--config=a.toml"]).unwrap().config_path,
+            Some(PathBuf::from("a.toml"))
+        );
+        assert!(parse_args(["--config"]).is_err());
+        assert_eq!(
+            parse_args(["--set", "window.margin"]).unwrap_err(),
+            "Invalid setting: window.margin (expected TABLE.KEY=VALUE)"
+        );
+    }
+
+    #[test]
+    fn test_config_args_round_trip() {
+        let options = parse_args([
+            "--primary",
+            "--config",
+            "/tmp/waypin.toml",
+            "--set",
+            "window.margin=8",
+            "--set=history.max_entries=10",
+            "history",
+        ])
+        .unwrap();
+        let args = config_args(&options);
+        assert_eq!(
+            args,
+            [
+                "--config=/tmp/waypin.toml",
+                "--set=window.margin=8",
+                "--set=history.max_entries=10"
+            ]
+        );
+        let forwarded = parse_args(&args).unwrap();
+        assert_eq!(forwarded.config_path, options.config_path);
+        assert_eq!(forwarded.settings, options.settings);
+        assert!(config_args(&Options::default()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_args_rejects_unknown() {
+        assert_eq!(
+            parse_args(["--bogus"]).unwrap_err(),
+            "Unknown option: --bogus"
+        );
+        assert_eq!(
+            parse_args(["invalid-arg"]).unwrap_err(),
+            "Unexpected argument: invalid-arg"
+        );
+        assert!(parse_args(["list-types", "list-types"]).is_err());
+    }
+
+    #[test]
+    fn test_usage_mentions_options() {
+        let usage = usage("waypin");
+        assert!(usage.starts_with("Usage: waypin"));
+        for option in [
+            "--primary",
+            "--type",
+            "--text",
+            "--image",
+            "--version",
+            "list-types",
+            "daemon",
+            "history",
+            "convert",
+            "save",
+            "load",
+            "--to",
+            "--quality",
+            "--config",
+            "--set",
+        ] {
+            assert!(usage.contains(option), "usage is missing {}", option);
+        }
+    }
+}
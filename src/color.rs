@@ -0,0 +1,103 @@
+/// MIME type GTK uses for dragged and copied colors: four native-endian
+/// 16-bit channels, red, green, blue and alpha.
+pub const X_COLOR_MIME_TYPE: &str = "application/x-color";
+
+/// Reads a color from clipboard data: `application/x-color` data, or text
+/// such as `#3584e4`, `#3584e4cc`, `#38e` or `rgb(53, 132, 228)`.
+pub fn parse_color(mime_type: &str, data: &[u8]) -> Option<[u8; 4]> {
+    if mime_type == X_COLOR_MIME_TYPE {
+        let channels: [u8; 8] = data.try_into().ok()?;
+        let channel = |i: usize| (u16::from_ne_bytes([channels[i], channels[i + 1]]) >> 8) as u8;
+        return Some([channel(0), channel(2), channel(4), channel(6)]);
+    }
+    parse_color_text(std::str::from_utf8(data).ok()?.trim())
+}
+
+fn parse_color_text(text: &str) -> Option<[u8; 4]> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return match hex.len() {
+            3 => Some([digit(0)? * 17, digit(1)? * 17, digit(2)? * 17, 255]),
+            6 => Some([byte(0)?, byte(2)?, byte(4)?, 255]),
+            8 => Some([byte(0)?, byte(2)?, byte(4)?, byte(6)?]),
+            _ => None,
+        };
+    }
+
+    let (name, rest) = text.split_once('(')?;
+    let values: Vec<&str> = rest.strip_suffix(')')?.split(',').map(str::trim).collect();
+    let channel = |value: &str| value.parse::<u8>().ok();
+    match (name.trim(), values.as_slice()) {
+        ("rgb", [r, g, b]) => Some([channel(r)?, channel(g)?, channel(b)?, 255]),
+        ("rgba", [r, g, b, a]) => {
+            let alpha = a.parse::<f64>().ok().filter(|a| (0.0..=1.0).contains(a))?;
+            Some([
+                channel(r)?,
+                channel(g)?,
+                channel(b)?,
+                (alpha * 255.0).round() as u8,
+            ])
+        }
+        _ => None,
+    }
+}
+
+/// Formats a color as `#rrggbb`, or `#rrggbbaa` when it is not opaque.
+pub fn format_hex(rgba: [u8; 4]) -> String {
+    let [r, g, b, a] = rgba;
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_x_color() {
+        let data: Vec<u8> = [0x3535u16, 0x8484, 0xe4e4, 0xffff]
+            .iter()
+            .flat_map(|channel| channel.to_ne_bytes())
+            .collect();
+        assert_eq!(
+            parse_color(X_COLOR_MIME_TYPE, &data),
+            Some([0x35, 0x84, 0xe4, 0xff])
+        );
+        assert_eq!(parse_color(X_COLOR_MIME_TYPE, &data[..6]), None);
+    }
+
+    #[test]
+    fn test_parse_color_text() {
+        let blue = Some([0x35, 0x84, 0xe4, 0xff]);
+        assert_eq!(parse_color("text/plain", b"#3584e4\n"), blue);
+        assert_eq!(parse_color("text/plain", b"rgb(53, 132, 228)"), blue);
+        assert_eq!(
+            parse_color("text/plain", b"#38e"),
+            Some([0x33, 0x88, 0xee, 0xff])
+        );
+        assert_eq!(
+            parse_color("text/plain", b"#3584e480"),
+            Some([0x35, 0x84, 0xe4, 0x80])
+        );
+        assert_eq!(
+            parse_color("text/plain", b"rgba(0,0,0,0.5)"),
+            Some([0, 0, 0, 128])
+        );
+        assert_eq!(parse_color("text/plain", b"#zzzzzz"), None);
+        assert_eq!(parse_color("text/plain", b"rgb(300, 0, 0)"), None);
+        assert_eq!(parse_color("text/plain", b"blue"), None);
+    }
+
+    #[test]
+    fn test_format_hex() {
+        assert_eq!(format_hex([0x35, 0x84, 0xe4, 0xff]), "#3584e4");
+        assert_eq!(format_hex([0, 0, 0, 0x80]), "#00000080");
+    }
+}
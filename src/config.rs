@@ -0,0 +1,860 @@
+//! The user configuration at `$XDG_CONFIG_HOME/waypin/config.toml`.
+//!
+//! ```toml
+//! [window]
+//! text_width = 400
+//! text_height = 300
+//! # Default [width, height] of the other viewers
+//! rich_text_size = [500, 400]
+//! file_list_size = [520, 360]
+//! hex_size = [720, 480]
+//! color_size = [320, 280]
+//! inspector_size = [760, 460]
+//! history_size = [480, 560]
+//! margin = 16
+//! # Seconds before the image viewer's controls fade; 0 keeps them shown
+//! fade_seconds = 3
+//! keep_above = true
+//! modal = true
+//! # GDK backend: "wayland", "x11" or "auto" (Wayland, falling back to X11)
+//! backend = "auto"
+//!
+//! # Pin the image and text viewers with layer-shell (needs the
+//! # layer-shell build feature and a compositor that supports it)
+//! [overlay]
+//! enabled = true
+//! # A corner, or [x, y] from the top-left of the output
+//! position = "bottom-right"
+//! margin = 32
+//!
+//! [detection]
+//! # Content types in the order they win; the rest follow in default order
+//! priority = ["text", "image"]
+//! # Image types read first when several are offered
+//! image_types = ["image/jpeg"]
+//!
+//! [mime_types]
+//! "application/json" = "text"
+//! "application/x-color" = "swatch"
+//!
+//! [history]
+//! # Entries `waypin daemon` keeps before deleting the oldest
+//! max_entries = 500
+//! ```
+//!
+//! Settings missing from the file keep their defaults, and `--set` on the
+//! command line wins over the file.
+
+use std::fmt;
+use std::fs;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::detect::{parse_content_type_name, DetectionRules};
+use crate::history::DEFAULT_MAX_ENTRIES;
+use crate::overlay::{Corner, OverlayConfig, OverlayPosition};
+use crate::ClipboardContentType;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub detection: DetectionRules,
+    pub history: HistoryConfig,
+}
+
+/// How much clipboard history `waypin daemon` keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryConfig {
+    /// Entries kept before the oldest are deleted.
+    pub max_entries: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+}
+
+/// How viewer windows look and behave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowConfig {
+    /// Default size of the text viewer.
+    pub text_width: i32,
+    pub text_height: i32,
+    /// Default `(width, height)` of the rich text, file list, hex, color,
+    /// inspector and history windows.
+    pub rich_text_size: (i32, i32),
+    pub file_list_size: (i32, i32),
+    pub hex_size: (i32, i32),
+    pub color_size: (i32, i32),
+    pub inspector_size: (i32, i32),
+    pub history_size: (i32, i32),
+    /// Space around each viewer's content, in pixels.
+    pub margin: i32,
+    /// Seconds without pointer movement before the image viewer's controls
+    /// fade out; 0 keeps them shown.
+    pub fade_seconds: u32,
+    /// Keep viewers above other windows.
+    pub keep_above: bool,
+    pub modal: bool,
+    pub backend: DisplayBackend,
+    /// The `[overlay]` table.
+    pub overlay: OverlayConfig,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            text_width: 400,
+            text_height: 300,
+            rich_text_size: (500, 400),
+            file_list_size: (520, 360),
+            hex_size: (720, 480),
+            color_size: (320, 280),
+            inspector_size: (760, 460),
+            history_size: (480, 560),
+            margin: 16,
+            fade_seconds: 3,
+            keep_above: true,
+            modal: true,
+            backend: DisplayBackend::Auto,
+            overlay: OverlayConfig::default(),
+        }
+    }
+}
+
+/// The GDK backend GTK is started with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayBackend {
+    X11,
+    Wayland,
+    /// `GDK_BACKEND` when it is set, otherwise native Wayland with X11 as
+    /// the fallback.
+    Auto,
+}
+
+impl DisplayBackend {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "x11" => Ok(Self::X11),
+            "wayland" => Ok(Self::Wayland),
+            "auto" => Ok(Self::Auto),
+            _ => Err(format!(
+                "unknown backend \"{}\" (expected x11, wayland or auto)",
+                name
+            )),
+        }
+    }
+
+    /// The `GDK_BACKEND` value to set, given its `current` value, or `None`
+    /// to leave it alone.
+    pub fn gdk_backend(&self, current: Option<&str>) -> Option<&'static str> {
+        match self {
+            Self::X11 => Some("x11"),
+            Self::Wayland => Some("wayland"),
+            Self::Auto if current.is_some_and(|value| !value.is_empty()) => None,
+            Self::Auto => Some("wayland,x11"),
+        }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/waypin/config.toml`, falling back to
+    /// `~/.config/waypin/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_home.join("waypin").join("config.toml"))
+    }
+
+    /// Reads the config at `path`. A missing file gives the defaults.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::from_toml(&text).map_err(|e| format!("{}:{}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// The config waypin runs with: the file given with `--config` (which
+    /// must exist) or the default one, then the `--set` overrides in order.
+    pub fn resolve(path: Option<&Path>, overrides: &[String]) -> Result<Self, String> {
+        let mut config = match path {
+            Some(path) if !path.exists() => {
+                return Err(format!("Config file {} does not exist", path.display()))
+            }
+            Some(path) => Self::load(path)?,
+            None => match Self::default_path() {
+                Some(path) => Self::load(&path)?,
+                None => Self::default(),
+            },
+        };
+        for setting in overrides {
+            config.set_override(setting)?;
+        }
+        Ok(config)
+    }
+
+    /// Parses and validates a config file. Errors start with the line
+    /// number they refer to.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        config.apply(text).map_err(|e| match e.span {
+            Some(span) => {
+                let line = text.as_bytes()[..span.start.min(text.len())]
+                    .iter()
+                    .filter(|&&byte| byte == b'\n')
+                    .count()
+                    + 1;
+                format!("{}: {}", line, e.message)
+            }
+            None => e.message,
+        })?;
+        Ok(config)
+    }
+
+    /// Applies a `table.key=value` setting from `--set`. The value is read
+    /// as TOML, or as a plain string when it isn't valid TOML, so
+    /// `window.backend=wayland` needs no quotes.
+    pub fn set_override(&mut self, setting: &str) -> Result<(), String> {
+        let error = |e: String| format!("--set {}: {}", setting, e);
+        let (name, value) = setting
+            .split_once('=')
+            .ok_or_else(|| error("expected TABLE.KEY=VALUE".to_string()))?;
+        let (table, key) = name
+            .trim()
+            .split_once('.')
+            .ok_or_else(|| error("expected TABLE.KEY=VALUE".to_string()))?;
+        let value = value.trim();
+        let value = match toml::from_str::<toml::Table>(&format!("value = {}", value)) {
+            Ok(_) => value.to_string(),
+            Err(_) => quoted(value),
+        };
+        let text = format!(
+            "[{}]\n{} = {}\n",
+            quoted(table),
+            quoted(key.trim_matches('"')),
+            value
+        );
+        self.apply(&text).map_err(|e| error(e.message))
+    }
+
+    /// Validates the settings in `text` and applies them over the current
+    /// ones.
+    fn apply(&mut self, text: &str) -> Result<(), ConfigError> {
+        let file: ConfigFile = toml::from_str(text)?;
+        let window = &mut self.window;
+        set(&mut window.text_width, file.window.text_width, |n| {
+            integer("text_width", n, 1..=10000)
+        })?;
+        set(&mut window.text_height, file.window.text_height, |n| {
+            integer("text_height", n, 1..=10000)
+        })?;
+        set(
+            &mut window.rich_text_size,
+            file.window.rich_text_size,
+            |size| window_size("rich_text_size", size),
+        )?;
+        set(
+            &mut window.file_list_size,
+            file.window.file_list_size,
+            |size| window_size("file_list_size", size),
+        )?;
+        set(&mut window.hex_size, file.window.hex_size, |size| {
+            window_size("hex_size", size)
+        })?;
+        set(&mut window.color_size, file.window.color_size, |size| {
+            window_size("color_size", size)
+        })?;
+        set(
+            &mut window.inspector_size,
+            file.window.inspector_size,
+            |size| window_size("inspector_size", size),
+        )?;
+        set(&mut window.history_size, file.window.history_size, |size| {
+            window_size("history_size", size)
+        })?;
+        set(&mut window.margin, file.window.margin, |n| {
+            integer("margin", n, 0..=200)
+        })?;
+        set(&mut window.fade_seconds, file.window.fade_seconds, |n| {
+            integer("fade_seconds", n, 0..=3600)
+        })?;
+        set(&mut window.keep_above, file.window.keep_above, Ok)?;
+        set(&mut window.modal, file.window.modal, Ok)?;
+        set(&mut window.backend, file.window.backend, |name| {
+            DisplayBackend::parse(&name)
+        })?;
+
+        let overlay = &mut window.overlay;
+        set(&mut overlay.enabled, file.overlay.enabled, Ok)?;
+        set(
+            &mut overlay.position,
+            file.overlay.position,
+            overlay_position,
+        )?;
+        set(&mut overlay.margin, file.overlay.margin, |n| {
+            integer("margin", n, 0..=10000)
+        })?;
+
+        let detection = &mut self.detection;
+        set(&mut detection.priority, file.detection.priority, priority)?;
+        set(
+            &mut detection.image_types,
+            file.detection.image_types,
+            image_types,
+        )?;
+        for (mime_type, content_type) in file.mime_types.0 {
+            let span = content_type.span();
+            let content_type =
+                parse_content_type_name(content_type.get_ref()).map_err(|message| ConfigError {
+                    span: Some(span),
+                    message,
+                })?;
+            let mime_types = &mut detection.mime_types;
+            match mime_types
+                .iter_mut()
+                .find(|(mapped, _)| *mapped == mime_type)
+            {
+                Some(mapping) => mapping.1 = content_type,
+                None => mime_types.push((mime_type, content_type)),
+            }
+        }
+
+        set(
+            &mut self.history.max_entries,
+            file.history.max_entries,
+            |n| integer("max_entries", n, 1..=100_000),
+        )?;
+        Ok(())
+    }
+}
+
+/// The config file as written. Every setting is optional, and keeps where
+/// it was written so validation errors can point at its line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    window: WindowTable,
+    overlay: OverlayTable,
+    detection: DetectionTable,
+    mime_types: MimeTypesTable,
+    history: HistoryTable,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WindowTable {
+    text_width: Option<Spanned<i64>>,
+    text_height: Option<Spanned<i64>>,
+    rich_text_size: Option<Spanned<Vec<i64>>>,
+    file_list_size: Option<Spanned<Vec<i64>>>,
+    hex_size: Option<Spanned<Vec<i64>>>,
+    color_size: Option<Spanned<Vec<i64>>>,
+    inspector_size: Option<Spanned<Vec<i64>>>,
+    history_size: Option<Spanned<Vec<i64>>>,
+    margin: Option<Spanned<i64>>,
+    fade_seconds: Option<Spanned<i64>>,
+    keep_above: Option<Spanned<bool>>,
+    modal: Option<Spanned<bool>>,
+    backend: Option<Spanned<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct OverlayTable {
+    enabled: Option<Spanned<bool>>,
+    /// A corner name or `[x, y]`, told apart in [`overlay_position`].
+    position: Option<Spanned<toml::Value>>,
+    margin: Option<Spanned<i64>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DetectionTable {
+    priority: Option<Spanned<Vec<String>>>,
+    image_types: Option<Spanned<Vec<String>>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HistoryTable {
+    max_entries: Option<Spanned<i64>>,
+}
+
+/// The `[mime_types]` entries in the order they are written, which is the
+/// order [`DetectionRules::mime_type_for`] tries them in.
+#[derive(Debug, Default)]
+struct MimeTypesTable(Vec<(String, Spanned<String>)>);
+
+impl<'de> Deserialize<'de> for MimeTypesTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Entries;
+
+        impl<'de> Visitor<'de> for Entries {
+            type Value = MimeTypesTable;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a table of MIME types and viewers")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(MimeTypesTable(entries))
+            }
+        }
+
+        deserializer.deserialize_map(Entries)
+    }
+}
+
+/// An error in the config text, with the bytes it is about when known.
+struct ConfigError {
+    span: Option<Range<usize>>,
+    message: String,
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        Self {
+            span: error.span(),
+            message: error.message().trim_end().to_string(),
+        }
+    }
+}
+
+/// Validates `value`, if the config sets it, and stores it in `setting`.
+fn set<T, U>(
+    setting: &mut U,
+    value: Option<Spanned<T>>,
+    check: impl FnOnce(T) -> Result<U, String>,
+) -> Result<(), ConfigError> {
+    if let Some(value) = value {
+        let span = value.span();
+        *setting = check(value.into_inner()).map_err(|message| ConfigError {
+            span: Some(span),
+            message,
+        })?;
+    }
+    Ok(())
+}
+
+/// `text` as a TOML string, for building a document from a `--set`.
+fn quoted(text: &str) -> String {
+    toml::Value::String(text.trim().to_string()).to_string()
+}
+
+/// A corner name, or an `[x, y]` offset from the top-left.
+fn overlay_position(value: toml::Value) -> Result<OverlayPosition, String> {
+    match value {
+        toml::Value::String(name) => Corner::parse(&name).map(OverlayPosition::Corner),
+        toml::Value::Array(values) => match values.as_slice() {
+            [toml::Value::Integer(x), toml::Value::Integer(y)] => Ok(OverlayPosition::At(
+                integer("position", *x, 0..=100_000)?,
+                integer("position", *y, 0..=100_000)?,
+            )),
+            _ => Err("\"position\" must be a corner or [x, y]".to_string()),
+        },
+        _ => Err("\"position\" must be a corner or [x, y]".to_string()),
+    }
+}
+
+fn window_size(key: &str, size: Vec<i64>) -> Result<(i32, i32), String> {
+    match size.as_slice() {
+        [width, height] => Ok((
+            integer(key, *width, 1..=10000)?,
+            integer(key, *height, 1..=10000)?,
+        )),
+        _ => Err(format!("\"{}\" must be [width, height]", key)),
+    }
+}
+
+fn integer<T: TryFrom<i64>>(
+    key: &str,
+    number: i64,
+    range: RangeInclusive<i64>,
+) -> Result<T, String> {
+    if !range.contains(&number) {
+        return Err(format!(
+            "\"{}\" must be between {} and {}, not {}",
+            key,
+            range.start(),
+            range.end(),
+            number
+        ));
+    }
+    T::try_from(number).map_err(|_| format!("\"{}\" must be a smaller integer", key))
+}
+
+fn priority(names: Vec<String>) -> Result<Vec<ClipboardContentType>, String> {
+    let mut priority = Vec::new();
+    for name in names {
+        let content_type = parse_content_type_name(&name)?;
+        if priority.contains(&content_type) {
+            return Err(format!("\"{}\" is listed twice in priority", name));
+        }
+        priority.push(content_type);
+    }
+    Ok(priority)
+}
+
+fn image_types(mime_types: Vec<String>) -> Result<Vec<String>, String> {
+    match mime_types
+        .iter()
+        .find(|mime_type| !mime_type.starts_with("image/"))
+    {
+        Some(mime_type) => Err(format!(
+            "\"{}\" in image_types is not an image type",
+            mime_type
+        )),
+        None => Ok(mime_types),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_config() {
+        let config = Config::from_toml(
+            "[detection]\n\
+             priority = [\"text\", \"image\"]\n\
+             image_types = [\"image/jpeg\"]\n\
+             \n\
+             [mime_types]\n\
+             \"application/json\" = \"text\"\n\
+             \"application/x-color\" = \"swatch\"\n",
+        )
+        .unwrap();
+        let rules = &config.detection;
+        assert_eq!(
+            rules.priority,
+            [ClipboardContentType::Text, ClipboardContentType::Image]
+        );
+        assert_eq!(rules.image_types, ["image/jpeg"]);
+        assert_eq!(
+            rules.mime_types,
+            [
+                ("application/json".to_string(), ClipboardContentType::Text),
+                (
+                    "application/x-color".to_string(),
+                    ClipboardContentType::Color
+                ),
+            ]
+        );
+        assert_eq!(
+            rules.detect("image/png\ntext/plain"),
+            ClipboardContentType::Text
+        );
+        assert_eq!(
+            rules.mime_type_for("image/png\nimage/jpeg", &ClipboardContentType::Image),
+            Some("image/jpeg")
+        );
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_detection_config_errors() {
+        for (text, error) in [
+            (
+                "[detection]\npriority = [\"txt\"]",
+                "2: unknown viewer \"txt\" (expected text, html, rtf, image, files, color or raw)",
+            ),
+            (
+                "[detection]\npriority = \"text\"",
+                "2: invalid type: string \"text\", expected a sequence",
+            ),
+            (
+                "[detection]\npriority = [\"text\", \"text\"]",
+                "2: \"text\" is listed twice in priority",
+            ),
+            (
+                "[detection]\nimage_types = [\"png\"]",
+                "2: \"png\" in image_types is not an image type",
+            ),
+            (
+                "[detection]\norder = []",
+                "2: unknown field `order`, expected `priority` or `image_types`",
+            ),
+            (
+                "[mime_types]\n\"application/json\" = 1",
+                "2: invalid type: integer `1`, expected a string",
+            ),
+            ("[colors]", "1: unknown field `colors`, expected one of `window`, `overlay`, `detection`, `mime_types`, `history`"),
+            (
+                "priority = []",
+                "1: unknown field `priority`, expected one of `window`, `overlay`, `detection`, `mime_types`, `history`",
+            ),
+        ] {
+            assert_eq!(Config::from_toml(text).unwrap_err(), error, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_window_config() {
+        assert_eq!(
+            Config::default().window,
+            WindowConfig {
+                text_width: 400,
+                text_height: 300,
+                rich_text_size: (500, 400),
+                file_list_size: (520, 360),
+                hex_size: (720, 480),
+                color_size: (320, 280),
+                inspector_size: (760, 460),
+                history_size: (480, 560),
+                margin: 16,
+                fade_seconds: 3,
+                keep_above: true,
+                modal: true,
+                backend: DisplayBackend::Auto,
+                overlay: OverlayConfig::default(),
+            }
+        );
+        let config = Config::from_toml(
+            "[window]\n\
+             text_width = 640\n\
+             hex_size = [1024, 600]\n\
+             margin = 0\n\
+             fade_seconds = 0\n\
+             keep_above = false\n\
+             backend = \"x11\"\n",
+        )
+        .unwrap();
+        let window = config.window;
+        assert_eq!((window.text_width, window.text_height), (640, 300));
+        assert_eq!(window.hex_size, (1024, 600));
+        assert_eq!(window.history_size, (480, 560));
+        assert_eq!(window.margin, 0);
+        assert_eq!(window.fade_seconds, 0);
+        assert!(!window.keep_above);
+        assert!(window.modal);
+        assert_eq!(window.backend, DisplayBackend::X11);
+    }
+
+    #[test]
+    fn test_overlay_config() {
+        let config = Config::from_toml(
+            "[overlay]\nenabled = true\nposition = \"bottom-left\"\nmargin = 8\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.window.overlay,
+            OverlayConfig {
+                enabled: true,
+                position: OverlayPosition::Corner(Corner::BottomLeft),
+                margin: 8,
+            }
+        );
+        let config = Config::from_toml("[overlay]\nposition = [120, 40]\n").unwrap();
+        assert_eq!(config.window.overlay.position, OverlayPosition::At(120, 40));
+
+        for (text, error) in [
+            (
+                "[overlay]\nposition = \"left\"",
+                "2: unknown corner \"left\" (expected top-left, top-right, bottom-left or bottom-right)",
+            ),
+            (
+                "[overlay]\nposition = [1, 2, 3]",
+                "2: \"position\" must be a corner or [x, y]",
+            ),
+            (
+                "[overlay]\nposition = [-1, 0]",
+                "2: \"position\" must be between 0 and 100000, not -1",
+            ),
+            (
+                "[overlay]\nposition = 4",
+                "2: \"position\" must be a corner or [x, y]",
+            ),
+        ] {
+            assert_eq!(Config::from_toml(text).unwrap_err(), error, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_history_config() {
+        assert_eq!(Config::default().history.max_entries, DEFAULT_MAX_ENTRIES);
+        let mut config = Config::from_toml("[history]\nmax_entries = 50\n").unwrap();
+        assert_eq!(config.history.max_entries, 50);
+        config.set_override("history.max_entries=20").unwrap();
+        assert_eq!(config.history.max_entries, 20);
+        assert_eq!(
+            Config::from_toml("\n[history]\nmax_entries = 0").unwrap_err(),
+            "3: \"max_entries\" must be between 1 and 100000, not 0"
+        );
+    }
+
+    #[test]
+    fn test_gdk_backend() {
+        let auto = DisplayBackend::Auto;
+        assert_eq!(auto.gdk_backend(None), Some("wayland,x11"));
+        assert_eq!(auto.gdk_backend(Some("")), Some("wayland,x11"));
+        assert_eq!(auto.gdk_backend(Some("x11")), None);
+        assert_eq!(DisplayBackend::X11.gdk_backend(None), Some("x11"));
+        assert_eq!(
+            DisplayBackend::Wayland.gdk_backend(Some("x11")),
+            Some("wayland")
+        );
+    }
+
+    #[test]
+    fn test_window_config_errors() {
+        for (text, error) in [
+            (
+                "[window]\ntext_width = 0",
+                "2: \"text_width\" must be between 1 and 10000, not 0",
+            ),
+            (
+                "[window]\nhex_size = [720]",
+                "2: \"hex_size\" must be [width, height]",
+            ),
+            (
+                "[window]\ncolor_size = [320, 0]",
+                "2: \"color_size\" must be between 1 and 10000, not 0",
+            ),
+            (
+                "[window]\nmargin = \"16px\"",
+                "2: invalid type: string \"16px\", expected i64",
+            ),
+            (
+                "[window]\nfade_seconds = 1.5",
+                "2: invalid type: floating point `1.5`, expected i64",
+            ),
+            (
+                "[window]\nmodal = \"yes\"",
+                "2: invalid type: string \"yes\", expected a boolean",
+            ),
+            (
+                "[window]\nbackend = \"mir\"",
+                "2: unknown backend \"mir\" (expected x11, wayland or auto)",
+            ),
+            (
+                "[window]\nwidth = 400",
+                "2: unknown field `width`, expected one of `text_width`, `text_height`, `rich_text_size`, `file_list_size`, `hex_size`, `color_size`, `inspector_size`, `history_size`, `margin`, `fade_seconds`, `keep_above`, `modal`, `backend`",
+            ),
+        ] {
+            assert_eq!(Config::from_toml(text).unwrap_err(), error, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_set_override() {
+        let mut config = Config::from_toml(
+            "[window]\nmargin = 8\nbackend = \"wayland\"\n\
+             [mime_types]\n\"application/json\" = \"text\"\n",
+        )
+        .unwrap();
+        config.set_override("window.margin=24").unwrap();
+        config.set_override("window.backend=x11").unwrap();
+        config.set_override("window.keep_above = false").unwrap();
+        config
+            .set_override("detection.priority=[\"text\"]")
+            .unwrap();
+        config
+            .set_override("mime_types.\"application/json\"=raw")
+            .unwrap();
+        config
+            .set_override("mime_types.application/vnd.api+json=text")
+            .unwrap();
+        assert_eq!(config.window.margin, 24);
+        assert_eq!(config.window.backend, DisplayBackend::X11);
+        assert!(!config.window.keep_above);
+        assert_eq!(config.detection.priority, [ClipboardContentType::Text]);
+        assert_eq!(
+            config.detection.mime_types,
+            [
+                (
+                    "application/json".to_string(),
+                    ClipboardContentType::Unsupported
+                ),
+                (
+                    "application/vnd.api+json".to_string(),
+                    ClipboardContentType::Text
+                ),
+            ]
+        );
+
+        for (setting, error) in [
+            ("margin=4", "--set margin=4: expected TABLE.KEY=VALUE"),
+            (
+                "window.margin",
+                "--set window.margin: expected TABLE.KEY=VALUE",
+            ),
+            (
+                "window.margin=wide",
+                "--set window.margin=wide: invalid type: string \"wide\", expected i64",
+            ),
+            (
+                "colors.fg=red",
+                "--set colors.fg=red: unknown field `colors`, expected one of `window`, `overlay`, `detection`, `mime_types`, `history`",
+            ),
+        ] {
+            assert_eq!(config.set_override(setting).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("waypin.toml");
+        fs::write(
+            &path,
+            "[window]\ntext_width = 800\nmargin = 4\n[detection]\nimage_types = [\"image/gif\"]\n",
+        )
+        .unwrap();
+
+        // Defaults, then the file, then --set in order
+        let config = Config::resolve(
+            Some(&path),
+            &[
+                "window.margin=10".to_string(),
+                "window.margin=12".to_string(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.window.text_width, 800);
+        assert_eq!(config.window.text_height, 300);
+        assert_eq!(config.window.margin, 12);
+        assert_eq!(config.detection.image_types, ["image/gif"]);
+
+        let missing = dir.path().join("missing.toml");
+        assert_eq!(
+            Config::resolve(Some(&missing), &[]).unwrap_err(),
+            format!("Config file {} does not exist", missing.display())
+        );
+        assert!(Config::resolve(Some(&path), &["window.modal=1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert_eq!(Config::load(&path), Ok(Config::default()));
+
+        fs::write(&path, "[detection]\npriority = [\"text\"]\n").unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.detection.priority, [ClipboardContentType::Text]);
+
+        fs::write(&path, "[detection]\nbogus = 1\n").unwrap();
+        let error = Config::load(&path).unwrap_err();
+        assert_eq!(
+            error,
+            format!(
+                "{}:2: unknown field `bogus`, expected `priority` or `image_types`",
+                path.display()
+            )
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use crate::raster::RgbaImage;
+use crate::{copy_to_clipboard, offered_types, ClipboardBackend};
+
+pub const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// Formats a clipboard image can be re-encoded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// JPEG at a quality from 1 to 100.
+    Jpeg {
+        quality: u8,
+    },
+    Webp,
+    Bmp,
+}
+
+impl ImageFormat {
+    /// Every format, with JPEG at the default quality.
+    pub const ALL: [ImageFormat; 4] = [
+        ImageFormat::Png,
+        ImageFormat::Jpeg {
+            quality: DEFAULT_JPEG_QUALITY,
+        },
+        ImageFormat::Webp,
+        ImageFormat::Bmp,
+    ];
+
+    /// Parses a target MIME type, accepting common aliases such as
+    /// `image/jpg`. `quality` is used for JPEG.
+    pub fn from_mime_type(mime_type: &str, quality: u8) -> Option<Self> {
+        match mime_type {
+            "image/png" => Some(ImageFormat::Png),
+            "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg {
+                quality: quality.clamp(1, 100),
+            }),
+            "image/webp" => Some(ImageFormat::Webp),
+            "image/bmp" | "image/x-bmp" => Some(ImageFormat::Bmp),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg { .. } => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Bmp => "image/bmp",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Jpeg { .. } => "JPEG",
+            ImageFormat::Webp => "WebP",
+            ImageFormat::Bmp => "BMP",
+        }
+    }
+
+    /// Whether the format keeps transparency; images are composited onto
+    /// white before encoding to formats that don't.
+    pub fn supports_alpha(self) -> bool {
+        !matches!(self, ImageFormat::Jpeg { .. } | ImageFormat::Bmp)
+    }
+}
+
+/// Decodes and encodes images. The viewer implements this with gdk-pixbuf,
+/// which keeps waypin_lib free of GTK and lets tests use a fake codec.
+pub trait ImageCodec {
+    fn decode(&self, mime_type: &str, data: &[u8]) -> Result<RgbaImage, String>;
+
+    fn encode(&self, image: &RgbaImage, format: ImageFormat) -> Result<Vec<u8>, String>;
+}
+
+/// Encodes `image` as each of `formats`, returning `(mime_type, data)`
+/// pairs in the same order.
+pub fn encode_image(
+    codec: &dyn ImageCodec,
+    image: &RgbaImage,
+    formats: &[ImageFormat],
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut opaque = None;
+    formats
+        .iter()
+        .map(|&format| {
+            let source = if format.supports_alpha() {
+                image
+            } else {
+                opaque.get_or_insert_with(|| image.over_background([255, 255, 255]))
+            };
+            let data = codec
+                .encode(source, format)
+                .map_err(|e| format!("Failed to encode {}: {}", format.label(), e))?;
+            Ok((format.mime_type().to_string(), data))
+        })
+        .collect()
+}
+
+/// Re-encodes image `data` of type `mime_type` as each of `formats`. A
+/// format matching the source type keeps the original bytes, and the image
+/// is only decoded when some format needs converting.
+pub fn transcode_image(
+    codec: &dyn ImageCodec,
+    mime_type: &str,
+    data: &[u8],
+    formats: &[ImageFormat],
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    if !mime_type.starts_with("image/") {
+        return Err("Invalid image MIME type".to_string());
+    }
+    if data.is_empty() {
+        return Err("Empty image data".to_string());
+    }
+    let source_format = ImageFormat::from_mime_type(mime_type, DEFAULT_JPEG_QUALITY);
+
+    let mut decoded = None;
+    let mut contents = Vec::with_capacity(formats.len());
+    for &format in formats {
+        if source_format.map(ImageFormat::mime_type) == Some(format.mime_type()) {
+            contents.push((format.mime_type().to_string(), data.to_vec()));
+            continue;
+        }
+        let image = match &decoded {
+            Some(image) => image,
+            None => decoded.insert(codec.decode(mime_type, data)?),
+        };
+        contents.extend(encode_image(codec, image, &[format])?);
+    }
+    Ok(contents)
+}
+
+/// Replaces the clipboard with image `data` re-encoded as each of
+/// `formats`, offered together so the pasting application can pick one.
+/// Backends that serve a single type offer only the first. Returns the MIME
+/// types actually offered.
+pub fn copy_image_as(
+    backend: &dyn ClipboardBackend,
+    codec: &dyn ImageCodec,
+    mime_type: &str,
+    data: &[u8],
+    formats: &[ImageFormat],
+) -> Result<Vec<String>, String> {
+    if formats.is_empty() {
+        return Err("No target formats given".to_string());
+    }
+    let contents = transcode_image(codec, mime_type, data, formats)?;
+    let contents: Vec<(&str, &[u8])> = contents
+        .iter()
+        .map(|(mime_type, data)| (mime_type.as_str(), data.as_slice()))
+        .collect();
+    copy_to_clipboard(backend, &contents)?;
+    Ok(offered_types(backend, &contents)
+        .into_iter()
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Encodes an image as its format label followed by its first pixel,
+    /// and counts decodes.
+    #[derive(Default)]
+    struct FakeCodec {
+        decodes: RefCell<usize>,
+    }
+
+    impl ImageCodec for FakeCodec {
+        fn decode(&self, _: &str, data: &[u8]) -> Result<RgbaImage, String> {
+            *self.decodes.borrow_mut() += 1;
+            let pixel: [u8; 4] = data.try_into().map_err(|_| "bad data".to_string())?;
+            Ok(RgbaImage::filled(1, 1, pixel))
+        }
+
+        fn encode(&self, image: &RgbaImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+            if format == ImageFormat::Webp {
+                return Err("no WebP encoder".to_string());
+            }
+            let mut data = format.label().as_bytes().to_vec();
+            data.extend_from_slice(&image.pixel(0, 0));
+            Ok(data)
+        }
+    }
+
+    #[test]
+    fn test_format_from_mime_type() {
+        assert_eq!(
+            ImageFormat::from_mime_type("image/png", 50),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            ImageFormat::from_mime_type("image/jpg", 50),
+            Some(ImageFormat::Jpeg { quality: 50 })
+        );
+        assert_eq!(
+            ImageFormat::from_mime_type("image/jpeg", 0),
+            Some(ImageFormat::Jpeg { quality: 1 })
+        );
+        assert_eq!(
+            ImageFormat::from_mime_type("image/x-bmp", 50),
+            Some(ImageFormat::Bmp)
+        );
+        assert_eq!(ImageFormat::from_mime_type("image/gif", 50), None);
+        for format in ImageFormat::ALL {
+            assert_eq!(
+                ImageFormat::from_mime_type(format.mime_type(), DEFAULT_JPEG_QUALITY),
+                Some(format)
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_flattens_alpha_for_opaque_formats() {
+        let image = RgbaImage::filled(1, 1, [0, 0, 0, 0]);
+        let contents = encode_image(
+            &FakeCodec::default(),
+            &image,
+            &[ImageFormat::Png, ImageFormat::Bmp],
+        )
+        .unwrap();
+        assert_eq!(
+            contents[0],
+            ("image/png".to_string(), b"PNG\0\0\0\0".to_vec())
+        );
+        assert_eq!(
+            contents[1],
+            ("image/bmp".to_string(), b"BMP\xff\xff\xff\xff".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_transcode_keeps_matching_source() {
+        let codec = FakeCodec::default();
+        let contents =
+            transcode_image(&codec, "image/png", &[1, 2, 3, 255], &[ImageFormat::Png]).unwrap();
+        assert_eq!(contents, [("image/png".to_string(), vec![1, 2, 3, 255])]);
+        assert_eq!(*codec.decodes.borrow(), 0);
+
+        let formats = [
+            ImageFormat::Jpeg { quality: 80 },
+            ImageFormat::Png,
+            ImageFormat::Bmp,
+        ];
+        let contents = transcode_image(&codec, "image/png", &[1, 2, 3, 255], &formats).unwrap();
+        let mime_types: Vec<_> = contents.iter().map(|(mime, _)| mime.as_str()).collect();
+        assert_eq!(mime_types, ["image/jpeg", "image/png", "image/bmp"]);
+        assert_eq!(contents[0].1, b"JPEG\x01\x02\x03\xff");
+        // Decoded once for both conversions
+        assert_eq!(*codec.decodes.borrow(), 1);
+    }
+
+    #[test]
+    fn test_transcode_errors() {
+        let codec = FakeCodec::default();
+        assert!(transcode_image(&codec, "text/plain", b"x", &[ImageFormat::Png]).is_err());
+        assert!(transcode_image(&codec, "image/png", b"", &[ImageFormat::Png]).is_err());
+        assert!(transcode_image(&codec, "image/gif", b"bad", &[ImageFormat::Png]).is_err());
+        let error =
+            transcode_image(&codec, "image/png", &[0; 4], &[ImageFormat::Webp]).unwrap_err();
+        assert_eq!(error, "Failed to encode WebP: no WebP encoder");
+    }
+}
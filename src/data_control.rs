@@ -0,0 +1,1017 @@
+//! Clipboard backend that speaks the `ext-data-control-v1` and
+//! `wlr-data-control-unstable-v1` protocols directly instead of spawning
+//! `wl-paste`/`wl-copy` for every operation.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{Read, Write};
+use std::os::fd::AsFd;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{
+    delegate_noop, event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1 as ext_device, ext_data_control_manager_v1 as ext_manager,
+    ext_data_control_offer_v1 as ext_offer, ext_data_control_source_v1 as ext_source,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1 as wlr_device, zwlr_data_control_manager_v1 as wlr_manager,
+    zwlr_data_control_offer_v1 as wlr_offer, zwlr_data_control_source_v1 as wlr_source,
+};
+
+use crate::{ClipboardBackend, Selection};
+
+/// Protocol-independent bookkeeping of data offers and the current
+/// selection, fed from data-control device and offer events.
+#[derive(Debug)]
+pub struct SelectionTracker<K> {
+    offers: HashMap<K, Vec<String>>,
+    selection: Option<K>,
+}
+
+impl<K: Eq + Hash + Clone> Default for SelectionTracker<K> {
+    fn default() -> Self {
+        Self {
+            offers: HashMap::new(),
+            selection: None,
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> SelectionTracker<K> {
+    /// A new offer was introduced by a `data_offer` event.
+    pub fn data_offer(&mut self, id: K) {
+        self.offers.insert(id, Vec::new());
+    }
+
+    /// An `offer` event advertised a MIME type on a known offer.
+    pub fn offer(&mut self, id: &K, mime_type: String) {
+        if let Some(mime_types) = self.offers.get_mut(id) {
+            mime_types.push(mime_type);
+        }
+    }
+
+    /// A `selection` event replaced the current selection. Returns the
+    /// offers that are no longer reachable and should be destroyed.
+    pub fn selection(&mut self, id: Option<K>) -> Vec<K> {
+        self.selection = id;
+        let stale: Vec<K> = self
+            .offers
+            .keys()
+            .filter(|key| self.selection.as_ref() != Some(*key))
+            .cloned()
+            .collect();
+        for key in &stale {
+            self.offers.remove(key);
+        }
+        stale
+    }
+
+    /// Forgets an offer that belongs to a selection we do not follow.
+    pub fn discard(&mut self, id: &K) {
+        self.offers.remove(id);
+    }
+
+    pub fn selected(&self) -> Option<&K> {
+        self.selection.as_ref()
+    }
+
+    pub fn mime_types(&self) -> &[String] {
+        self.selection
+            .as_ref()
+            .and_then(|id| self.offers.get(id))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Newline-separated listing in the same shape as `wl-paste --list-types`.
+    pub fn types(&self) -> String {
+        self.mime_types().join("\n")
+    }
+}
+
+/// Payloads served by a data source we own, keyed by MIME type.
+#[derive(Debug, Clone, Default)]
+pub struct SourceContents {
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+impl SourceContents {
+    pub fn new(contents: &[(&str, &[u8])]) -> Self {
+        Self {
+            entries: contents
+                .iter()
+                .map(|(mime_type, data)| (mime_type.to_string(), data.to_vec()))
+                .collect(),
+        }
+    }
+
+    pub fn mime_types(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(mime_type, _)| mime_type.as_str())
+    }
+
+    pub fn data_for(&self, mime_type: &str) -> Option<&[u8]> {
+        self.entries
+            .iter()
+            .find(|(mime, _)| mime == mime_type)
+            .map(|(_, data)| data.as_slice())
+    }
+}
+
+enum Manager {
+    Ext(ext_manager::ExtDataControlManagerV1),
+    Wlr(wlr_manager::ZwlrDataControlManagerV1),
+}
+
+enum Device {
+    Ext(ext_device::ExtDataControlDeviceV1),
+    Wlr(wlr_device::ZwlrDataControlDeviceV1),
+}
+
+enum Offer {
+    Ext(ext_offer::ExtDataControlOfferV1),
+    Wlr(wlr_offer::ZwlrDataControlOfferV1),
+}
+
+enum Source {
+    Ext(ext_source::ExtDataControlSourceV1),
+    Wlr(wlr_source::ZwlrDataControlSourceV1),
+}
+
+impl Manager {
+    fn get_data_device(&self, seat: &wl_seat::WlSeat, qh: &QueueHandle<State>) -> Device {
+        match self {
+            Manager::Ext(m) => Device::Ext(m.get_data_device(seat, qh, ())),
+            Manager::Wlr(m) => Device::Wlr(m.get_data_device(seat, qh, ())),
+        }
+    }
+
+    fn create_data_source(&self, qh: &QueueHandle<State>) -> Source {
+        match self {
+            Manager::Ext(m) => Source::Ext(m.create_data_source(qh, ())),
+            Manager::Wlr(m) => Source::Wlr(m.create_data_source(qh, ())),
+        }
+    }
+}
+
+impl Device {
+    fn supports_primary(&self) -> bool {
+        match self {
+            Device::Ext(_) => true,
+            Device::Wlr(d) => d.version() >= 2,
+        }
+    }
+
+    fn set_selection(&self, selection: Selection, source: Option<&Source>) {
+        match (self, selection) {
+            (Device::Ext(d), Selection::Clipboard) => d.set_selection(source.and_then(Source::ext)),
+            (Device::Ext(d), Selection::Primary) => {
+                d.set_primary_selection(source.and_then(Source::ext))
+            }
+            (Device::Wlr(d), Selection::Clipboard) => d.set_selection(source.and_then(Source::wlr)),
+            (Device::Wlr(d), Selection::Primary) => {
+                d.set_primary_selection(source.and_then(Source::wlr))
+            }
+        }
+    }
+}
+
+impl Offer {
+    fn id(&self) -> ObjectId {
+        match self {
+            Offer::Ext(o) => o.id(),
+            Offer::Wlr(o) => o.id(),
+        }
+    }
+
+    fn receive(&self, mime_type: &str, fd: std::os::fd::BorrowedFd) {
+        match self {
+            Offer::Ext(o) => o.receive(mime_type.to_string(), fd),
+            Offer::Wlr(o) => o.receive(mime_type.to_string(), fd),
+        }
+    }
+
+    fn destroy(&self) {
+        match self {
+            Offer::Ext(o) => o.destroy(),
+            Offer::Wlr(o) => o.destroy(),
+        }
+    }
+}
+
+impl Source {
+    fn ext(&self) -> Option<&ext_source::ExtDataControlSourceV1> {
+        match self {
+            Source::Ext(s) => Some(s),
+            Source::Wlr(_) => None,
+        }
+    }
+
+    fn wlr(&self) -> Option<&wlr_source::ZwlrDataControlSourceV1> {
+        match self {
+            Source::Wlr(s) => Some(s),
+            Source::Ext(_) => None,
+        }
+    }
+
+    fn offer(&self, mime_type: &str) {
+        match self {
+            Source::Ext(s) => s.offer(mime_type.to_string()),
+            Source::Wlr(s) => s.offer(mime_type.to_string()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    selection: Selection,
+    tracker: SelectionTracker<ObjectId>,
+    offers: HashMap<ObjectId, Offer>,
+    contents: SourceContents,
+    cancelled: bool,
+    finished: bool,
+}
+
+impl State {
+    fn data_offer(&mut self, offer: Offer) {
+        self.tracker.data_offer(offer.id());
+        self.offers.insert(offer.id(), offer);
+    }
+
+    fn selection(&mut self, selection: Selection, id: Option<ObjectId>) {
+        if selection != self.selection {
+            if let Some(offer) = id.and_then(|id| self.offers.remove(&id)) {
+                self.tracker.discard(&offer.id());
+                offer.destroy();
+            }
+            return;
+        }
+        for stale in self.tracker.selection(id) {
+            if let Some(offer) = self.offers.remove(&stale) {
+                offer.destroy();
+            }
+        }
+    }
+
+    /// Whether a source we own still holds the selection.
+    fn is_serving(&self) -> bool {
+        !self.cancelled && !self.finished
+    }
+
+    fn send(&self, mime_type: &str, fd: std::os::fd::OwnedFd) {
+        if let Some(data) = self.contents.data_for(mime_type) {
+            let _ = File::from(fd).write_all(data);
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(State: ignore wl_seat::WlSeat);
+
+macro_rules! dispatch_data_control {
+    (
+        $variant:ident,
+        $manager:ident :: $manager_ty:ident,
+        $device:ident :: $device_ty:ident,
+        $offer:ident :: $offer_ty:ident,
+        $source:ident :: $source_ty:ident
+    ) => {
+        delegate_noop!(State: $manager::$manager_ty);
+
+        impl Dispatch<$device::$device_ty, ()> for State {
+            fn event(
+                state: &mut Self,
+                _: &$device::$device_ty,
+                event: $device::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                match event {
+                    $device::Event::DataOffer { id } => state.data_offer(Offer::$variant(id)),
+                    $device::Event::Selection { id } => {
+                        state.selection(Selection::Clipboard, id.map(|o| o.id()))
+                    }
+                    $device::Event::PrimarySelection { id } => {
+                        state.selection(Selection::Primary, id.map(|o| o.id()))
+                    }
+                    $device::Event::Finished => state.finished = true,
+                    _ => {}
+                }
+            }
+
+            event_created_child!(State, $device::$device_ty, [
+                $device::EVT_DATA_OFFER_OPCODE => ($offer::$offer_ty, ()),
+            ]);
+        }
+
+        impl Dispatch<$offer::$offer_ty, ()> for State {
+            fn event(
+                state: &mut Self,
+                offer: &$offer::$offer_ty,
+                event: $offer::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                if let $offer::Event::Offer { mime_type } = event {
+                    state.tracker.offer(&offer.id(), mime_type);
+                }
+            }
+        }
+
+        impl Dispatch<$source::$source_ty, ()> for State {
+            fn event(
+                state: &mut Self,
+                source: &$source::$source_ty,
+                event: $source::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                match event {
+                    $source::Event::Send { mime_type, fd } => state.send(&mime_type, fd),
+                    $source::Event::Cancelled => {
+                        state.cancelled = true;
+                        source.destroy();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    };
+}
+
+dispatch_data_control!(
+    Ext,
+    ext_manager::ExtDataControlManagerV1,
+    ext_device::ExtDataControlDeviceV1,
+    ext_offer::ExtDataControlOfferV1,
+    ext_source::ExtDataControlSourceV1
+);
+
+dispatch_data_control!(
+    Wlr,
+    wlr_manager::ZwlrDataControlManagerV1,
+    wlr_device::ZwlrDataControlDeviceV1,
+    wlr_offer::ZwlrDataControlOfferV1,
+    wlr_source::ZwlrDataControlSourceV1
+);
+
+/// One connection to the compositor with a data-control device bound to
+/// the first seat, following a single selection.
+struct Session {
+    conn: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    manager: Manager,
+    device: Device,
+}
+
+impl Session {
+    /// Connects to the compositor at `socket`, or the one named by
+    /// `WAYLAND_DISPLAY` when it is `None`.
+    fn connect(selection: Selection, socket: Option<&Path>) -> Result<Self, String> {
+        let conn = match socket {
+            Some(socket) => UnixStream::connect(socket)
+                .map_err(|e| e.to_string())
+                .and_then(|stream| Connection::from_socket(stream).map_err(|e| e.to_string())),
+            None => Connection::connect_to_env().map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("Failed to connect to Wayland: {}", e))?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)
+            .map_err(|e| format!("Failed to list Wayland globals: {}", e))?;
+        let qh = queue.handle();
+
+        let seat: wl_seat::WlSeat = globals
+            .bind(&qh, 1..=1, ())
+            .map_err(|_| "Compositor has no wl_seat".to_string())?;
+        let manager = if let Ok(manager) = globals.bind(&qh, 1..=1, ()) {
+            Manager::Ext(manager)
+        } else if let Ok(manager) = globals.bind(&qh, 1..=2, ()) {
+            Manager::Wlr(manager)
+        } else {
+            return Err("Compositor supports neither ext-data-control nor wlr-data-control".into());
+        };
+        let device = manager.get_data_device(&seat, &qh);
+        if selection == Selection::Primary && !device.supports_primary() {
+            return Err("Compositor's data-control does not support the primary selection".into());
+        }
+
+        let mut state = State {
+            selection,
+            ..State::default()
+        };
+        queue
+            .roundtrip(&mut state)
+            .map_err(|e| format!("Wayland roundtrip failed: {}", e))?;
+
+        Ok(Self {
+            conn,
+            queue,
+            state,
+            manager,
+            device,
+        })
+    }
+
+    fn roundtrip(&mut self) -> Result<(), String> {
+        self.queue
+            .roundtrip(&mut self.state)
+            .map(|_| ())
+            .map_err(|e| format!("Wayland roundtrip failed: {}", e))
+    }
+
+    /// Takes ownership of the selection and serves `contents` until another
+    /// client replaces it. `ready` is told once the selection is set.
+    fn serve(mut self, contents: SourceContents, ready: mpsc::Sender<Result<(), String>>) {
+        let source = self.manager.create_data_source(&self.queue.handle());
+        for mime_type in contents.mime_types() {
+            source.offer(mime_type);
+        }
+        self.state.contents = contents;
+        self.device
+            .set_selection(self.state.selection, Some(&source));
+
+        let result = self.roundtrip();
+        let failed = result.is_err();
+        let _ = ready.send(result);
+        if failed {
+            return;
+        }
+
+        while self.state.is_serving() {
+            if self.queue.blocking_dispatch(&mut self.state).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Backend talking to the compositor's data-control protocol.
+///
+/// Compositors only serve a selection while its owner is alive, so every
+/// `write` hands the data to a thread that keeps serving it until another
+/// client takes over the selection. Those threads end with the process:
+/// dropping the backend leaves them running, and
+/// [`wait_for_copies`](ClipboardBackend::wait_for_copies) keeps the process
+/// alive until they are done, the same way `wl-copy` lingers in the
+/// background.
+pub struct DataControlClipboard {
+    selection: Selection,
+    socket: Option<PathBuf>,
+    session: RefCell<Session>,
+    servers: RefCell<Vec<JoinHandle<()>>>,
+}
+
+impl DataControlClipboard {
+    pub fn connect(selection: Selection) -> Result<Self, String> {
+        Self::connect_to(None, selection)
+    }
+
+    /// Like [`connect`](Self::connect), but to the compositor listening on
+    /// `socket` when it is given.
+    fn connect_to(socket: Option<PathBuf>, selection: Selection) -> Result<Self, String> {
+        Ok(Self {
+            selection,
+            session: RefCell::new(Session::connect(selection, socket.as_deref())?),
+            socket,
+            servers: RefCell::new(Vec::new()),
+        })
+    }
+}
+
+impl ClipboardBackend for DataControlClipboard {
+    fn list_types(&self) -> Result<String, String> {
+        let mut session = self.session.borrow_mut();
+        session.roundtrip()?;
+        Ok(session.state.tracker.types())
+    }
+
+    fn read(&self, mime_type: &str) -> Result<Vec<u8>, String> {
+        let mut session = self.session.borrow_mut();
+        session.roundtrip()?;
+
+        let state = &session.state;
+        let id = state
+            .tracker
+            .selected()
+            .ok_or_else(|| "Clipboard is empty".to_string())?;
+        if !state.tracker.mime_types().iter().any(|m| m == mime_type) {
+            return Err(format!("Clipboard does not offer {}", mime_type));
+        }
+        let offer = state
+            .offers
+            .get(id)
+            .ok_or_else(|| "Selection offer is gone".to_string())?;
+
+        let (mut reader, writer) =
+            UnixStream::pair().map_err(|e| format!("Failed to create pipe: {}", e))?;
+        offer.receive(mime_type, writer.as_fd());
+        session
+            .conn
+            .flush()
+            .map_err(|e| format!("Failed to flush Wayland connection: {}", e))?;
+        drop(writer);
+
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read {}: {}", mime_type, e))?;
+        Ok(data)
+    }
+
+    fn write(&self, contents: &[(&str, &[u8])]) -> Result<(), String> {
+        if contents.is_empty() {
+            return Err("Nothing to copy".to_string());
+        }
+
+        // Servers whose copy has been replaced are done
+        self.servers
+            .borrow_mut()
+            .retain(|handle| !handle.is_finished());
+
+        let contents = SourceContents::new(contents);
+        let selection = self.selection;
+        let socket = self.socket.clone();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let session = Session::connect(selection, socket.as_deref());
+            match session {
+                Ok(session) => session.serve(contents, ready_tx),
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                }
+            }
+        });
+        self.servers.borrow_mut().push(handle);
+
+        ready_rx
+            .recv()
+            .map_err(|_| "Clipboard source thread exited".to_string())?
+    }
+
+    fn offers_multiple_types(&self) -> bool {
+        true
+    }
+
+    fn wait_for_copies(&self) {
+        let servers = std::mem::take(&mut *self.servers.borrow_mut());
+        for handle in servers {
+            let _ = handle.join();
+        }
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        let mut session = self.session.borrow_mut();
+        session.device.set_selection(self.selection, None);
+        session.roundtrip()
+    }
+
+    fn watch(&self, on_change: &mut dyn FnMut() -> bool) -> Result<(), String> {
+        let mut last_seen: Option<Option<ObjectId>> = None;
+        loop {
+            let current = self.session.borrow().state.tracker.selected().cloned();
+            if last_seen.as_ref() != Some(&current) {
+                last_seen = Some(current);
+                // The session is not borrowed here, so `on_change` may read
+                // the clipboard through this backend.
+                if !on_change() {
+                    return Ok(());
+                }
+            }
+
+            let session = &mut *self.session.borrow_mut();
+            if session.state.finished {
+                return Err("Data-control device was destroyed".to_string());
+            }
+            session
+                .queue
+                .blocking_dispatch(&mut session.state)
+                .map_err(|e| format!("Wayland dispatch failed: {}", e))?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_empty() {
+        let tracker: SelectionTracker<u32> = SelectionTracker::default();
+        assert!(tracker.selected().is_none());
+        assert!(tracker.mime_types().is_empty());
+        assert_eq!(tracker.types(), "");
+    }
+
+    #[test]
+    fn test_tracker_collects_selection_types() {
+        let mut tracker = SelectionTracker::default();
+        tracker.data_offer(1u32);
+        tracker.offer(&1, "image/png".to_string());
+        tracker.offer(&1, "text/plain".to_string());
+        assert!(tracker.selection(Some(1)).is_empty());
+
+        assert_eq!(tracker.selected(), Some(&1));
+        assert_eq!(tracker.types(), "image/png\ntext/plain");
+    }
+
+    #[test]
+    fn test_tracker_ignores_unknown_offers() {
+        let mut tracker = SelectionTracker::default();
+        tracker.offer(&7u32, "text/plain".to_string());
+        tracker.selection(Some(7));
+        assert!(tracker.mime_types().is_empty());
+    }
+
+    #[test]
+    fn test_tracker_replaces_selection() {
+        let mut tracker = SelectionTracker::default();
+        tracker.data_offer(1u32);
+        tracker.offer(&1, "text/plain".to_string());
+        tracker.selection(Some(1));
+
+        tracker.data_offer(2);
+        tracker.offer(&2, "image/png".to_string());
+        assert_eq!(tracker.selection(Some(2)), vec![1]);
+        assert_eq!(tracker.types(), "image/png");
+    }
+
+    #[test]
+    fn test_tracker_cleared_selection() {
+        let mut tracker = SelectionTracker::default();
+        tracker.data_offer(1u32);
+        tracker.selection(Some(1));
+
+        assert_eq!(tracker.selection(None), vec![1]);
+        assert!(tracker.selected().is_none());
+        assert_eq!(tracker.types(), "");
+    }
+
+    #[test]
+    fn test_tracker_discard_other_selection() {
+        let mut tracker = SelectionTracker::default();
+        tracker.data_offer(1u32);
+        tracker.offer(&1, "text/plain".to_string());
+        tracker.discard(&1);
+        tracker.selection(Some(1));
+        assert!(tracker.mime_types().is_empty());
+    }
+
+    #[test]
+    fn test_source_contents_lookup() {
+        let contents = SourceContents::new(&[("text/html", b"<b>hi</b>"), ("text/plain", b"hi")]);
+        assert_eq!(
+            contents.mime_types().collect::<Vec<_>>(),
+            vec!["text/html", "text/plain"]
+        );
+        assert_eq!(contents.data_for("text/plain"), Some(&b"hi"[..]));
+        assert_eq!(contents.data_for("image/png"), None);
+    }
+
+    #[test]
+    fn test_serve_sends_requested_type() {
+        let mut state = State {
+            contents: SourceContents::new(&[("text/html", b"<b>hi</b>"), ("text/plain", b"hi")]),
+            ..State::default()
+        };
+        assert!(state.is_serving());
+
+        let (mut reader, writer) = UnixStream::pair().unwrap();
+        state.send("text/plain", writer.into());
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"hi");
+
+        // A type we never offered closes the pipe without data
+        let (mut reader, writer) = UnixStream::pair().unwrap();
+        state.send("image/png", writer.into());
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert!(data.is_empty());
+
+        state.cancelled = true;
+        assert!(!state.is_serving());
+    }
+
+    /// Just enough of a compositor to pass one `ext-data-control-v1`
+    /// selection between clients: a seat, the manager, and offers that
+    /// forward `receive` to the owning source.
+    mod compositor {
+        use std::collections::HashMap;
+        use std::ffi::CString;
+        use std::os::fd::{AsRawFd, OwnedFd};
+        use std::os::unix::net::UnixListener;
+        use std::path::Path;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use std::thread::JoinHandle;
+        use std::time::Duration;
+
+        use wayland_backend::protocol::{Argument, Message};
+        use wayland_backend::server::{
+            Backend, ClientData, ClientId, GlobalHandler, GlobalId, Handle, ObjectData, ObjectId,
+        };
+        use wayland_client::protocol::wl_seat;
+        use wayland_client::Proxy;
+
+        use super::{ext_device, ext_manager, ext_offer, ext_source};
+
+        #[derive(Default)]
+        struct State {
+            /// MIME types offered by each data source.
+            sources: HashMap<ObjectId, Vec<String>>,
+            /// The source each offer was made for.
+            offers: HashMap<ObjectId, ObjectId>,
+            /// Data devices, with the selection generation they last saw.
+            devices: Vec<(ClientId, ObjectId, Option<u32>)>,
+            selection: Option<ObjectId>,
+            generation: u32,
+        }
+
+        impl State {
+            fn set_selection(&mut self, handle: &Handle, source: Option<ObjectId>) {
+                if let Some(old) = self.selection.take() {
+                    if Some(&old) != source.as_ref() {
+                        send(handle, old, ext_source::EVT_CANCELLED_OPCODE, vec![]);
+                    }
+                }
+                self.selection = source;
+                self.generation += 1;
+            }
+
+            /// Tells every device that has not seen it about the selection.
+            fn announce(&mut self, handle: &Handle) {
+                for (client, device, seen) in &mut self.devices {
+                    if *seen == Some(self.generation) || handle.object_info(device.clone()).is_err()
+                    {
+                        continue;
+                    }
+                    *seen = Some(self.generation);
+                    let Some(source) = &self.selection else {
+                        send(
+                            handle,
+                            device.clone(),
+                            ext_device::EVT_SELECTION_OPCODE,
+                            vec![Argument::Object(ObjectId::null())],
+                        );
+                        continue;
+                    };
+                    let offer = handle
+                        .create_object::<State>(
+                            client.clone(),
+                            ext_offer::ExtDataControlOfferV1::interface(),
+                            1,
+                            Arc::new(Handler),
+                        )
+                        .unwrap();
+                    send(
+                        handle,
+                        device.clone(),
+                        ext_device::EVT_DATA_OFFER_OPCODE,
+                        vec![Argument::NewId(offer.clone())],
+                    );
+                    for mime_type in &self.sources[source] {
+                        send(
+                            handle,
+                            offer.clone(),
+                            ext_offer::EVT_OFFER_OPCODE,
+                            vec![string(mime_type)],
+                        );
+                    }
+                    send(
+                        handle,
+                        device.clone(),
+                        ext_device::EVT_SELECTION_OPCODE,
+                        vec![Argument::Object(offer.clone())],
+                    );
+                    self.offers.insert(offer, source.clone());
+                }
+            }
+        }
+
+        fn send(
+            handle: &Handle,
+            sender_id: ObjectId,
+            opcode: u16,
+            args: Vec<Argument<ObjectId, i32>>,
+        ) {
+            let _ = handle.send_event(Message {
+                sender_id,
+                opcode,
+                args: args.into(),
+            });
+        }
+
+        fn string(text: &str) -> Argument<ObjectId, i32> {
+            Argument::Str(Some(Box::new(CString::new(text).unwrap())))
+        }
+
+        fn text(argument: Option<Argument<ObjectId, OwnedFd>>) -> String {
+            match argument {
+                Some(Argument::Str(Some(text))) => text.to_string_lossy().into_owned(),
+                other => panic!("expected a string, got {:?}", other),
+            }
+        }
+
+        /// Handles every global and object; requests are told apart by
+        /// interface and opcode.
+        struct Handler;
+
+        struct Client;
+
+        impl ClientData for Client {}
+
+        impl GlobalHandler<State> for Handler {
+            fn bind(
+                self: Arc<Self>,
+                _: &Handle,
+                _: &mut State,
+                _: ClientId,
+                _: GlobalId,
+                _: ObjectId,
+            ) -> Arc<dyn ObjectData<State>> {
+                self
+            }
+        }
+
+        impl ObjectData<State> for Handler {
+            fn request(
+                self: Arc<Self>,
+                handle: &Handle,
+                state: &mut State,
+                client: ClientId,
+                msg: Message<ObjectId, OwnedFd>,
+            ) -> Option<Arc<dyn ObjectData<State>>> {
+                let object = msg.sender_id;
+                let mut args = msg.args.into_iter();
+                match (object.interface().name, msg.opcode) {
+                    ("ext_data_control_manager_v1", ext_manager::REQ_CREATE_DATA_SOURCE_OPCODE) => {
+                        let Some(Argument::NewId(source)) = args.next() else {
+                            return None;
+                        };
+                        state.sources.insert(source, Vec::new());
+                        Some(self)
+                    }
+                    ("ext_data_control_manager_v1", ext_manager::REQ_GET_DATA_DEVICE_OPCODE) => {
+                        let Some(Argument::NewId(device)) = args.next() else {
+                            return None;
+                        };
+                        state.devices.push((client, device, None));
+                        Some(self)
+                    }
+                    ("ext_data_control_device_v1", ext_device::REQ_SET_SELECTION_OPCODE) => {
+                        if let Some(Argument::Object(source)) = args.next() {
+                            let source = (!source.is_null()).then_some(source);
+                            state.set_selection(handle, source);
+                        }
+                        None
+                    }
+                    ("ext_data_control_source_v1", ext_source::REQ_OFFER_OPCODE) => {
+                        let mime_type = text(args.next());
+                        if let Some(mime_types) = state.sources.get_mut(&object) {
+                            mime_types.push(mime_type);
+                        }
+                        None
+                    }
+                    ("ext_data_control_source_v1", ext_source::REQ_DESTROY_OPCODE) => {
+                        state.sources.remove(&object);
+                        if state.selection.as_ref() == Some(&object) {
+                            state.set_selection(handle, None);
+                        }
+                        None
+                    }
+                    ("ext_data_control_offer_v1", ext_offer::REQ_RECEIVE_OPCODE) => {
+                        let mime_type = text(args.next());
+                        let Some(Argument::Fd(fd)) = args.next() else {
+                            return None;
+                        };
+                        if let Some(source) = state.offers.get(&object) {
+                            // The event holds a duplicate, so `fd` closes here
+                            send(
+                                handle,
+                                source.clone(),
+                                ext_source::EVT_SEND_OPCODE,
+                                vec![string(&mime_type), Argument::Fd(fd.as_raw_fd())],
+                            );
+                        }
+                        None
+                    }
+                    _ => None,
+                }
+            }
+
+            fn destroyed(self: Arc<Self>, _: &Handle, _: &mut State, _: ClientId, _: ObjectId) {}
+        }
+
+        /// A compositor serving clients on `socket` until dropped.
+        pub struct Compositor {
+            stop: Arc<AtomicBool>,
+            thread: Option<JoinHandle<()>>,
+        }
+
+        impl Compositor {
+            pub fn start(socket: &Path) -> Self {
+                let listener = UnixListener::bind(socket).unwrap();
+                listener.set_nonblocking(true).unwrap();
+                let stop = Arc::new(AtomicBool::new(false));
+                let stopped = stop.clone();
+                let thread = std::thread::spawn(move || {
+                    let mut backend = Backend::<State>::new().unwrap();
+                    let mut handle = backend.handle();
+                    handle.create_global::<State>(
+                        wl_seat::WlSeat::interface(),
+                        1,
+                        Arc::new(Handler),
+                    );
+                    handle.create_global::<State>(
+                        ext_manager::ExtDataControlManagerV1::interface(),
+                        1,
+                        Arc::new(Handler),
+                    );
+                    let mut state = State::default();
+                    while !stopped.load(Ordering::Relaxed) {
+                        if let Ok((stream, _)) = listener.accept() {
+                            stream.set_nonblocking(true).unwrap();
+                            handle.insert_client(stream, Arc::new(Client)).unwrap();
+                        }
+                        let _ = backend.dispatch_all_clients(&mut state);
+                        state.announce(&handle);
+                        let _ = backend.flush(None);
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                });
+                Self {
+                    stop,
+                    thread: Some(thread),
+                }
+            }
+        }
+
+        impl Drop for Compositor {
+            fn drop(&mut self) {
+                self.stop.store(true, Ordering::Relaxed);
+                if let Some(thread) = self.thread.take() {
+                    let _ = thread.join();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_clipboard_over_fake_compositor() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("wayland-test");
+        let _compositor = compositor::Compositor::start(&socket);
+
+        let clipboard =
+            DataControlClipboard::connect_to(Some(socket.clone()), Selection::Clipboard).unwrap();
+        assert_eq!(clipboard.list_types().unwrap(), "");
+        assert_eq!(
+            clipboard.read("text/plain").unwrap_err(),
+            "Clipboard is empty"
+        );
+
+        clipboard
+            .write(&[("text/html", b"<b>hi</b>"), ("text/plain", b"hi")])
+            .unwrap();
+        assert_eq!(clipboard.list_types().unwrap(), "text/html\ntext/plain");
+        assert_eq!(clipboard.read("text/plain").unwrap(), b"hi");
+        assert_eq!(clipboard.read("text/html").unwrap(), b"<b>hi</b>");
+        assert_eq!(
+            clipboard.read("image/png").unwrap_err(),
+            "Clipboard does not offer image/png"
+        );
+
+        // A second copy replaces the first, whose server then stops
+        clipboard.write(&[("text/plain", b"bye")]).unwrap();
+        assert_eq!(clipboard.list_types().unwrap(), "text/plain");
+        assert_eq!(clipboard.read("text/plain").unwrap(), b"bye");
+
+        clipboard.clear().unwrap();
+        assert_eq!(clipboard.list_types().unwrap(), "");
+        // Every server has been cancelled, so this returns
+        clipboard.wait_for_copies();
+    }
+}
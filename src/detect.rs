@@ -0,0 +1,387 @@
+use crate::cli::ViewerKind;
+use crate::rich_text::{is_rtf_mime_type, RTF_MIME_TYPES};
+use crate::{
+    get_raw_format_from_types, is_image_mime_type, ClipboardContentType, IMAGE_MIME_TYPES,
+};
+
+/// Text types in the order waypin prefers them.
+pub const TEXT_MIME_TYPES: [&str; 5] = [
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+    "TEXT",
+];
+
+/// Content types in the order they win by default when several are offered.
+pub const DEFAULT_PRIORITY: [ClipboardContentType; 6] = [
+    ClipboardContentType::File,
+    ClipboardContentType::Image,
+    ClipboardContentType::Html,
+    ClipboardContentType::Rtf,
+    ClipboardContentType::Color,
+    ClipboardContentType::Text,
+];
+
+/// How a type listing is turned into a viewer: which content type wins,
+/// which image type is read, and which extra MIME types map to a viewer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionRules {
+    /// Content types in the order they win. Types left out follow in their
+    /// default order; raw data always comes last.
+    pub priority: Vec<ClipboardContentType>,
+    /// Image types to prefer, before the default [`IMAGE_MIME_TYPES`] order.
+    pub image_types: Vec<String>,
+    /// Image types the image loader can decode besides
+    /// [`IMAGE_MIME_TYPES`]; other `image/*` types are not shown as images.
+    pub decodable_image_types: Vec<String>,
+    /// MIME types shown with a given viewer, overriding the built-in
+    /// families (e.g. `application/json` as text).
+    pub mime_types: Vec<(String, ClipboardContentType)>,
+}
+
+impl Default for DetectionRules {
+    fn default() -> Self {
+        Self {
+            priority: DEFAULT_PRIORITY.to_vec(),
+            image_types: Vec::new(),
+            decodable_image_types: Vec::new(),
+            mime_types: Vec::new(),
+        }
+    }
+}
+
+impl DetectionRules {
+    /// The viewer `mime_type` is mapped to, if any.
+    pub fn mapped_viewer(&self, mime_type: &str) -> Option<ClipboardContentType> {
+        self.mime_types
+            .iter()
+            .find(|(mapped, _)| mapped == mime_type)
+            .map(|(_, content_type)| content_type.clone())
+    }
+
+    /// The content type an explicitly requested MIME type is shown as.
+    pub fn viewer_for_mime_type(&self, mime_type: &str) -> ClipboardContentType {
+        if let Some(content_type) = self.mapped_viewer(mime_type) {
+            return content_type;
+        }
+        if mime_type.starts_with("image/") {
+            ClipboardContentType::Image
+        } else if mime_type == "text/html" {
+            ClipboardContentType::Html
+        } else if is_rtf_mime_type(mime_type) {
+            ClipboardContentType::Rtf
+        } else {
+            ClipboardContentType::Text
+        }
+    }
+
+    /// The MIME type read to show the listing as `content_type`: the
+    /// built-in types of that family that aren't mapped elsewhere, then the
+    /// types mapped to it.
+    pub fn mime_type_for<'a>(
+        &self,
+        types: &'a str,
+        content_type: &ClipboardContentType,
+    ) -> Option<&'a str> {
+        if *content_type == ClipboardContentType::Unsupported {
+            return get_raw_format_from_types(types);
+        }
+        let builtin = types.lines().filter(|mime_type| {
+            self.mapped_viewer(mime_type).is_none()
+                && self.builtin_content_type(mime_type).as_ref() == Some(content_type)
+        });
+        let preferred: Vec<&str> = match content_type {
+            ClipboardContentType::Image => self
+                .image_types
+                .iter()
+                .map(String::as_str)
+                .chain(IMAGE_MIME_TYPES)
+                .collect(),
+            ClipboardContentType::Text => TEXT_MIME_TYPES.to_vec(),
+            ClipboardContentType::Rtf => RTF_MIME_TYPES.to_vec(),
+            _ => Vec::new(),
+        };
+        let builtin: Vec<&str> = builtin.collect();
+        let best_builtin = preferred
+            .iter()
+            .find_map(|mime_type| builtin.iter().find(|offered| *offered == mime_type))
+            .or_else(|| builtin.first())
+            .copied();
+
+        best_builtin.or_else(|| {
+            self.mime_types
+                .iter()
+                .filter(|(_, mapped)| mapped == content_type)
+                .find_map(|(mime_type, _)| types.lines().find(|offered| offered == mime_type))
+        })
+    }
+
+    /// Every way the listing can be shown, in priority order, with raw data
+    /// for the first offered type last.
+    pub fn rank(&self, types: &str) -> Vec<ClipboardContentType> {
+        let mut order = self.priority.clone();
+        for content_type in DEFAULT_PRIORITY {
+            if !order.contains(&content_type) {
+                order.push(content_type);
+            }
+        }
+        order.retain(|content_type| *content_type != ClipboardContentType::Unsupported);
+        order.push(ClipboardContentType::Unsupported);
+
+        order
+            .into_iter()
+            .filter(|content_type| self.mime_type_for(types, content_type).is_some())
+            .collect()
+    }
+
+    /// The content type that wins for the listing.
+    pub fn detect(&self, types: &str) -> ClipboardContentType {
+        self.rank(types)
+            .into_iter()
+            .next()
+            .unwrap_or(ClipboardContentType::Unsupported)
+    }
+
+    /// Picks the content type and the MIME type to read for a type listing.
+    ///
+    /// An explicit MIME type selects the viewer it is mapped to, or the one
+    /// for its family (`image/*`, HTML, RTF or text), unless a viewer is
+    /// forced as well.
+    pub fn choose_payload(
+        &self,
+        types: &str,
+        mime_type: Option<&str>,
+        viewer: Option<ViewerKind>,
+    ) -> (ClipboardContentType, Option<String>) {
+        let content_type = match (viewer, mime_type) {
+            (Some(ViewerKind::Image), _) => ClipboardContentType::Image,
+            (Some(ViewerKind::Text), _) => ClipboardContentType::Text,
+            (None, Some(mime)) => self.viewer_for_mime_type(mime),
+            (None, None) => self.detect(types),
+        };
+
+        let detected_mime = self.mime_type_for(types, &content_type);
+        (
+            content_type,
+            mime_type.or(detected_mime).map(str::to_string),
+        )
+    }
+
+    /// Whether `mime_type` is an image type known to decode: a built-in
+    /// one, a preferred one or one the image loader reported.
+    pub fn is_image_type(&self, mime_type: &str) -> bool {
+        is_image_mime_type(mime_type)
+            || self
+                .image_types
+                .iter()
+                .chain(&self.decodable_image_types)
+                .any(|image_type| image_type == mime_type)
+    }
+
+    /// The content type of a MIME type's built-in family. Only the known
+    /// text types count as text here, and nothing is a color without a
+    /// mapping.
+    fn builtin_content_type(&self, mime_type: &str) -> Option<ClipboardContentType> {
+        if mime_type == "text/uri-list" {
+            Some(ClipboardContentType::File)
+        } else if self.is_image_type(mime_type) {
+            Some(ClipboardContentType::Image)
+        } else if mime_type == "text/html" {
+            Some(ClipboardContentType::Html)
+        } else if is_rtf_mime_type(mime_type) {
+            Some(ClipboardContentType::Rtf)
+        } else if TEXT_MIME_TYPES.contains(&mime_type) {
+            Some(ClipboardContentType::Text)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parses a content type name as used in the config file.
+pub fn parse_content_type_name(name: &str) -> Result<ClipboardContentType, String> {
+    match name {
+        "text" => Ok(ClipboardContentType::Text),
+        "html" => Ok(ClipboardContentType::Html),
+        "rtf" => Ok(ClipboardContentType::Rtf),
+        "image" => Ok(ClipboardContentType::Image),
+        "files" => Ok(ClipboardContentType::File),
+        "color" | "swatch" => Ok(ClipboardContentType::Color),
+        "raw" => Ok(ClipboardContentType::Unsupported),
+        _ => Err(format!(
+            "unknown viewer \"{}\" (expected text, html, rtf, image, files, color or raw)",
+            name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(
+        priority: &[ClipboardContentType],
+        image_types: &[&str],
+        mime_types: &[(&str, ClipboardContentType)],
+    ) -> DetectionRules {
+        DetectionRules {
+            priority: priority.to_vec(),
+            image_types: image_types.iter().map(|s| s.to_string()).collect(),
+            decodable_image_types: Vec::new(),
+            mime_types: mime_types
+                .iter()
+                .map(|(mime, content_type)| (mime.to_string(), content_type.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_default_rules_match_builtin_detection() {
+        use ClipboardContentType::*;
+        let rules = DetectionRules::default();
+        for (types, expected) in [
+            (
+                "text/plain\nimage/png\ntext/html",
+                &[Image, Html, Text, Unsupported][..],
+            ),
+            ("text/uri-list\ntext/plain", &[File, Text, Unsupported]),
+            ("image/jpeg\nimage/png", &[Image, Unsupported]),
+            ("text/plain\napplication/rtf", &[Rtf, Text, Unsupported]),
+            ("UTF8_STRING\nTARGETS", &[Text, Unsupported]),
+            ("application/pdf", &[Unsupported]),
+            ("TARGETS", &[]),
+        ] {
+            assert_eq!(rules.rank(types), expected, "{}", types);
+        }
+        assert_eq!(
+            rules.mime_type_for("image/jpeg\nimage/png", &ClipboardContentType::Image),
+            Some("image/png")
+        );
+    }
+
+    #[test]
+    fn test_decodable_image_types() {
+        let types = "text/plain\nimage/x-portable-pixmap";
+        let mut rules = DetectionRules::default();
+        assert_eq!(rules.detect(types), ClipboardContentType::Text);
+        assert_eq!(
+            rules.mime_type_for(types, &ClipboardContentType::Image),
+            None
+        );
+
+        rules.decodable_image_types = vec!["image/x-portable-pixmap".to_string()];
+        assert_eq!(rules.detect(types), ClipboardContentType::Image);
+        assert_eq!(
+            rules.mime_type_for(types, &ClipboardContentType::Image),
+            Some("image/x-portable-pixmap")
+        );
+        // Known formats still win
+        assert_eq!(
+            rules.mime_type_for(
+                "image/x-portable-pixmap\nimage/gif",
+                &ClipboardContentType::Image
+            ),
+            Some("image/gif")
+        );
+    }
+
+    #[test]
+    fn test_priority() {
+        let text_first = rules(&[ClipboardContentType::Text], &[], &[]);
+        assert_eq!(
+            text_first.rank("image/png\ntext/plain\ntext/html"),
+            [
+                ClipboardContentType::Text,
+                ClipboardContentType::Image,
+                ClipboardContentType::Html,
+                ClipboardContentType::Unsupported,
+            ]
+        );
+        // Raw data stays last even when listed first
+        let raw_first = rules(
+            &[
+                ClipboardContentType::Unsupported,
+                ClipboardContentType::Image,
+            ],
+            &[],
+            &[],
+        );
+        assert_eq!(
+            raw_first.detect("text/plain\nimage/png"),
+            ClipboardContentType::Image
+        );
+    }
+
+    #[test]
+    fn test_image_types() {
+        let jpeg_first = rules(&[], &["image/jpeg"], &[]);
+        assert_eq!(
+            jpeg_first.mime_type_for("image/png\nimage/jpeg", &ClipboardContentType::Image),
+            Some("image/jpeg")
+        );
+        assert_eq!(
+            jpeg_first.mime_type_for("image/png\nimage/gif", &ClipboardContentType::Image),
+            Some("image/png")
+        );
+    }
+
+    #[test]
+    fn test_mime_type_mapping() {
+        let mapped = rules(
+            &[],
+            &[],
+            &[
+                ("application/json", ClipboardContentType::Text),
+                ("application/x-color", ClipboardContentType::Color),
+                ("text/html", ClipboardContentType::Text),
+            ],
+        );
+        assert_eq!(
+            mapped.choose_payload("application/json", None, None),
+            (
+                ClipboardContentType::Text,
+                Some("application/json".to_string())
+            )
+        );
+        // Built-in text types still come before mapped ones
+        assert_eq!(
+            mapped.mime_type_for("application/json\ntext/plain", &ClipboardContentType::Text),
+            Some("text/plain")
+        );
+        assert_eq!(
+            mapped.rank("application/x-color\ntext/plain"),
+            [
+                ClipboardContentType::Color,
+                ClipboardContentType::Text,
+                ClipboardContentType::Unsupported,
+            ]
+        );
+        // HTML mapped to text no longer opens the HTML viewer
+        assert_eq!(mapped.detect("text/html"), ClipboardContentType::Text);
+        assert_eq!(
+            mapped.choose_payload("text/plain", Some("application/x-color"), None),
+            (
+                ClipboardContentType::Color,
+                Some("application/x-color".to_string())
+            )
+        );
+        assert_eq!(
+            DetectionRules::default().detect("application/x-color"),
+            ClipboardContentType::Unsupported
+        );
+    }
+
+    #[test]
+    fn test_parse_content_type_name() {
+        assert_eq!(
+            parse_content_type_name("files"),
+            Ok(ClipboardContentType::File)
+        );
+        assert_eq!(
+            parse_content_type_name("swatch"),
+            Ok(ClipboardContentType::Color)
+        );
+        assert!(parse_content_type_name("Text").is_err());
+    }
+}
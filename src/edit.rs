@@ -0,0 +1,162 @@
+use crate::annotate::{Annotation, Point};
+use crate::raster::RgbaImage;
+use crate::transform::Transform;
+
+/// One step of editing an image in the viewer. Edits apply in order, each
+/// to the result of the ones before, so coordinates refer to the image as
+/// it was when the edit was made.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Annotate(Annotation),
+    Transform(Transform),
+}
+
+impl Edit {
+    pub fn apply(&self, mut image: RgbaImage) -> RgbaImage {
+        match self {
+            Edit::Annotate(annotation) => {
+                annotation.draw(&mut image);
+                image
+            }
+            Edit::Transform(transform) => transform.apply(&image),
+        }
+    }
+
+    /// Follows a drag to `point`; see [`Annotation::drag_to`]. A crop
+    /// moves its second corner.
+    pub fn drag_to(&mut self, point: Point) {
+        match self {
+            Edit::Annotate(annotation) => annotation.drag_to(point),
+            Edit::Transform(Transform::Crop { to, .. }) => *to = point,
+            Edit::Transform(_) => {}
+        }
+    }
+
+    /// Whether the edit would change nothing, like a crop or box dragged
+    /// out to zero size.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Edit::Annotate(annotation) => annotation.is_empty(),
+            Edit::Transform(Transform::Crop { from, to }) => from == to,
+            Edit::Transform(_) => false,
+        }
+    }
+}
+
+/// Applies `edits` in order to a copy of `base`.
+pub fn apply_edits(base: &RgbaImage, edits: &[Edit]) -> RgbaImage {
+    edits
+        .iter()
+        .fold(base.clone(), |image, edit| edit.apply(image))
+}
+
+/// Edits with undo and redo.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EditHistory {
+    done: Vec<Edit>,
+    undone: Vec<Edit>,
+}
+
+impl EditHistory {
+    /// The edits currently applied, oldest first.
+    pub fn edits(&self) -> &[Edit] {
+        &self.done
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty()
+    }
+
+    /// Adds an edit; anything undone can no longer be redone.
+    pub fn push(&mut self, edit: Edit) {
+        self.done.push(edit);
+        self.undone.clear();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.done.pop() {
+            Some(edit) => {
+                self.undone.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        match self.undone.pop() {
+            Some(edit) => {
+                self.done.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    #[test]
+    fn test_edits_apply_in_order() {
+        let base = RgbaImage::filled(4, 2, WHITE);
+        // A box in the top-left corner, then a 90° turn moves it top-right
+        let edits = [
+            Edit::Annotate(Annotation::Rectangle {
+                from: Point::new(0.5, 0.5),
+                to: Point::new(0.5, 0.5),
+                color: RED,
+                width: 1.0,
+            }),
+            Edit::Transform(Transform::RotateClockwise),
+        ];
+        let image = apply_edits(&base, &edits);
+        assert_eq!((image.width, image.height), (2, 4));
+        assert_eq!(image.pixel(1, 0), RED);
+        assert_eq!(image.pixel(0, 0), WHITE);
+        assert_eq!(base, RgbaImage::filled(4, 2, WHITE));
+    }
+
+    #[test]
+    fn test_crop_drag() {
+        let mut crop = Edit::Transform(Transform::Crop {
+            from: Point::new(1.0, 1.0),
+            to: Point::new(1.0, 1.0),
+        });
+        assert!(crop.is_empty());
+        crop.drag_to(Point::new(3.0, 2.0));
+        assert!(!crop.is_empty());
+        let image = crop.apply(RgbaImage::new(4, 4));
+        assert_eq!((image.width, image.height), (2, 1));
+        assert!(!Edit::Transform(Transform::FlipVertical).is_empty());
+    }
+
+    #[test]
+    fn test_history_undo_redo() {
+        let mark = |x| {
+            Edit::Annotate(Annotation::Pixelate {
+                from: Point::new(x, 0.0),
+                to: Point::new(x + 1.0, 1.0),
+                block: 1,
+            })
+        };
+        let mut history = EditHistory::default();
+        assert!(!history.undo());
+        history.push(mark(1.0));
+        history.push(mark(2.0));
+        assert!(history.undo());
+        assert_eq!(history.edits(), [mark(1.0)]);
+        assert!(history.redo());
+        assert_eq!(history.edits().len(), 2);
+        assert!(!history.redo());
+
+        history.undo();
+        history.push(mark(3.0));
+        assert!(!history.redo());
+        assert_eq!(history.edits(), [mark(1.0), mark(3.0)]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::ops::Range;
+
+/// Bytes shown on each line of a hex dump.
+pub const BYTES_PER_LINE: usize = 16;
+
+/// Bytes shown on each page of the hex viewer, so large payloads are never
+/// formatted into one text buffer.
+pub const PAGE_SIZE: usize = 256 * BYTES_PER_LINE;
+
+/// Formats one line of a hex dump in the style of `hexdump -C`: the offset,
+/// up to [`BYTES_PER_LINE`] bytes in two groups of eight, and the printable
+/// ASCII characters.
+pub fn hex_line(offset: usize, bytes: &[u8]) -> String {
+    let mut line = format!("{:08x} ", offset);
+    for index in 0..BYTES_PER_LINE {
+        if index % 8 == 0 {
+            line.push(' ');
+        }
+        match bytes.get(index) {
+            Some(byte) => line.push_str(&format!("{:02x} ", byte)),
+            None => line.push_str("   "),
+        }
+    }
+    line.push_str(" |");
+    line.extend(bytes.iter().map(|&byte| printable(byte)));
+    line.push('|');
+    line
+}
+
+/// Formats `data` as a hex dump, numbering from `offset`.
+pub fn hex_dump(data: &[u8], offset: usize) -> String {
+    data.chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(index, chunk)| hex_line(offset + index * BYTES_PER_LINE, chunk) + "\n")
+        .collect()
+}
+
+/// Column of the first hex digit of byte `index` (0-15) in a [`hex_line`].
+pub fn hex_column(index: usize) -> usize {
+    10 + index * 3 + usize::from(index >= 8)
+}
+
+/// Column of the ASCII character of byte `index` (0-15) in a [`hex_line`].
+pub fn ascii_column(index: usize) -> usize {
+    hex_column(BYTES_PER_LINE) + 2 + index
+}
+
+/// Number of pages needed for `len` bytes; empty data still has one page.
+pub fn page_count(len: usize) -> usize {
+    len.div_ceil(PAGE_SIZE).max(1)
+}
+
+/// The byte range shown on `page`.
+pub fn page_range(page: usize, len: usize) -> Range<usize> {
+    let start = (page * PAGE_SIZE).min(len);
+    start..(start + PAGE_SIZE).min(len)
+}
+
+/// Parses a search query into bytes. Hex digits grouped in pairs
+/// (`89 50 4e 47`) or prefixed with `0x` (`0xCAFE`) are read as bytes; a
+/// query in double quotes, or anything else, is searched for as UTF-8 text,
+/// so words such as `add` or `cafe` are found as text.
+pub fn parse_byte_pattern(query: &str) -> Result<Vec<u8>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Empty search".to_string());
+    }
+    if let Some(text) = query
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .filter(|text| !text.is_empty())
+    {
+        return Ok(text.as_bytes().to_vec());
+    }
+
+    let groups: Vec<&str> = query.split_whitespace().collect();
+    let prefixed = query.starts_with("0x") || query.starts_with("0X");
+    let paired = groups.len() > 1 && groups.iter().all(|group| group.len() == 2);
+    if prefixed || paired {
+        let digits: String = groups
+            .iter()
+            .map(|group| {
+                group
+                    .strip_prefix("0x")
+                    .or_else(|| group.strip_prefix("0X"))
+                    .unwrap_or(group)
+            })
+            .collect();
+        if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            if digits.len() % 2 == 1 {
+                return Err(format!("Odd number of hex digits in {}", query));
+            }
+            return Ok((0..digits.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+                .collect());
+        }
+    }
+    Ok(query.as_bytes().to_vec())
+}
+
+/// Offset of the first occurrence of `pattern` in `data` at or after
+/// `from`.
+pub fn find_bytes(data: &[u8], pattern: &[u8], from: usize) -> Option<usize> {
+    if pattern.is_empty() || from >= data.len() {
+        return None;
+    }
+    data[from..]
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|position| from + position)
+}
+
+fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_line() {
+        assert_eq!(
+            hex_line(0, b"Hello, world!\n\0\x7f"),
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 7f  |Hello, world!...|"
+        );
+        assert_eq!(
+            hex_line(0x10, b"abc"),
+            "00000010  61 62 63                                          |abc|"
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let line = hex_line(0, b"0123456789abcdef");
+        for index in 0..BYTES_PER_LINE {
+            let byte = b"0123456789abcdef"[index];
+            assert_eq!(
+                &line[hex_column(index)..hex_column(index) + 2],
+                format!("{:02x}", byte)
+            );
+            assert_eq!(line.as_bytes()[ascii_column(index)], byte);
+        }
+    }
+
+    #[test]
+    fn test_pages() {
+        assert_eq!(page_count(0), 1);
+        assert_eq!(page_count(PAGE_SIZE), 1);
+        assert_eq!(page_count(PAGE_SIZE + 1), 2);
+        assert_eq!(page_range(0, 10), 0..10);
+        assert_eq!(page_range(1, PAGE_SIZE + 5), PAGE_SIZE..PAGE_SIZE + 5);
+        assert_eq!(page_range(3, 10), 10..10);
+    }
+
+    #[test]
+    fn test_parse_byte_pattern() {
+        assert_eq!(
+            parse_byte_pattern("89 50 4e 47"),
+            Ok(vec![0x89, b'P', b'N', b'G'])
+        );
+        assert_eq!(parse_byte_pattern("0xCAFE"), Ok(vec![0xca, 0xfe]));
+        assert_eq!(parse_byte_pattern("\"cafe\""), Ok(b"cafe".to_vec()));
+        assert_eq!(parse_byte_pattern("PDF-1"), Ok(b"PDF-1".to_vec()));
+        assert_eq!(parse_byte_pattern("0x89 0x50"), Ok(vec![0x89, b'P']));
+        // Hex-looking words are text unless grouped in pairs or prefixed
+        assert_eq!(parse_byte_pattern("add"), Ok(b"add".to_vec()));
+        assert_eq!(parse_byte_pattern("cafe"), Ok(b"cafe".to_vec()));
+        assert_eq!(parse_byte_pattern("be ef"), Ok(vec![0xbe, 0xef]));
+        assert_eq!(parse_byte_pattern("hi yo"), Ok(b"hi yo".to_vec()));
+        assert!(parse_byte_pattern("0xabc").is_err());
+        assert!(parse_byte_pattern("  ").is_err());
+    }
+
+    #[test]
+    fn test_find_bytes() {
+        let data = b"abcabc";
+        assert_eq!(find_bytes(data, b"bc", 0), Some(1));
+        assert_eq!(find_bytes(data, b"bc", 2), Some(4));
+        assert_eq!(find_bytes(data, b"bc", 5), None);
+        assert_eq!(find_bytes(data, b"", 0), None);
+        assert_eq!(find_bytes(data, b"abcabcabc", 0), None);
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let dump = hex_dump(&[0xff; 20], 0x100);
+        let lines: Vec<_> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("00000100  ff ff"));
+        assert!(lines[1].starts_with("00000110  ff ff ff ff "));
+        assert!(lines[1].ends_with("|....|"));
+        assert_eq!(hex_dump(b"", 0), "");
+    }
+}
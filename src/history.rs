@@ -0,0 +1,807 @@
+//! On-disk clipboard history recorded by `waypin daemon`.
+//!
+//! Each entry lives in its own directory named after the content hash, so
+//! copying the same thing twice maps to the same entry:
+//!
+//! ```text
+//! $XDG_DATA_HOME/waypin/history/<hash>/meta
+//! $XDG_DATA_HOME/waypin/history/<hash>/<index>.bin
+//! ```
+//!
+//! `meta` is a small line-based file with the timestamp, classification,
+//! chosen MIME type and one `type <index> <mime>` line per stored payload.
+//! Past [`HistoryStore::with_max_entries`] the oldest entries are deleted.
+//!
+//! History often holds passwords and tokens, so the directories are only
+//! accessible to their owner, and anything a password manager marks as
+//! secret is never recorded.
+
+use std::cell::RefCell;
+use std::fs::{self, DirBuilder, OpenOptions, Permissions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::detect::DetectionRules;
+use crate::snapshot::ClipboardSnapshot;
+use crate::{is_metadata_target, offered_types, ClipboardBackend, ClipboardContentType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// Content hash, also the entry's directory name.
+    pub id: String,
+    /// Milliseconds since the Unix epoch of the last time it was copied.
+    pub timestamp: u64,
+    pub content_type: ClipboardContentType,
+    /// MIME type the viewer would show, if any.
+    pub mime_type: Option<String>,
+    /// Every stored MIME type, in the order the clipboard offered them.
+    pub types: Vec<String>,
+}
+
+/// What `HistoryStore::add` did with a new clipboard state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddOutcome {
+    /// A new entry was written.
+    Added,
+    /// An older entry had the same content and was moved to the top.
+    Refreshed,
+    /// The newest entry already has this content.
+    Unchanged,
+}
+
+/// 64-bit FNV-1a, used for content hashes because it is stable across
+/// runs and platforms, unlike `std`'s `DefaultHasher`.
+pub fn content_hash(parts: &[&[u8]]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.iter().chain(&[0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Hash used to deduplicate a snapshot. The chosen payload identifies the
+/// content, so the same text copied from apps offering different extra
+/// types still maps to one entry.
+pub fn snapshot_hash(snapshot: &ClipboardSnapshot, payloads: &[(String, Vec<u8>)]) -> String {
+    let content_type = content_type_name(&snapshot.content_type).as_bytes();
+    if !snapshot.payload.is_empty() {
+        return content_hash(&[content_type, &snapshot.payload]);
+    }
+    let mut parts = vec![content_type];
+    for (mime_type, data) in payloads {
+        parts.push(mime_type.as_bytes());
+        parts.push(data);
+    }
+    content_hash(&parts)
+}
+
+fn content_type_name(content_type: &ClipboardContentType) -> &'static str {
+    match content_type {
+        ClipboardContentType::Text => "Text",
+        ClipboardContentType::Html => "Html",
+        ClipboardContentType::Rtf => "Rtf",
+        ClipboardContentType::Image => "Image",
+        ClipboardContentType::File => "File",
+        ClipboardContentType::Color => "Color",
+        ClipboardContentType::Unsupported => "Unsupported",
+    }
+}
+
+fn parse_content_type(name: &str) -> Option<ClipboardContentType> {
+    match name {
+        "Text" => Some(ClipboardContentType::Text),
+        "Html" => Some(ClipboardContentType::Html),
+        "Rtf" => Some(ClipboardContentType::Rtf),
+        "Image" => Some(ClipboardContentType::Image),
+        "File" => Some(ClipboardContentType::File),
+        "Color" => Some(ClipboardContentType::Color),
+        "Unsupported" => Some(ClipboardContentType::Unsupported),
+        _ => None,
+    }
+}
+
+/// Entries kept unless `[history] max_entries` says otherwise.
+pub const DEFAULT_MAX_ENTRIES: usize = 500;
+
+/// Offered by KeePassXC and other password managers alongside a secret
+/// they copy, asking clipboard managers not to keep it.
+pub const PASSWORD_MANAGER_HINT: &str = "x-kde-passwordManagerHint";
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+pub struct HistoryStore {
+    dir: PathBuf,
+    /// Entry ids, most recently copied first, read once when the store is
+    /// opened so clipboard changes don't rescan the directory.
+    order: RefCell<Vec<String>>,
+    max_entries: usize,
+}
+
+impl HistoryStore {
+    /// `$XDG_DATA_HOME/waypin/history`, falling back to
+    /// `~/.local/share/waypin/history`.
+    pub fn default_dir() -> Option<PathBuf> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share"))
+            })?;
+        Some(data_home.join("waypin").join("history"))
+    }
+
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, String> {
+        let dir = dir.into();
+        create_private_dir(&dir)?;
+        // Tighten a directory left readable by older versions
+        fs::set_permissions(&dir, Permissions::from_mode(0o700))
+            .map_err(|e| format!("Failed to restrict {}: {}", dir.display(), e))?;
+        let store = Self {
+            dir,
+            order: RefCell::new(Vec::new()),
+            max_entries: DEFAULT_MAX_ENTRIES,
+        };
+        let order = store.entries()?.into_iter().map(|entry| entry.id).collect();
+        store.order.replace(order);
+        Ok(store)
+    }
+
+    /// Keeps at most `max_entries` entries; older ones are deleted the next
+    /// time one is added.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn open_default() -> Result<Self, String> {
+        let dir = Self::default_dir()
+            .ok_or_else(|| "Neither XDG_DATA_HOME nor HOME is set".to_string())?;
+        Self::open(dir)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// All entries, most recently copied first.
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>, String> {
+        let read_dir = fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to read {}: {}", self.dir.display(), e))?;
+        let mut entries: Vec<HistoryEntry> = read_dir
+            .filter_map(|item| item.ok())
+            .filter_map(|item| {
+                let id = item.file_name().into_string().ok()?;
+                self.load(&id)
+            })
+            .collect();
+        entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.id.cmp(&b.id)));
+        Ok(entries)
+    }
+
+    /// Whether `id` is the most recently copied entry, and still on disk.
+    pub fn is_newest(&self, id: &str) -> bool {
+        self.order
+            .borrow()
+            .first()
+            .is_some_and(|newest| newest == id)
+            && self.dir.join(id).join("meta").is_file()
+    }
+
+    pub fn get(&self, id: &str) -> Option<HistoryEntry> {
+        self.load(id)
+    }
+
+    /// Stored payload of one MIME type of an entry.
+    pub fn payload(&self, entry: &HistoryEntry, mime_type: &str) -> Result<Vec<u8>, String> {
+        let index = entry
+            .types
+            .iter()
+            .position(|t| t == mime_type)
+            .ok_or_else(|| format!("Entry {} has no {} payload", entry.id, mime_type))?;
+        let path = self.dir.join(&entry.id).join(format!("{}.bin", index));
+        fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    }
+
+    /// Records a clipboard state. `payloads` holds every MIME type worth
+    /// keeping, including the snapshot's chosen one.
+    pub fn add(
+        &self,
+        snapshot: &ClipboardSnapshot,
+        payloads: &[(String, Vec<u8>)],
+        timestamp: u64,
+    ) -> Result<(AddOutcome, String), String> {
+        let id = snapshot_hash(snapshot, payloads);
+
+        if let Some(mut existing) = self.load(&id) {
+            if self.is_newest(&id) {
+                return Ok((AddOutcome::Unchanged, id));
+            }
+            existing.timestamp = timestamp;
+            self.write_meta(&existing)?;
+            self.move_to_top(&id);
+            return Ok((AddOutcome::Refreshed, id));
+        }
+
+        let entry_dir = self.dir.join(&id);
+        create_private_dir(&entry_dir)?;
+        for (index, (_, data)) in payloads.iter().enumerate() {
+            write_private(&entry_dir.join(format!("{}.bin", index)), data)?;
+        }
+
+        let entry = HistoryEntry {
+            id: id.clone(),
+            timestamp,
+            content_type: snapshot.content_type.clone(),
+            mime_type: snapshot.mime_type.clone(),
+            types: payloads.iter().map(|(mime, _)| mime.clone()).collect(),
+        };
+        // meta is written last so a half-written entry is never listed
+        self.write_meta(&entry)?;
+        self.move_to_top(&id);
+        self.prune()?;
+        Ok((AddOutcome::Added, id))
+    }
+
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        self.order.borrow_mut().retain(|other| other != id);
+        let path = self.dir.join(id);
+        fs::remove_dir_all(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+    }
+
+    fn move_to_top(&self, id: &str) {
+        let mut order = self.order.borrow_mut();
+        order.retain(|other| other != id);
+        order.insert(0, id.to_string());
+    }
+
+    /// Deletes the oldest entries past `max_entries`.
+    fn prune(&self) -> Result<(), String> {
+        let excess = {
+            let mut order = self.order.borrow_mut();
+            let keep = self.max_entries.min(order.len());
+            order.split_off(keep)
+        };
+        for id in excess {
+            let path = self.dir.join(&id);
+            match fs::remove_dir_all(&path) {
+                Ok(()) => {}
+                // Already deleted from the history window
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to remove {}: {}", path.display(), e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Option<HistoryEntry> {
+        let meta = fs::read_to_string(self.dir.join(id).join("meta")).ok()?;
+        parse_meta(id, &meta)
+    }
+
+    fn write_meta(&self, entry: &HistoryEntry) -> Result<(), String> {
+        let path = self.dir.join(&entry.id).join("meta");
+        write_private(&path, format_meta(entry).as_bytes())
+    }
+}
+
+/// Creates `dir` and any missing parents, readable only by the owner.
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))
+}
+
+/// Writes `data` to `path`, creating it readable only by the owner.
+fn write_private(path: &Path, data: &[u8]) -> Result<(), String> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(data))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn format_meta(entry: &HistoryEntry) -> String {
+    let mut meta = format!(
+        "timestamp {}\ncontent_type {}\n",
+        entry.timestamp,
+        content_type_name(&entry.content_type)
+    );
+    if let Some(mime_type) = &entry.mime_type {
+        meta.push_str(&format!("mime_type {}\n", mime_type));
+    }
+    for (index, mime_type) in entry.types.iter().enumerate() {
+        meta.push_str(&format!("type {} {}\n", index, mime_type));
+    }
+    meta
+}
+
+fn parse_meta(id: &str, meta: &str) -> Option<HistoryEntry> {
+    let mut timestamp = None;
+    let mut content_type = None;
+    let mut mime_type = None;
+    let mut types = Vec::new();
+
+    for line in meta.lines() {
+        let (key, value) = line.split_once(' ')?;
+        match key {
+            "timestamp" => timestamp = value.parse().ok(),
+            "content_type" => content_type = parse_content_type(value),
+            "mime_type" => mime_type = Some(value.to_string()),
+            "type" => {
+                let (index, mime) = value.split_once(' ')?;
+                if index.parse::<usize>().ok()? != types.len() {
+                    return None;
+                }
+                types.push(mime.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    Some(HistoryEntry {
+        id: id.to_string(),
+        timestamp: timestamp?,
+        content_type: content_type?,
+        mime_type,
+        types,
+    })
+}
+
+/// Reads the current clipboard with all of its MIME types and stores it.
+/// Returns `None` when the clipboard is empty or holds a password. The
+/// entry is classified with `rules`, like the viewers do.
+pub fn record_clipboard(
+    backend: &dyn ClipboardBackend,
+    store: &HistoryStore,
+    rules: &DetectionRules,
+) -> Result<Option<(AddOutcome, String)>, String> {
+    let Ok(snapshot) = ClipboardSnapshot::capture_with_rules(backend, None, None, rules) else {
+        return Ok(None);
+    };
+    if snapshot
+        .types
+        .lines()
+        .any(|mime| mime == PASSWORD_MANAGER_HINT)
+    {
+        return Ok(None);
+    }
+
+    // Skip reading every other MIME type when nothing changed, which is
+    // the common case for polling backends.
+    if !snapshot.payload.is_empty() {
+        let id = snapshot_hash(&snapshot, &[]);
+        if store.is_newest(&id) {
+            return Ok(Some((AddOutcome::Unchanged, id)));
+        }
+    }
+
+    let payloads: Vec<(String, Vec<u8>)> = snapshot
+        .types
+        .lines()
+        .filter(|mime| !mime.is_empty() && !is_metadata_target(mime))
+        .filter_map(|mime| {
+            let data = if snapshot.mime_type.as_deref() == Some(mime) {
+                snapshot.payload.clone()
+            } else {
+                backend.read(mime).ok()?
+            };
+            Some((mime.to_string(), data))
+        })
+        .collect();
+
+    store.add(&snapshot, &payloads, now_millis()).map(Some)
+}
+
+/// Watches the clipboard and records every change until the watch ends.
+pub fn run_daemon(
+    backend: &dyn ClipboardBackend,
+    store: &HistoryStore,
+    rules: &DetectionRules,
+) -> Result<(), String> {
+    backend.watch(&mut || {
+        match record_clipboard(backend, store, rules) {
+            Ok(Some((AddOutcome::Added, id))) => println!("Stored clipboard entry {}", id),
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to record clipboard: {}", e),
+        }
+        true
+    })
+}
+
+/// Puts a stored entry back on the clipboard with all of its MIME types and
+/// returns the ones actually offered. The type the viewer showed goes
+/// first, since backends that can only offer one representation use the
+/// first.
+pub fn restore_entry(
+    backend: &dyn ClipboardBackend,
+    store: &HistoryStore,
+    entry: &HistoryEntry,
+) -> Result<Vec<String>, String> {
+    let mut types: Vec<&str> = entry.types.iter().map(String::as_str).collect();
+    if let Some(preferred) = entry.mime_type.as_deref() {
+        if let Some(index) = types.iter().position(|t| *t == preferred) {
+            types.remove(index);
+            types.insert(0, preferred);
+        }
+    }
+
+    let payloads = types
+        .iter()
+        .map(|mime| Ok((*mime, store.payload(entry, mime)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let contents: Vec<(&str, &[u8])> = payloads
+        .iter()
+        .map(|(mime, data)| (*mime, data.as_slice()))
+        .collect();
+    backend.write(&contents)?;
+    Ok(offered_types(backend, &contents)
+        .into_iter()
+        .map(str::to_string)
+        .collect())
+}
+
+/// First line of a text entry, cut to `max_chars`, for list display.
+pub fn text_snippet(text: &str, max_chars: usize) -> String {
+    let trimmed = text.trim();
+    let first_line = trimmed.lines().next().unwrap_or_default();
+    let mut snippet: String = first_line.chars().take(max_chars).collect();
+    if first_line.chars().count() > max_chars || trimmed.lines().nth(1).is_some() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Case-insensitive substring search used by the history browser.
+pub fn matches_query(text: &str, query: &str) -> bool {
+    let query = query.trim();
+    query.is_empty() || text.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// Short human-readable age such as "5 min ago".
+pub fn format_age(now_millis: u64, timestamp: u64) -> String {
+    let seconds = now_millis.saturating_sub(timestamp) / 1000;
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", seconds / 60),
+        3600..=86_399 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} d ago", seconds / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_snapshot(text: &str) -> ClipboardSnapshot {
+        ClipboardSnapshot {
+            types: "text/plain\nUTF8_STRING".to_string(),
+            content_type: ClipboardContentType::Text,
+            mime_type: Some("text/plain".to_string()),
+            payload: text.as_bytes().to_vec(),
+        }
+    }
+
+    fn text_payloads(text: &str) -> Vec<(String, Vec<u8>)> {
+        vec![
+            ("text/plain".to_string(), text.as_bytes().to_vec()),
+            ("UTF8_STRING".to_string(), text.as_bytes().to_vec()),
+        ]
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        assert_eq!(content_hash(&[]), "cbf29ce484222325");
+        assert_eq!(content_hash(&[b"abc"]), content_hash(&[b"abc"]));
+        assert_ne!(content_hash(&[b"abc"]), content_hash(&[b"abd"]));
+        // Part boundaries matter
+        assert_ne!(content_hash(&[b"ab", b"c"]), content_hash(&[b"a", b"bc"]));
+    }
+
+    #[test]
+    fn test_snapshot_hash_ignores_extra_types() {
+        let snapshot = text_snapshot("hello");
+        let fewer = vec![("text/plain".to_string(), b"hello".to_vec())];
+        assert_eq!(
+            snapshot_hash(&snapshot, &text_payloads("hello")),
+            snapshot_hash(&snapshot, &fewer)
+        );
+    }
+
+    #[test]
+    fn test_meta_roundtrip() {
+        let entry = HistoryEntry {
+            id: "0123456789abcdef".to_string(),
+            timestamp: 1_700_000_000_123,
+            content_type: ClipboardContentType::Image,
+            mime_type: Some("image/png".to_string()),
+            types: vec![
+                "image/png".to_string(),
+                "text/plain;charset=utf-8".to_string(),
+            ],
+        };
+        assert_eq!(parse_meta(&entry.id, &format_meta(&entry)), Some(entry));
+    }
+
+    #[test]
+    fn test_parse_meta_rejects_incomplete() {
+        assert_eq!(parse_meta("id", "timestamp 1\n"), None);
+        assert_eq!(parse_meta("id", "content_type Text\n"), None);
+        assert_eq!(parse_meta("id", "timestamp 1\ncontent_type Nope\n"), None);
+        assert_eq!(
+            parse_meta("id", "timestamp 1\ncontent_type Text\ntype 3 text/plain\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_store_add_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path().join("history")).unwrap();
+
+        let (outcome, id) = store
+            .add(&text_snapshot("hello"), &text_payloads("hello"), 10)
+            .unwrap();
+        assert_eq!(outcome, AddOutcome::Added);
+
+        let entries = store.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, id);
+        assert_eq!(entries[0].content_type, ClipboardContentType::Text);
+        assert_eq!(entries[0].types, vec!["text/plain", "UTF8_STRING"]);
+        assert_eq!(store.payload(&entries[0], "UTF8_STRING").unwrap(), b"hello");
+        assert!(store.payload(&entries[0], "image/png").is_err());
+    }
+
+    #[test]
+    fn test_store_deduplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap();
+
+        store
+            .add(&text_snapshot("a"), &text_payloads("a"), 10)
+            .unwrap();
+        let (outcome, _) = store
+            .add(&text_snapshot("a"), &text_payloads("a"), 20)
+            .unwrap();
+        assert_eq!(outcome, AddOutcome::Unchanged);
+        assert_eq!(store.entries().unwrap()[0].timestamp, 10);
+
+        store
+            .add(&text_snapshot("b"), &text_payloads("b"), 30)
+            .unwrap();
+        let (outcome, id) = store
+            .add(&text_snapshot("a"), &text_payloads("a"), 40)
+            .unwrap();
+        assert_eq!(outcome, AddOutcome::Refreshed);
+
+        let entries = store.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, id);
+        assert_eq!(entries[0].timestamp, 40);
+    }
+
+    #[test]
+    fn test_store_orders_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap();
+
+        for (index, text) in ["one", "two", "three"].iter().enumerate() {
+            store
+                .add(&text_snapshot(text), &text_payloads(text), index as u64)
+                .unwrap();
+        }
+        let texts: Vec<Vec<u8>> = store
+            .entries()
+            .unwrap()
+            .iter()
+            .map(|entry| store.payload(entry, "text/plain").unwrap())
+            .collect();
+        assert_eq!(
+            texts,
+            vec![b"three".to_vec(), b"two".to_vec(), b"one".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_store_prunes_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap().with_max_entries(2);
+
+        let ids: Vec<String> = ["one", "two", "three"]
+            .iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let (_, id) = store
+                    .add(&text_snapshot(text), &text_payloads(text), index as u64)
+                    .unwrap();
+                id
+            })
+            .collect();
+        let kept: Vec<String> = store
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(kept, [ids[2].clone(), ids[1].clone()]);
+        assert!(!dir.path().join(&ids[0]).exists());
+
+        // A reopened store knows the order without being told
+        let store = HistoryStore::open(dir.path()).unwrap();
+        assert!(store.is_newest(&ids[2]));
+    }
+
+    #[test]
+    fn test_store_notices_removed_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap();
+        let (_, id) = store
+            .add(&text_snapshot("a"), &text_payloads("a"), 1)
+            .unwrap();
+
+        // Deleted by another process, such as the history window
+        fs::remove_dir_all(dir.path().join(&id)).unwrap();
+        assert!(!store.is_newest(&id));
+        let (outcome, _) = store
+            .add(&text_snapshot("a"), &text_payloads("a"), 2)
+            .unwrap();
+        assert_eq!(outcome, AddOutcome::Added);
+    }
+
+    #[test]
+    fn test_store_remove_and_skip_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap();
+        fs::create_dir(dir.path().join("not-an-entry")).unwrap();
+        fs::write(dir.path().join("stray-file"), b"x").unwrap();
+
+        let (_, id) = store
+            .add(&text_snapshot("x"), &text_payloads("x"), 1)
+            .unwrap();
+        assert_eq!(store.entries().unwrap().len(), 1);
+
+        store.remove(&id).unwrap();
+        assert!(store.entries().unwrap().is_empty());
+        assert!(store.get(&id).is_none());
+    }
+
+    #[test]
+    fn test_store_is_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = dir.path().join("history");
+        fs::create_dir(&history).unwrap();
+        fs::set_permissions(&history, Permissions::from_mode(0o755)).unwrap();
+        let store = HistoryStore::open(&history).unwrap();
+
+        let (_, id) = store
+            .add(&text_snapshot("secret"), &text_payloads("secret"), 1)
+            .unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&history), 0o700);
+        assert_eq!(mode(&history.join(&id)), 0o700);
+        assert_eq!(mode(&history.join(&id).join("meta")), 0o600);
+        assert_eq!(mode(&history.join(&id).join("0.bin")), 0o600);
+    }
+
+    #[test]
+    fn test_record_clipboard_skips_passwords() {
+        use crate::backend::FakeClipboard;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap();
+        let clipboard = FakeClipboard::new(
+            "text/plain\nx-kde-passwordManagerHint",
+            &[
+                ("text/plain", b"hunter2"),
+                (PASSWORD_MANAGER_HINT, b"secret"),
+            ],
+        );
+        let rules = DetectionRules::default();
+        assert_eq!(record_clipboard(&clipboard, &store, &rules).unwrap(), None);
+        assert!(store.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_clipboard_uses_rules() {
+        use crate::backend::FakeClipboard;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap();
+        let clipboard = FakeClipboard::new(
+            "image/png\ntext/plain",
+            &[("image/png", b"\x89PNG"), ("text/plain", b"caption")],
+        );
+        let rules = DetectionRules {
+            priority: vec![ClipboardContentType::Text],
+            ..DetectionRules::default()
+        };
+        record_clipboard(&clipboard, &store, &rules).unwrap();
+
+        let entries = store.entries().unwrap();
+        assert_eq!(entries[0].content_type, ClipboardContentType::Text);
+        assert_eq!(entries[0].mime_type.as_deref(), Some("text/plain"));
+        assert_eq!(entries[0].types, ["image/png", "text/plain"]);
+    }
+
+    #[test]
+    fn test_text_snippet() {
+        assert_eq!(text_snippet("hello", 10), "hello");
+        assert_eq!(text_snippet("  hello  ", 10), "hello");
+        assert_eq!(text_snippet("hello world", 5), "hello…");
+        assert_eq!(text_snippet("first\nsecond", 20), "first…");
+        assert_eq!(text_snippet("Привіт світ", 6), "Привіт…");
+        assert_eq!(text_snippet("", 5), "");
+    }
+
+    #[test]
+    fn test_matches_query() {
+        assert!(matches_query("Hello World", ""));
+        assert!(matches_query("Hello World", "world"));
+        assert!(matches_query("Hello World", "  LO W "));
+        assert!(!matches_query("Hello World", "planet"));
+    }
+
+    #[test]
+    fn test_format_age() {
+        let now = 10_000_000;
+        assert_eq!(format_age(now, now), "just now");
+        assert_eq!(format_age(now, now + 5_000), "just now");
+        assert_eq!(format_age(now, now - 5 * 60_000), "5 min ago");
+        assert_eq!(format_age(now, now - 2 * 3_600_000), "2 h ago");
+        assert_eq!(format_age(now * 100, now * 100 - 3 * 86_400_000), "3 d ago");
+    }
+
+    #[test]
+    fn test_restore_entry_offers_preferred_type_first() {
+        use crate::backend::FakeClipboard;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap();
+        let snapshot = ClipboardSnapshot {
+            types: "text/html\ntext/plain".to_string(),
+            content_type: ClipboardContentType::Text,
+            mime_type: Some("text/plain".to_string()),
+            payload: b"hi".to_vec(),
+        };
+        let payloads = vec![
+            ("text/html".to_string(), b"<b>hi</b>".to_vec()),
+            ("text/plain".to_string(), b"hi".to_vec()),
+        ];
+        let (_, id) = store.add(&snapshot, &payloads, 1).unwrap();
+
+        let entry = store.get(&id).unwrap();
+        let recorder = FakeClipboard {
+            multiple_types: true,
+            ..FakeClipboard::default()
+        };
+        let offered = restore_entry(&recorder, &store, &entry).unwrap();
+        assert_eq!(offered, ["text/plain", "text/html"]);
+        assert_eq!(
+            *recorder.contents.borrow(),
+            vec![
+                ("text/plain".to_string(), b"hi".to_vec()),
+                ("text/html".to_string(), b"<b>hi</b>".to_vec()),
+            ]
+        );
+
+        // wl-copy and xclip only offer the preferred type
+        let single = FakeClipboard::default();
+        let offered = restore_entry(&single, &store, &entry).unwrap();
+        assert_eq!(offered, ["text/plain"]);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::hex::hex_dump;
+use crate::{parse_types, ClipboardBackend, ClipboardContentType};
+
+/// Bytes of each type shown in the inspector preview.
+pub const PREVIEW_LEN: usize = 4096;
+
+/// One offered type with the data read for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectedType {
+    pub mime_type: String,
+    pub content_type: ClipboardContentType,
+    /// The payload, or why it could not be read.
+    pub data: Result<Vec<u8>, String>,
+}
+
+impl InspectedType {
+    pub fn size(&self) -> Option<usize> {
+        self.data.as_ref().ok().map(Vec::len)
+    }
+}
+
+/// Reads every offered type, in the order offered. A type that fails to
+/// read is kept with its error so the listing stays complete.
+pub fn inspect_clipboard(backend: &dyn ClipboardBackend) -> Result<Vec<InspectedType>, String> {
+    let types = backend.list_types().unwrap_or_default();
+    let offered = parse_types(&types);
+    if offered.is_empty() {
+        return Err("Could not retrieve clipboard types or clipboard is empty.".to_string());
+    }
+    Ok(offered
+        .into_iter()
+        .map(|entry| InspectedType {
+            data: backend.read(&entry.mime_type),
+            mime_type: entry.mime_type,
+            content_type: entry.content_type,
+        })
+        .collect())
+}
+
+/// The start of a payload, shown as text when it decodes as UTF-8 and as a
+/// hex dump otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preview {
+    Text(String),
+    Hex(String),
+}
+
+/// Previews the first `limit` bytes of `data`.
+pub fn preview(data: &[u8], limit: usize) -> Preview {
+    let head = &data[..data.len().min(limit)];
+    match text_prefix(head, head.len() < data.len()) {
+        Some(text) => Preview::Text(text.to_string()),
+        None => Preview::Hex(hex_dump(head, 0)),
+    }
+}
+
+/// `head` as text, allowing a character cut off at the end when `head` is
+/// only the start of the payload.
+fn text_prefix(head: &[u8], truncated: bool) -> Option<&str> {
+    if head.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(head) {
+        Ok(text) => Some(text),
+        Err(e) if truncated && e.error_len().is_none() => {
+            std::str::from_utf8(&head[..e.valid_up_to()]).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::FakeClipboard;
+
+    #[test]
+    fn test_inspect_clipboard() {
+        let backend = FakeClipboard::new(
+            "text/html\nchromium/x-web-custom-data\nTARGETS\n",
+            &[
+                ("text/html", b"<b>hi</b>".as_slice()),
+                ("chromium/x-web-custom-data", b"\x01\x00".as_slice()),
+            ],
+        );
+        let inspected = inspect_clipboard(&backend).unwrap();
+        assert_eq!(inspected.len(), 3);
+        assert_eq!(inspected[0].content_type, ClipboardContentType::Html);
+        assert_eq!(inspected[0].size(), Some(9));
+        assert_eq!(inspected[1].data, Ok(vec![1, 0]));
+        assert_eq!(inspected[2].size(), None);
+
+        let empty = FakeClipboard::new("\n", &[]);
+        assert!(inspect_clipboard(&empty).is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview(b"hello", 16), Preview::Text("hello".to_string()));
+        assert_eq!(preview(b"hello", 4), Preview::Text("hell".to_string()));
+        // A character split by the limit is dropped
+        assert_eq!(preview("aé".as_bytes(), 2), Preview::Text("a".to_string()));
+        assert!(matches!(preview(b"\x89PNG\r\n\x1a\n", 16), Preview::Hex(_)));
+        assert!(matches!(preview(b"a\0b", 16), Preview::Hex(_)));
+        assert!(matches!(preview(b"\xff\xfe", 16), Preview::Hex(_)));
+    }
+}
@@ -0,0 +1,74 @@
+//! The few gtk-layer-shell calls waypin needs to pin a viewer as an
+//! overlay, declared by hand to avoid another dependency. Built with the
+//! `layer-shell` feature; `build.rs` finds and links `libgtk-layer-shell`.
+
+use std::os::raw::{c_char, c_int};
+
+use gtk::glib::translate::ToGlibPtr;
+use gtk::prelude::*;
+use waypin_lib::overlay::Corner;
+
+// GtkLayerShellLayer, GtkLayerShellEdge and GtkLayerShellKeyboardMode
+const LAYER_OVERLAY: c_int = 3;
+const EDGE_LEFT: c_int = 0;
+const EDGE_RIGHT: c_int = 1;
+const EDGE_TOP: c_int = 2;
+const EDGE_BOTTOM: c_int = 3;
+const KEYBOARD_MODE_ON_DEMAND: c_int = 2;
+
+extern "C" {
+    fn gtk_layer_is_supported() -> gtk::glib::ffi::gboolean;
+    fn gtk_layer_init_for_window(window: *mut gtk::ffi::GtkWindow);
+    fn gtk_layer_set_namespace(window: *mut gtk::ffi::GtkWindow, name_space: *const c_char);
+    fn gtk_layer_set_layer(window: *mut gtk::ffi::GtkWindow, layer: c_int);
+    fn gtk_layer_set_anchor(
+        window: *mut gtk::ffi::GtkWindow,
+        edge: c_int,
+        anchor_to_edge: gtk::glib::ffi::gboolean,
+    );
+    fn gtk_layer_set_margin(window: *mut gtk::ffi::GtkWindow, edge: c_int, margin_size: c_int);
+    fn gtk_layer_set_keyboard_mode(window: *mut gtk::ffi::GtkWindow, mode: c_int);
+}
+
+/// Whether the compositor offers layer-shell. False on X11 and on Wayland
+/// compositors without it, such as GNOME.
+pub fn is_supported() -> bool {
+    unsafe { gtk_layer_is_supported() != 0 }
+}
+
+/// Turns `window` into an overlay-layer surface anchored at `corner`. It
+/// only takes the keyboard once clicked, so it doesn't steal focus when it
+/// opens. Must be called before the window is shown.
+pub fn pin(window: &impl IsA<gtk::Window>, corner: Corner, margins: (i32, i32)) {
+    let pointer: *mut gtk::ffi::GtkWindow = window.as_ref().to_glib_none().0;
+    unsafe {
+        gtk_layer_init_for_window(pointer);
+        gtk_layer_set_namespace(pointer, c"waypin".as_ptr());
+        gtk_layer_set_layer(pointer, LAYER_OVERLAY);
+        gtk_layer_set_anchor(pointer, EDGE_LEFT, corner.is_left().into());
+        gtk_layer_set_anchor(pointer, EDGE_RIGHT, (!corner.is_left()).into());
+        gtk_layer_set_anchor(pointer, EDGE_TOP, corner.is_top().into());
+        gtk_layer_set_anchor(pointer, EDGE_BOTTOM, (!corner.is_top()).into());
+        gtk_layer_set_keyboard_mode(pointer, KEYBOARD_MODE_ON_DEMAND);
+    }
+    set_margins(window, corner, margins);
+}
+
+/// Moves a pinned window by setting its margins from the edges of `corner`.
+pub fn set_margins(window: &impl IsA<gtk::Window>, corner: Corner, margins: (i32, i32)) {
+    let pointer: *mut gtk::ffi::GtkWindow = window.as_ref().to_glib_none().0;
+    let horizontal = if corner.is_left() {
+        EDGE_LEFT
+    } else {
+        EDGE_RIGHT
+    };
+    let vertical = if corner.is_top() {
+        EDGE_TOP
+    } else {
+        EDGE_BOTTOM
+    };
+    unsafe {
+        gtk_layer_set_margin(pointer, horizontal, margins.0);
+        gtk_layer_set_margin(pointer, vertical, margins.1);
+    }
+}
@@ -1,5 +1,36 @@
 use std::process::Command;
 
+pub mod annotate;
+pub mod backend;
+pub mod cli;
+pub mod color;
+pub mod config;
+pub mod convert;
+pub mod detect;
+pub mod edit;
+pub mod hex;
+pub mod history;
+pub mod inspect;
+pub mod load;
+pub mod overlay;
+pub mod raster;
+pub mod rich_text;
+pub mod save;
+pub mod snapshot;
+pub mod transform;
+pub mod uri_list;
+pub mod zoom;
+#[cfg(feature = "data-control")]
+pub mod data_control;
+
+pub use backend::{
+    default_backend, offered_types, BackendKind, ClipboardBackend, Selection, WlClipboard,
+    XClipboard,
+};
+pub use config::{Config, DisplayBackend, HistoryConfig, WindowConfig};
+pub use detect::DetectionRules;
+pub use snapshot::{dispatch, ClipboardSnapshot, ViewerRequest};
+
 pub fn run_command(args: &[&str]) -> Option<Vec<u8>> {
     let output = Command::new(args[0])
         .args(&args[1..])
@@ -16,49 +47,163 @@ pub fn has_mime_type(types: &str, mime: &str) -> bool {
     types.lines().any(|line| line == mime)
 }
 
-pub fn detect_clipboard_content_type(types: &str) -> ClipboardContentType {
-    let is_image = has_mime_type(types, "image/png") 
-        || has_mime_type(types, "image/jpeg") 
-        || has_mime_type(types, "image/gif");
-    let is_text = has_mime_type(types, "text/plain") 
-        || has_mime_type(types, "text/plain;charset=utf-8") 
-        || has_mime_type(types, "UTF8_STRING") 
-        || has_mime_type(types, "TEXT") 
-        || has_mime_type(types, "STRING");
-    let is_file = has_mime_type(types, "text/uri-list");
-
-    if is_file {
+/// One entry of a clipboard type listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OfferedType {
+    pub mime_type: String,
+    /// How waypin would show this type on its own.
+    pub content_type: ClipboardContentType,
+}
+
+/// Parses a type listing into its entries, in the order offered, skipping
+/// blank lines and repeats.
+pub fn parse_types(types: &str) -> Vec<OfferedType> {
+    let mut offered: Vec<OfferedType> = Vec::new();
+    for mime_type in types.lines().filter(|line| !line.trim().is_empty()) {
+        if offered.iter().any(|entry| entry.mime_type == mime_type) {
+            continue;
+        }
+        offered.push(OfferedType {
+            mime_type: mime_type.to_string(),
+            content_type: classify_mime_type(mime_type),
+        });
+    }
+    offered
+}
+
+/// Whether `target` is an X11 selection target that describes the selection
+/// rather than carrying its content.
+pub fn is_metadata_target(target: &str) -> bool {
+    matches!(
+        target,
+        "TARGETS"
+            | "TIMESTAMP"
+            | "MULTIPLE"
+            | "SAVE_TARGETS"
+            | "DELETE"
+            | "INSERT_PROPERTY"
+            | "INSERT_SELECTION"
+    )
+}
+
+/// The first offered type that carries content, for showing data waypin
+/// has no viewer for.
+pub fn get_raw_format_from_types(types: &str) -> Option<&str> {
+    types
+        .lines()
+        .find(|line| !line.trim().is_empty() && !is_metadata_target(line))
+}
+
+/// The content type of a single MIME type. Unlike
+/// [`detect_clipboard_content_type`] this accepts any `text/*` type as text.
+pub fn classify_mime_type(mime_type: &str) -> ClipboardContentType {
+    if mime_type == "text/uri-list" {
         ClipboardContentType::File
-    } else if is_image {
+    } else if is_image_mime_type(mime_type) {
         ClipboardContentType::Image
-    } else if is_text {
+    } else if mime_type == "text/html" {
+        ClipboardContentType::Html
+    } else if rich_text::is_rtf_mime_type(mime_type) {
+        ClipboardContentType::Rtf
+    } else if mime_type.starts_with("text/")
+        || matches!(mime_type, "UTF8_STRING" | "STRING" | "TEXT")
+    {
         ClipboardContentType::Text
     } else {
         ClipboardContentType::Unsupported
     }
 }
 
+pub fn detect_clipboard_content_type(types: &str) -> ClipboardContentType {
+    DetectionRules::default().detect(types)
+}
+
+/// Every way the listing can be shown with the default rules, best first:
+/// file list, image, HTML, RTF, text, and finally `Unsupported` for the raw
+/// data of the first offered type.
+pub fn rank_clipboard_content_types(types: &str) -> Vec<ClipboardContentType> {
+    DetectionRules::default().rank(types)
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum ClipboardContentType {
     Text,
+    Html,
+    Rtf,
     Image,
     File,
+    /// A single color, shown as a swatch.
+    Color,
     Unsupported,
 }
 
-pub fn get_image_format_from_types(types: &str) -> Option<&'static str> {
-    if has_mime_type(types, "image/png") {
-        Some("image/png")
-    } else if has_mime_type(types, "image/jpeg") {
-        Some("image/jpeg")
-    } else if has_mime_type(types, "image/gif") {
-        Some("image/gif")
-    } else {
-        None
+impl ClipboardContentType {
+    /// Name of the viewer for this content type, as shown to the user.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClipboardContentType::Text => "Text",
+            ClipboardContentType::Html => "HTML",
+            ClipboardContentType::Rtf => "RTF",
+            ClipboardContentType::Image => "Image",
+            ClipboardContentType::File => "Files",
+            ClipboardContentType::Color => "Color",
+            ClipboardContentType::Unsupported => "Raw",
+        }
     }
 }
 
-pub fn copy_image_to_clipboard(mime_type: &str, data: &[u8]) -> Result<(), String> {
+/// Image MIME types in the order waypin prefers them when several are
+/// offered. Lossless and widely decodable formats come first; SVG is last
+/// among the known types because apps that offer it usually offer a
+/// rendered PNG too.
+pub const IMAGE_MIME_TYPES: [&str; 11] = [
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/avif",
+    "image/tiff",
+    "image/bmp",
+    "image/x-bmp",
+    "image/x-icon",
+    "image/vnd.microsoft.icon",
+    "image/svg+xml",
+];
+
+/// Whether `mime_type` is one of the [`IMAGE_MIME_TYPES`] waypin always
+/// shows as an image. [`DetectionRules`] adds the types the image loader
+/// reports it can decode.
+pub fn is_image_mime_type(mime_type: &str) -> bool {
+    IMAGE_MIME_TYPES.contains(&mime_type)
+}
+
+pub fn is_svg_mime_type(mime_type: &str) -> bool {
+    mime_type == "image/svg+xml"
+}
+
+/// Picks the image type to read: the first of [`IMAGE_MIME_TYPES`] that is
+/// offered.
+pub fn get_image_format_from_types(types: &str) -> Option<&str> {
+    DetectionRules::default().mime_type_for(types, &ClipboardContentType::Image)
+}
+
+pub fn get_text_format_from_types(types: &str) -> Option<&'static str> {
+    detect::TEXT_MIME_TYPES
+        .into_iter()
+        .find(|mime| has_mime_type(types, mime))
+}
+
+pub fn get_rtf_format_from_types(types: &str) -> Option<&'static str> {
+    rich_text::RTF_MIME_TYPES
+        .into_iter()
+        .find(|mime| has_mime_type(types, mime))
+}
+
+pub fn copy_image_to_clipboard(
+    backend: &dyn ClipboardBackend,
+    mime_type: &str,
+    data: &[u8],
+) -> Result<(), String> {
     if !mime_type.starts_with("image/") {
         return Err("Invalid image MIME type".to_string());
     }
@@ -67,27 +212,22 @@ pub fn copy_image_to_clipboard(mime_type: &str, data: &[u8]) -> Result<(), Strin
         return Err("Empty image data".to_string());
     }
     
-    use std::io::Write;
-    let mut child = std::process::Command::new("wl-copy")
-        .arg("--type")
-        .arg(mime_type)
-        .stdin(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn wl-copy: {}", e))?;
-    
-    if let Some(stdin) = child.stdin.as_mut() {
-        stdin.write_all(data)
-            .map_err(|e| format!("Failed to write image data: {}", e))?;
+    copy_to_clipboard(backend, &[(mime_type, data)])
+}
+
+/// Replaces the clipboard with `(mime_type, data)` pairs of any kind,
+/// preferred type first. Empty payloads are rejected.
+pub fn copy_to_clipboard(
+    backend: &dyn ClipboardBackend,
+    contents: &[(&str, &[u8])],
+) -> Result<(), String> {
+    if contents.is_empty() {
+        return Err("Nothing to copy".to_string());
     }
-    
-    let exit_status = child.wait()
-        .map_err(|e| format!("Failed to wait for wl-copy: {}", e))?;
-    
-    if exit_status.success() {
-        Ok(())
-    } else {
-        Err("wl-copy command failed".to_string())
+    if let Some((mime_type, _)) = contents.iter().find(|(_, data)| data.is_empty()) {
+        return Err(format!("Empty {} data", mime_type));
     }
+    backend.write(contents)
 }
 
 #[cfg(test)]
@@ -184,6 +324,18 @@ mod tests {
         assert_eq!(detect_clipboard_content_type(types), ClipboardContentType::Image);
     }
 
+    #[test]
+    fn test_detect_clipboard_content_type_rich_text() {
+        let types = "text/html\ntext/plain";
+        assert_eq!(detect_clipboard_content_type(types), ClipboardContentType::Html);
+        let types = "application/rtf\ntext/plain";
+        assert_eq!(detect_clipboard_content_type(types), ClipboardContentType::Rtf);
+
+        // HTML is preferred over RTF, and images over both
+        assert_eq!(detect_clipboard_content_type("text/rtf\ntext/html"), ClipboardContentType::Html);
+        assert_eq!(detect_clipboard_content_type("text/html\nimage/png"), ClipboardContentType::Image);
+    }
+
     #[test]
     fn test_detect_clipboard_content_type_unsupported() {
         let types = "application/octet-stream\napplication/pdf";
@@ -218,6 +370,37 @@ mod tests {
         assert_eq!(get_image_format_from_types(types), Some("image/gif"));
     }
 
+    #[test]
+    fn test_get_image_format_extended_priority() {
+        let types = "image/svg+xml\nimage/bmp\nimage/webp";
+        assert_eq!(get_image_format_from_types(types), Some("image/webp"));
+
+        let types = "image/svg+xml\nimage/tiff\nimage/avif";
+        assert_eq!(get_image_format_from_types(types), Some("image/avif"));
+
+        let types = "image/svg+xml\nimage/bmp";
+        assert_eq!(get_image_format_from_types(types), Some("image/bmp"));
+
+        // Types outside the table aren't known to decode
+        let types = "image/x-portable-pixmap\nimage/svg+xml";
+        assert_eq!(get_image_format_from_types(types), Some("image/svg+xml"));
+        assert_eq!(
+            get_image_format_from_types("text/plain\nimage/x-portable-pixmap"),
+            None
+        );
+        assert_eq!(get_image_format_from_types("image/"), None);
+    }
+
+    #[test]
+    fn test_detect_clipboard_content_type_extended_images() {
+        for mime in IMAGE_MIME_TYPES {
+            let types = format!("text/plain\n{}", mime);
+            assert_eq!(detect_clipboard_content_type(&types), ClipboardContentType::Image);
+        }
+        assert!(is_svg_mime_type("image/svg+xml"));
+        assert!(!is_svg_mime_type("image/png"));
+    }
+
     #[test]
     fn test_get_image_format_no_images() {
         assert_eq!(get_image_format_from_types("text/plain\napplication/json"), None);
@@ -230,24 +413,45 @@ mod tests {
         assert_eq!(get_image_format_from_types(types), Some("image/png"));
     }
 
+    #[test]
+    fn test_get_text_format_from_types() {
+        assert_eq!(get_text_format_from_types("text/plain"), Some("text/plain"));
+        assert_eq!(get_text_format_from_types("STRING\nUTF8_STRING"), Some("UTF8_STRING"));
+        assert_eq!(get_text_format_from_types("TEXT"), Some("TEXT"));
+        assert_eq!(get_text_format_from_types("image/png"), None);
+    }
+
+    #[test]
+    fn test_get_text_format_priority() {
+        let types = "text/plain\ntext/plain;charset=utf-8\nUTF8_STRING";
+        assert_eq!(get_text_format_from_types(types), Some("text/plain;charset=utf-8"));
+    }
+
+    #[test]
+    fn test_get_rtf_format_from_types() {
+        assert_eq!(get_rtf_format_from_types("text/plain\ntext/richtext"), Some("text/richtext"));
+        assert_eq!(get_rtf_format_from_types("application/rtf\ntext/rtf"), Some("text/rtf"));
+        assert_eq!(get_rtf_format_from_types("text/html"), None);
+    }
+
     #[test]
     fn test_copy_image_to_clipboard_validation() {
         // Test invalid MIME type
-        let result = copy_image_to_clipboard("text/plain", &[1, 2, 3]);
+        let result = copy_image_to_clipboard(&WlClipboard::default(), "text/plain", &[1, 2, 3]);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "Invalid image MIME type");
         
         // Test empty data
-        let result = copy_image_to_clipboard("image/png", &[]);
+        let result = copy_image_to_clipboard(&WlClipboard::default(), "image/png", &[]);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "Empty image data");
         
         // Test invalid image MIME types
-        let result = copy_image_to_clipboard("application/octet-stream", &[1, 2, 3]);
+        let result = copy_image_to_clipboard(&WlClipboard::default(), "application/octet-stream", &[1, 2, 3]);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "Invalid image MIME type");
         
-        let result = copy_image_to_clipboard("video/mp4", &[1, 2, 3]);
+        let result = copy_image_to_clipboard(&WlClipboard::default(), "video/mp4", &[1, 2, 3]);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "Invalid image MIME type");
     }
@@ -263,7 +467,7 @@ mod tests {
         let mime_types = vec!["image/png", "image/jpeg", "image/gif", "image/bmp", "image/webp"];
         
         for mime_type in mime_types {
-            let result = copy_image_to_clipboard(mime_type, &test_data);
+            let result = copy_image_to_clipboard(&WlClipboard::default(), mime_type, &test_data);
             // The result might be an error due to wl-copy not being available,
             // but it shouldn't be a validation error
             if let Err(err) = result {
@@ -273,6 +477,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_types() {
+        let types = "text/html\nimage/png\n\nchromium/x-web-custom-data\ntext/html\nUTF8_STRING\n";
+        let offered = parse_types(types);
+        let summary: Vec<_> = offered
+            .iter()
+            .map(|entry| (entry.mime_type.as_str(), entry.content_type.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("text/html", ClipboardContentType::Html),
+                ("image/png", ClipboardContentType::Image),
+                ("chromium/x-web-custom-data", ClipboardContentType::Unsupported),
+                ("UTF8_STRING", ClipboardContentType::Text),
+            ]
+        );
+        assert!(parse_types("").is_empty());
+    }
+
+    #[test]
+    fn test_rank_clipboard_content_types() {
+        assert_eq!(
+            rank_clipboard_content_types("text/plain\nimage/png\ntext/html"),
+            [
+                ClipboardContentType::Image,
+                ClipboardContentType::Html,
+                ClipboardContentType::Text,
+                ClipboardContentType::Unsupported,
+            ]
+        );
+        assert_eq!(
+            rank_clipboard_content_types("TARGETS\napplication/pdf"),
+            [ClipboardContentType::Unsupported]
+        );
+        assert!(rank_clipboard_content_types("TARGETS\n").is_empty());
+        assert_eq!(
+            detect_clipboard_content_type("TARGETS\n"),
+            ClipboardContentType::Unsupported
+        );
+    }
+
+    #[test]
+    fn test_get_raw_format_from_types() {
+        assert_eq!(
+            get_raw_format_from_types("TARGETS\nTIMESTAMP\napplication/pdf\ntext/plain"),
+            Some("application/pdf")
+        );
+        assert_eq!(get_raw_format_from_types("TARGETS\nMULTIPLE\n"), None);
+        assert_eq!(get_raw_format_from_types(""), None);
+    }
+
+    #[test]
+    fn test_classify_mime_type() {
+        assert_eq!(classify_mime_type("text/uri-list"), ClipboardContentType::File);
+        assert_eq!(classify_mime_type("text/rtf"), ClipboardContentType::Rtf);
+        assert_eq!(classify_mime_type("text/x-moz-url"), ClipboardContentType::Text);
+        assert_eq!(classify_mime_type("image/svg+xml"), ClipboardContentType::Image);
+        assert_eq!(
+            classify_mime_type("application/x-kde-cutselection"),
+            ClipboardContentType::Unsupported
+        );
+    }
+
     #[test]
     fn test_clipboard_content_type_clone() {
         let content_type = ClipboardContentType::Image;
@@ -324,4 +592,11 @@ mod tests {
         assert!(has_mime_type(complex_types, "text/uri-list"));
         assert!(!has_mime_type(complex_types, "image/gif"));
     }
+
+    #[test]
+    fn test_content_type_label() {
+        assert_eq!(ClipboardContentType::Image.label(), "Image");
+        assert_eq!(ClipboardContentType::File.label(), "Files");
+        assert_eq!(ClipboardContentType::Unsupported.label(), "Raw");
+    }
 }
@@ -0,0 +1,231 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::uri_list::{format_uri_list, path_to_file_uri};
+use crate::{copy_to_clipboard, ClipboardBackend, ClipboardContentType};
+
+/// MIME type text files are offered as.
+pub const TEXT_MIME_TYPE: &str = "text/plain;charset=utf-8";
+
+/// Files larger than this are offered as a file reference rather than
+/// being read to check for text.
+pub const MAX_TEXT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Bytes read to recognise an image.
+const SNIFF_LEN: usize = 1024;
+
+/// The content type of a loaded file, with its `(mime_type, data)` pairs.
+pub type FileContents = (ClipboardContentType, Vec<(String, Vec<u8>)>);
+
+/// Recognises an image from the magic bytes at the start of `data`.
+pub fn sniff_image_mime_type(data: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: [(&[u8], &str); 7] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"II*\0", "image/tiff"),
+        (b"MM\0*", "image/tiff"),
+        (b"\0\0\x01\0", "image/x-icon"),
+    ];
+    if let Some((_, mime_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| data.starts_with(signature))
+    {
+        return Some(mime_type);
+    }
+    if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        return Some("image/webp");
+    }
+    if matches!(data.get(4..12), Some(b"ftypavif" | b"ftypavis")) {
+        return Some("image/avif");
+    }
+    // "BM" alone is too common in text; check the DIB header size too
+    if data.starts_with(b"BM") && matches!(data.get(14), Some(12 | 40 | 52 | 56 | 108 | 124)) {
+        return Some("image/bmp");
+    }
+
+    // SVG is XML text whose root element is <svg>
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if (text.starts_with("<svg") || text.starts_with("<?xml") || text.starts_with("<!DOCTYPE svg"))
+        && text.contains("<svg")
+    {
+        return Some("image/svg+xml");
+    }
+    None
+}
+
+/// Whether `data` looks like text: valid UTF-8 without NUL bytes.
+pub fn is_text(data: &[u8]) -> bool {
+    !data.contains(&0) && std::str::from_utf8(data).is_ok()
+}
+
+/// The clipboard contents for a file: images with their image type, UTF-8
+/// text as [`TEXT_MIME_TYPE`], and anything else (including directories) as
+/// a `text/uri-list` pointing at the file.
+pub fn file_contents(path: &Path) -> Result<FileContents, String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    if metadata.is_file() {
+        let read_error = |e: std::io::Error| format!("Cannot read {}: {}", path.display(), e);
+        let mut file = fs::File::open(path).map_err(read_error)?;
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        file.by_ref()
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut head)
+            .map_err(read_error)?;
+        if head.is_empty() {
+            return Err(format!("{} is empty", path.display()));
+        }
+
+        if let Some(mime_type) = sniff_image_mime_type(&head) {
+            let data = fs::read(path).map_err(read_error)?;
+            return Ok((
+                ClipboardContentType::Image,
+                vec![(mime_type.to_string(), data)],
+            ));
+        }
+        if metadata.len() <= MAX_TEXT_SIZE {
+            let data = fs::read(path).map_err(read_error)?;
+            if is_text(&data) {
+                return Ok((
+                    ClipboardContentType::Text,
+                    vec![(TEXT_MIME_TYPE.to_string(), data)],
+                ));
+            }
+        }
+    }
+    files_contents(&[path.to_path_buf()])
+}
+
+/// A `text/uri-list` referring to `paths`, made absolute.
+pub fn files_contents(paths: &[PathBuf]) -> Result<FileContents, String> {
+    let uris = paths
+        .iter()
+        .map(|path| {
+            fs::canonicalize(path)
+                .map(|path| path_to_file_uri(&path))
+                .map_err(|e| format!("Cannot find {}: {}", path.display(), e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if uris.is_empty() {
+        return Err("No files to load".to_string());
+    }
+    let list = format_uri_list(uris.iter().map(String::as_str));
+    Ok((
+        ClipboardContentType::File,
+        vec![("text/uri-list".to_string(), list.into_bytes())],
+    ))
+}
+
+/// Puts `paths` on the clipboard: a single file by its content (see
+/// [`file_contents`]), several files as a `text/uri-list`. Returns the
+/// content type, so the caller can open the matching viewer.
+pub fn load_files(
+    backend: &dyn ClipboardBackend,
+    paths: &[PathBuf],
+) -> Result<ClipboardContentType, String> {
+    let (content_type, contents) = match paths {
+        [path] => file_contents(path)?,
+        paths => files_contents(paths)?,
+    };
+    let contents: Vec<(&str, &[u8])> = contents
+        .iter()
+        .map(|(mime_type, data)| (mime_type.as_str(), data.as_slice()))
+        .collect();
+    copy_to_clipboard(backend, &contents)?;
+    Ok(content_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uri_list::parse_uri_list;
+
+    #[test]
+    fn test_sniff_image_mime_type() {
+        assert_eq!(
+            sniff_image_mime_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniff_image_mime_type(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some("image/jpeg")
+        );
+        assert_eq!(sniff_image_mime_type(b"GIF89a\x01\0"), Some("image/gif"));
+        assert_eq!(
+            sniff_image_mime_type(b"RIFF\x24\0\0\0WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(
+            sniff_image_mime_type(b"\0\0\0\x1cftypavif\0\0\0\0"),
+            Some("image/avif")
+        );
+        assert_eq!(
+            sniff_image_mime_type(b"BM\x36\0\0\0\0\0\0\0\x36\0\0\0\x28\0\0\0"),
+            Some("image/bmp")
+        );
+        assert_eq!(sniff_image_mime_type(b"BMW dealers in the area"), None);
+        assert_eq!(
+            sniff_image_mime_type(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"\"/>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(sniff_image_mime_type(b"RIFF\x24\0\0\0WAVEfmt "), None);
+        assert_eq!(
+            sniff_image_mime_type(b"<?xml version=\"1.0\"?><doc/>"),
+            None
+        );
+        assert_eq!(sniff_image_mime_type(b"hello"), None);
+    }
+
+    #[test]
+    fn test_is_text() {
+        assert!(is_text("héllo\n".as_bytes()));
+        assert!(!is_text(b"bin\0ary"));
+        assert!(!is_text(b"\xff\xfe"));
+    }
+
+    #[test]
+    fn test_file_contents() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let image = dir.path().join("shot");
+        fs::write(&image, b"\x89PNG\r\n\x1a\nrest").unwrap();
+        let (content_type, contents) = file_contents(&image).unwrap();
+        assert_eq!(content_type, ClipboardContentType::Image);
+        assert_eq!(
+            contents,
+            [("image/png".to_string(), b"\x89PNG\r\n\x1a\nrest".to_vec())]
+        );
+
+        let text = dir.path().join("note.md");
+        fs::write(&text, "# Notes").unwrap();
+        let (content_type, contents) = file_contents(&text).unwrap();
+        assert_eq!(content_type, ClipboardContentType::Text);
+        assert_eq!(contents[0].0, TEXT_MIME_TYPE);
+
+        let binary = dir.path().join("data.bin");
+        fs::write(&binary, b"\0\x01\x02").unwrap();
+        let (content_type, contents) = file_contents(&binary).unwrap();
+        assert_eq!(content_type, ClipboardContentType::File);
+        assert_eq!(contents[0].0, "text/uri-list");
+        let entries = parse_uri_list(std::str::from_utf8(&contents[0].1).unwrap());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, Some(fs::canonicalize(&binary).unwrap()));
+
+        let (content_type, _) = file_contents(dir.path()).unwrap();
+        assert_eq!(content_type, ClipboardContentType::File);
+    }
+
+    #[test]
+    fn test_file_contents_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.txt");
+        fs::write(&empty, "").unwrap();
+        assert!(file_contents(&empty).unwrap_err().ends_with("is empty"));
+        assert!(file_contents(&dir.path().join("missing")).is_err());
+        assert!(files_contents(&[]).is_err());
+    }
+}
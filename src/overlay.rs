@@ -0,0 +1,137 @@
+//! Placement of viewers pinned as layer-shell overlays.
+//!
+//! A layer-shell surface has no position of its own: it is anchored to
+//! edges of the output and kept a margin away from them. Every placement is
+//! expressed as a [`Corner`] and the margins from its two edges.
+
+/// A corner of the output an overlay is anchored to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "top-left" => Ok(Self::TopLeft),
+            "top-right" => Ok(Self::TopRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => Err(format!(
+                "unknown corner \"{}\" (expected top-left, top-right, bottom-left or bottom-right)",
+                name
+            )),
+        }
+    }
+
+    pub fn is_left(self) -> bool {
+        matches!(self, Self::TopLeft | Self::BottomLeft)
+    }
+
+    pub fn is_top(self) -> bool {
+        matches!(self, Self::TopLeft | Self::TopRight)
+    }
+}
+
+/// Where an overlay opens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayPosition {
+    /// In a corner, [`OverlayConfig::margin`] away from its edges.
+    Corner(Corner),
+    /// At this offset from the top-left corner of the output.
+    At(i32, i32),
+}
+
+/// The `[overlay]` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverlayConfig {
+    /// Pin the image and text viewers, and tabbed windows with an image or
+    /// text tab, as overlays where layer-shell is available.
+    pub enabled: bool,
+    pub position: OverlayPosition,
+    /// Distance from the edges of a corner, in pixels.
+    pub margin: i32,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position: OverlayPosition::Corner(Corner::TopRight),
+            margin: 32,
+        }
+    }
+}
+
+impl OverlayConfig {
+    /// The corner to anchor to, with the horizontal and vertical margins.
+    pub fn placement(&self) -> (Corner, (i32, i32)) {
+        match self.position {
+            OverlayPosition::Corner(corner) => (corner, (self.margin, self.margin)),
+            OverlayPosition::At(x, y) => (Corner::TopLeft, (x, y)),
+        }
+    }
+}
+
+/// The margins after the pointer moved `delta` since a drag started with
+/// `margins`. Margins from the right or bottom edge shrink as the pointer
+/// moves towards that edge, and none goes below zero.
+pub fn dragged_margins(corner: Corner, margins: (i32, i32), delta: (i32, i32)) -> (i32, i32) {
+    let horizontal = if corner.is_left() {
+        margins.0 + delta.0
+    } else {
+        margins.0 - delta.0
+    };
+    let vertical = if corner.is_top() {
+        margins.1 + delta.1
+    } else {
+        margins.1 - delta.1
+    };
+    (horizontal.max(0), vertical.max(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corner_parse() {
+        assert_eq!(Corner::parse("bottom-left"), Ok(Corner::BottomLeft));
+        assert!(Corner::parse("top").is_err());
+    }
+
+    #[test]
+    fn test_placement() {
+        let config = OverlayConfig {
+            margin: 20,
+            ..OverlayConfig::default()
+        };
+        assert_eq!(config.placement(), (Corner::TopRight, (20, 20)));
+        let at = OverlayConfig {
+            position: OverlayPosition::At(100, 40),
+            ..config
+        };
+        assert_eq!(at.placement(), (Corner::TopLeft, (100, 40)));
+    }
+
+    #[test]
+    fn test_dragged_margins() {
+        // Dragging right and down moves away from the top-left corner...
+        assert_eq!(
+            dragged_margins(Corner::TopLeft, (10, 10), (5, 20)),
+            (15, 30)
+        );
+        // ...and towards the bottom-right one
+        assert_eq!(
+            dragged_margins(Corner::BottomRight, (10, 10), (5, 20)),
+            (5, 0)
+        );
+        assert_eq!(
+            dragged_margins(Corner::TopRight, (32, 32), (-8, -40)),
+            (40, 0)
+        );
+    }
+}
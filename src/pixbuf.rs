@@ -0,0 +1,106 @@
+//! Decoding and encoding images with gdk-pixbuf.
+
+use gtk::gdk_pixbuf::PixbufLoader;
+use gtk::prelude::*;
+use waypin_lib::convert::{ImageCodec, ImageFormat};
+use waypin_lib::raster::RgbaImage;
+
+pub fn pixbuf_to_rgba(pixbuf: &gtk::gdk_pixbuf::Pixbuf) -> Result<RgbaImage, String> {
+    RgbaImage::from_rows(
+        pixbuf.width() as u32,
+        pixbuf.height() as u32,
+        pixbuf.rowstride() as usize,
+        pixbuf.n_channels() as usize,
+        &pixbuf.read_pixel_bytes(),
+    )
+}
+
+pub fn rgba_to_pixbuf(image: &RgbaImage) -> gtk::gdk_pixbuf::Pixbuf {
+    gtk::gdk_pixbuf::Pixbuf::from_bytes(
+        &gtk::glib::Bytes::from(&image.data),
+        gtk::gdk_pixbuf::Colorspace::Rgb,
+        true,
+        8,
+        image.width as i32,
+        image.height as i32,
+        image.width as i32 * 4,
+    )
+}
+
+/// Image codec for transcoding, backed by gdk-pixbuf's loaders and savers.
+pub struct PixbufCodec;
+
+impl ImageCodec for PixbufCodec {
+    fn decode(&self, mime_type: &str, data: &[u8]) -> Result<RgbaImage, String> {
+        pixbuf_to_rgba(&load_image(data, mime_type, None)?)
+    }
+
+    fn encode(&self, image: &RgbaImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+        let (saver, options) = match format {
+            ImageFormat::Png => ("png", Vec::new()),
+            ImageFormat::Jpeg { quality } => ("jpeg", vec![("quality", quality.to_string())]),
+            ImageFormat::Webp => ("webp", Vec::new()),
+            ImageFormat::Bmp => ("bmp", Vec::new()),
+        };
+        let options: Vec<(&str, &str)> = options
+            .iter()
+            .map(|(key, value)| (*key, value.as_str()))
+            .collect();
+        rgba_to_pixbuf(image)
+            .save_to_bufferv(saver, &options)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Decodes image data, telling the loader its MIME type when it has a
+/// module for it. `fit` renders scalable formats such as SVG to fit that
+/// size instead of their intrinsic one.
+pub fn load_image(
+    data: &[u8],
+    mime_type: &str,
+    fit: Option<(i32, i32)>,
+) -> Result<gtk::gdk_pixbuf::Pixbuf, String> {
+    decode_image(data, mime_type, fit)?
+        .pixbuf()
+        .ok_or_else(|| "Failed to decode image data.".to_string())
+}
+
+/// Like [`load_image`], but returns the loader so animations can be read
+/// from it as well.
+pub fn decode_image(
+    data: &[u8],
+    mime_type: &str,
+    fit: Option<(i32, i32)>,
+) -> Result<PixbufLoader, String> {
+    let loader = PixbufLoader::with_mime_type(mime_type).unwrap_or_else(|_| PixbufLoader::new());
+    if let Some((max_width, max_height)) = fit {
+        loader.connect_size_prepared(move |loader, width, height| {
+            if width > 0 && height > 0 {
+                let scale = f64::min(
+                    max_width as f64 / width as f64,
+                    max_height as f64 / height as f64,
+                );
+                loader.set_size(
+                    ((width as f64 * scale).round() as i32).max(1),
+                    ((height as f64 * scale).round() as i32).max(1),
+                );
+            }
+        });
+    }
+    if loader.write(data).is_err() {
+        return Err("Failed to load image from clipboard data.".to_string());
+    }
+    if loader.close().is_err() {
+        return Err("Failed to finalize image loading.".to_string());
+    }
+    Ok(loader)
+}
+
+/// Every MIME type gdk-pixbuf has a loader for.
+pub fn pixbuf_mime_types() -> Vec<String> {
+    gtk::gdk_pixbuf::Pixbuf::formats()
+        .iter()
+        .flat_map(|format| format.mime_types())
+        .map(|mime_type| mime_type.to_string())
+        .collect()
+}
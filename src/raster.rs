@@ -0,0 +1,214 @@
+/// An 8-bit RGBA image with straight (not premultiplied) alpha, rows packed
+/// without padding. Image edits in waypin_lib work on this so they can be
+/// tested without GTK; the viewer converts to and from pixbufs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// A fully transparent image.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::filled(width, height, [0, 0, 0, 0])
+    }
+
+    pub fn filled(width: u32, height: u32, color: [u8; 4]) -> Self {
+        Self {
+            width,
+            height,
+            data: color.repeat(width as usize * height as usize),
+        }
+    }
+
+    /// Copies pixel rows that are `stride` bytes apart with 3 (RGB) or 4
+    /// (RGBA) channels, as handed out by gdk-pixbuf.
+    pub fn from_rows(
+        width: u32,
+        height: u32,
+        stride: usize,
+        channels: usize,
+        rows: &[u8],
+    ) -> Result<Self, String> {
+        if channels != 3 && channels != 4 {
+            return Err(format!("Unsupported channel count: {}", channels));
+        }
+        let row_len = width as usize * channels;
+        if stride < row_len {
+            return Err("Row stride is shorter than a row".to_string());
+        }
+        if height > 0 && rows.len() < stride * (height as usize - 1) + row_len {
+            return Err("Pixel data is shorter than the image".to_string());
+        }
+
+        let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height as usize {
+            let row = &rows[y * stride..y * stride + row_len];
+            for pixel in row.chunks_exact(channels) {
+                data.extend_from_slice(&pixel[..3]);
+                data.push(if channels == 4 { pixel[3] } else { 255 });
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Converts a Cairo `ARGB32` surface (native-endian, premultiplied).
+    pub fn from_premultiplied_argb32(
+        width: u32,
+        height: u32,
+        stride: usize,
+        rows: &[u8],
+    ) -> Result<Self, String> {
+        let mut image = Self::from_rows(width, height, stride, 4, rows)?;
+        for pixel in image.data.chunks_exact_mut(4) {
+            let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            let alpha = (argb >> 24) as u8;
+            let unpremultiply = |channel: u32| -> u8 {
+                let channel = (channel & 0xff) as u16;
+                match alpha {
+                    0 => 0,
+                    alpha => ((channel * 255 + alpha as u16 / 2) / alpha as u16).min(255) as u8,
+                }
+            };
+            pixel.copy_from_slice(&[
+                unpremultiply(argb >> 16),
+                unpremultiply(argb >> 8),
+                unpremultiply(argb),
+                alpha,
+            ]);
+        }
+        Ok(image)
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        (y as usize * self.width as usize + x as usize) * 4
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = self.offset(x, y);
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.data[offset..offset + 4]);
+        pixel
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: [u8; 4]) {
+        let offset = self.offset(x, y);
+        self.data[offset..offset + 4].copy_from_slice(&color);
+    }
+
+    /// Draws `color` over the pixel at `(x, y)` with `coverage` from 0 to 1;
+    /// pixels outside the image are ignored.
+    pub fn blend_pixel(&mut self, x: i64, y: i64, color: [u8; 4], coverage: f64) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let (x, y) = (x as u32, y as u32);
+        let source_alpha = color[3] as f64 / 255.0 * coverage.clamp(0.0, 1.0);
+        if source_alpha <= 0.0 {
+            return;
+        }
+        let dest = self.pixel(x, y);
+        let dest_alpha = dest[3] as f64 / 255.0;
+        let alpha = source_alpha + dest_alpha * (1.0 - source_alpha);
+        let mut blended = [0u8; 4];
+        for channel in 0..3 {
+            let value = (color[channel] as f64 * source_alpha
+                + dest[channel] as f64 * dest_alpha * (1.0 - source_alpha))
+                / alpha;
+            blended[channel] = value.round().clamp(0.0, 255.0) as u8;
+        }
+        blended[3] = (alpha * 255.0).round() as u8;
+        self.set_pixel(x, y, blended);
+    }
+
+    /// This image composited onto an opaque `background` color.
+    pub fn over_background(&self, background: [u8; 3]) -> RgbaImage {
+        let mut opaque = RgbaImage::filled(
+            self.width,
+            self.height,
+            [background[0], background[1], background[2], 255],
+        );
+        opaque.draw_image(self, 0, 0);
+        opaque
+    }
+
+    /// Draws `other` over this image with its top-left corner at `(x, y)`.
+    pub fn draw_image(&mut self, other: &RgbaImage, x: i64, y: i64) {
+        for oy in 0..other.height {
+            for ox in 0..other.width {
+                let pixel = other.pixel(ox, oy);
+                let color = [pixel[0], pixel[1], pixel[2], 255];
+                self.blend_pixel(x + ox as i64, y + oy as i64, color, pixel[3] as f64 / 255.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_rows_rgb_with_padding() {
+        // 2x2 RGB with two padding bytes per row
+        let rows = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0];
+        let image = RgbaImage::from_rows(2, 2, 8, 3, &rows).unwrap();
+        assert_eq!(image.pixel(1, 0), [4, 5, 6, 255]);
+        assert_eq!(image.pixel(0, 1), [7, 8, 9, 255]);
+        assert_eq!(image.data.len(), 16);
+    }
+
+    #[test]
+    fn test_from_rows_rejects_short_data() {
+        assert!(RgbaImage::from_rows(2, 2, 8, 4, &[0; 8]).is_err());
+        assert!(RgbaImage::from_rows(2, 2, 4, 4, &[0; 16]).is_err());
+        assert!(RgbaImage::from_rows(1, 1, 4, 2, &[0; 4]).is_err());
+    }
+
+    #[test]
+    fn test_from_premultiplied_argb32() {
+        let half_red = u32::to_ne_bytes(0x80_80_00_00);
+        let clear = u32::to_ne_bytes(0);
+        let rows: Vec<u8> = [half_red, clear].concat();
+        let image = RgbaImage::from_premultiplied_argb32(2, 1, 8, &rows).unwrap();
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 128]);
+        assert_eq!(image.pixel(1, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_blend_pixel() {
+        let mut image = RgbaImage::filled(2, 1, [0, 0, 255, 255]);
+        image.blend_pixel(0, 0, [255, 0, 0, 255], 1.0);
+        image.blend_pixel(1, 0, [255, 0, 0, 255], 0.5);
+        image.blend_pixel(5, 0, [255, 0, 0, 255], 1.0);
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 0), [128, 0, 128, 255]);
+
+        let mut clear = RgbaImage::new(1, 1);
+        clear.blend_pixel(0, 0, [10, 20, 30, 255], 0.5);
+        assert_eq!(clear.pixel(0, 0), [10, 20, 30, 128]);
+    }
+
+    #[test]
+    fn test_over_background() {
+        let mut image = RgbaImage::new(2, 1);
+        image.set_pixel(1, 0, [0, 0, 0, 255]);
+        let opaque = image.over_background([255, 255, 255]);
+        assert_eq!(opaque.pixel(0, 0), [255, 255, 255, 255]);
+        assert_eq!(opaque.pixel(1, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_draw_image_clips() {
+        let mut image = RgbaImage::filled(2, 2, [0, 0, 0, 255]);
+        let stamp = RgbaImage::filled(2, 2, [255, 255, 255, 255]);
+        image.draw_image(&stamp, 1, 1);
+        assert_eq!(image.pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 1), [255, 255, 255, 255]);
+    }
+}
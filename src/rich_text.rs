@@ -0,0 +1,779 @@
+/// Formatting applied to a range of converted text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextStyle {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Monospace,
+    /// A heading, with its level from 1 to 6.
+    Heading(u8),
+    /// A link to the given target.
+    Link(String),
+}
+
+/// A styled range, in character (not byte) offsets into the text so it can
+/// be applied to a GTK text buffer directly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyledSpan {
+    pub start: usize,
+    pub end: usize,
+    pub style: TextStyle,
+}
+
+/// Plain text plus the formatting that was recovered from the markup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyledText {
+    pub text: String,
+    pub spans: Vec<StyledSpan>,
+}
+
+/// The MIME types waypin reads RTF from, most specific first.
+pub const RTF_MIME_TYPES: [&str; 3] = ["text/rtf", "application/rtf", "text/richtext"];
+
+pub fn is_rtf_mime_type(mime_type: &str) -> bool {
+    RTF_MIME_TYPES.contains(&mime_type)
+}
+
+/// Converts markup of the given MIME type: RTF for the RTF types, HTML
+/// otherwise.
+pub fn markup_to_styled_text(mime_type: &str, markup: &str) -> StyledText {
+    if is_rtf_mime_type(mime_type) {
+        rtf_to_styled_text(markup)
+    } else {
+        html_to_styled_text(markup)
+    }
+}
+
+/// Accumulates text while tracking the character offset, collapsing
+/// whitespace the way a browser would outside `<pre>`.
+#[derive(Default)]
+struct TextBuilder {
+    text: String,
+    chars: usize,
+    pending_space: bool,
+}
+
+impl TextBuilder {
+    fn push_str(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.push(ch);
+        }
+    }
+
+    fn push(&mut self, ch: char) {
+        self.text.push(ch);
+        self.chars += 1;
+    }
+
+    fn push_collapsed(&mut self, text: &str) {
+        for ch in text.chars() {
+            if ch.is_whitespace() && ch != '\u{a0}' {
+                self.pending_space = true;
+                continue;
+            }
+            self.flush_space();
+            self.push(ch);
+        }
+    }
+
+    /// Writes a space collapsed before an element, so the element's span
+    /// starts at its first visible character.
+    fn flush_space(&mut self) {
+        if self.pending_space && !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.push(' ');
+        }
+        self.pending_space = false;
+    }
+
+    fn line_break(&mut self) {
+        self.pending_space = false;
+        self.push('\n');
+    }
+
+    /// Starts a new line unless already at the start of one.
+    fn ensure_line(&mut self) {
+        self.pending_space = false;
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.push('\n');
+        }
+    }
+
+    /// Leaves one empty line between blocks.
+    fn ensure_blank_line(&mut self) {
+        self.ensure_line();
+        if !self.text.is_empty() && !self.text.ends_with("\n\n") {
+            self.push('\n');
+        }
+    }
+
+    fn finish(self, mut spans: Vec<StyledSpan>) -> StyledText {
+        let text = self.text.trim_end().to_string();
+        let len = text.chars().count();
+        spans.retain_mut(|span| {
+            span.end = span.end.min(len);
+            span.start < span.end
+        });
+        spans.sort_by_key(|span| span.start);
+        StyledText { text, spans }
+    }
+}
+
+enum ListKind {
+    Unordered,
+    Ordered(usize),
+}
+
+/// Converts an HTML fragment into text with bold, italic, underline, code,
+/// heading and link spans. Lists become bulleted or numbered lines, block
+/// elements become line breaks, and scripts, styles and comments are
+/// dropped. The `Version:`/`StartHTML:` header of Windows clipboard HTML is
+/// skipped.
+pub fn html_to_styled_text(html: &str) -> StyledText {
+    let mut html = html;
+    if html.starts_with("Version:") {
+        html = &html[html.find('<').unwrap_or(html.len())..];
+    }
+
+    let mut out = TextBuilder::default();
+    let mut spans = Vec::new();
+    // Open elements with the offset where they started and their style
+    let mut open: Vec<(String, usize, Option<TextStyle>)> = Vec::new();
+    let mut lists: Vec<ListKind> = Vec::new();
+    let mut preformatted = 0usize;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            push_html_text(&mut out, rest, preformatted > 0);
+            break;
+        };
+        push_html_text(&mut out, &rest[..lt], preformatted > 0);
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment
+                .find("-->")
+                .map_or("", |end| &comment[end + "-->".len()..]);
+            continue;
+        }
+        let Some(gt) = rest.find('>') else {
+            push_html_text(&mut out, rest, preformatted > 0);
+            break;
+        };
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+
+        let closing = tag.starts_with('/');
+        let tag = tag.trim_start_matches('/');
+        let self_closing = tag.ends_with('/');
+        let name_end = tag
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+        let attributes = &tag[name_end..];
+
+        if closing {
+            if let Some(index) = open.iter().rposition(|(open_name, ..)| *open_name == name) {
+                for (open_name, start, style) in open.drain(index..).rev() {
+                    if let Some(style) = style {
+                        spans.push(StyledSpan {
+                            start,
+                            end: out.chars,
+                            style,
+                        });
+                    }
+                    close_block(&mut out, &mut lists, &mut preformatted, &open_name);
+                }
+            }
+            continue;
+        }
+
+        match name.as_str() {
+            "script" | "style" | "title" | "head" if !self_closing => {
+                let close = format!("</{}", name);
+                rest = find_ignore_case(rest, &close).map_or("", |end| {
+                    let after = &rest[end..];
+                    after.find('>').map_or("", |gt| &after[gt + 1..])
+                });
+                continue;
+            }
+            "br" => {
+                out.line_break();
+                continue;
+            }
+            "hr" => {
+                out.ensure_line();
+                out.push_str("――――――――――");
+                out.line_break();
+                continue;
+            }
+            "img" => {
+                if let Some(alt) = attribute(attributes, "alt").filter(|alt| !alt.is_empty()) {
+                    out.push_collapsed(&format!("[{}]", decode_entities(&alt)));
+                }
+                continue;
+            }
+            "p" | "div" | "blockquote" | "table" | "section" | "article" | "header" | "footer"
+            | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => out.ensure_blank_line(),
+            "tr" | "dt" | "dd" => out.ensure_line(),
+            "td" | "th" if !out.text.is_empty() && !out.text.ends_with('\n') => out.push('\t'),
+            "pre" => {
+                out.ensure_blank_line();
+                preformatted += 1;
+            }
+            "ul" => {
+                out.ensure_line();
+                lists.push(ListKind::Unordered);
+            }
+            "ol" => {
+                out.ensure_line();
+                let start = attribute(attributes, "start")
+                    .and_then(|start| start.trim().parse().ok())
+                    .unwrap_or(1usize);
+                lists.push(ListKind::Ordered(start));
+            }
+            "li" => {
+                out.ensure_line();
+                out.push_str(&"    ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(ListKind::Ordered(number)) => {
+                        out.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => out.push_str("• "),
+                }
+            }
+            _ => {}
+        }
+
+        let style = match name.as_str() {
+            "b" | "strong" => Some(TextStyle::Bold),
+            "i" | "em" | "cite" | "var" => Some(TextStyle::Italic),
+            "u" | "ins" => Some(TextStyle::Underline),
+            "s" | "strike" | "del" => Some(TextStyle::Strikethrough),
+            "code" | "tt" | "kbd" | "samp" | "pre" => Some(TextStyle::Monospace),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                Some(TextStyle::Heading(name.as_bytes()[1] - b'0'))
+            }
+            "a" => {
+                attribute(attributes, "href").map(|href| TextStyle::Link(decode_entities(&href)))
+            }
+            _ => None,
+        };
+        if self_closing || is_void_element(&name) {
+            continue;
+        }
+        if style.is_some() {
+            out.flush_space();
+        }
+        open.push((name, out.chars, style));
+    }
+
+    for (name, start, style) in open.into_iter().rev() {
+        if let Some(style) = style {
+            spans.push(StyledSpan {
+                start,
+                end: out.chars,
+                style,
+            });
+        }
+        close_block(&mut out, &mut lists, &mut preformatted, &name);
+    }
+
+    out.finish(spans)
+}
+
+fn close_block(
+    out: &mut TextBuilder,
+    lists: &mut Vec<ListKind>,
+    preformatted: &mut usize,
+    name: &str,
+) {
+    match name {
+        "p" | "div" | "blockquote" | "table" | "section" | "article" | "header" | "footer"
+        | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => out.ensure_blank_line(),
+        "pre" => {
+            *preformatted = preformatted.saturating_sub(1);
+            out.ensure_blank_line();
+        }
+        "ul" | "ol" => {
+            lists.pop();
+            if lists.is_empty() {
+                out.ensure_blank_line();
+            } else {
+                out.ensure_line();
+            }
+        }
+        "li" | "tr" => out.ensure_line(),
+        _ => {}
+    }
+}
+
+fn push_html_text(out: &mut TextBuilder, text: &str, preformatted: bool) {
+    let text = decode_entities(text);
+    if preformatted {
+        out.push_str(&text);
+    } else {
+        out.push_collapsed(&text);
+    }
+}
+
+fn is_void_element(name: &str) -> bool {
+    matches!(
+        name,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Reads an attribute value from the part of a tag after its name.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return None;
+        }
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let body = &after[1..];
+                        let end = body.find(quote).unwrap_or(body.len());
+                        rest = body.get(end + 1..).unwrap_or_default();
+                        &body[..end]
+                    }
+                    _ => {
+                        let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                        rest = &after[end..];
+                        &after[..end]
+                    }
+                }
+            }
+            None => "",
+        };
+        if key.eq_ignore_ascii_case(name) {
+            return Some(value.to_string());
+        }
+    }
+}
+
+/// Replaces the common named entities and numeric character references.
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end + 1])?, end + 2)));
+        match entity {
+            Some((ch, len)) => {
+                decoded.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bull" => '•',
+        "middot" => '·',
+        "euro" => '€',
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct RtfFormat {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strike: bool,
+}
+
+#[derive(Clone, Copy)]
+struct RtfGroup {
+    format: RtfFormat,
+    skip: bool,
+    unicode_skip: usize,
+}
+
+/// Converts RTF into text with bold, italic, underline and strikethrough
+/// spans. Font, colour and style tables, pictures and other destinations
+/// are skipped; `\uN` and `\'hh` escapes are decoded (the latter as
+/// Windows-1252's Latin-1 subset).
+pub fn rtf_to_styled_text(rtf: &str) -> StyledText {
+    let mut out = TextBuilder::default();
+    let mut spans = Vec::new();
+    let mut group = RtfGroup {
+        format: RtfFormat::default(),
+        skip: false,
+        unicode_skip: 1,
+    };
+    let mut stack = Vec::new();
+    // Where each style's current span started
+    let mut started: [Option<usize>; 4] = [None; 4];
+    // Fallback characters still to drop after a `\uN`
+    let mut fallback = 0usize;
+    let chars: Vec<char> = rtf.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '{' => {
+                stack.push(group);
+                fallback = 0;
+                i += 1;
+                // `{\*\dest ...}` marks a destination readers may ignore
+                if chars.get(i) == Some(&'\\') && chars.get(i + 1) == Some(&'*') {
+                    group.skip = true;
+                }
+            }
+            '}' => {
+                group = stack.pop().unwrap_or(group);
+                fallback = 0;
+                i += 1;
+            }
+            '\\' => {
+                i += 1;
+                let Some(&next) = chars.get(i) else {
+                    break;
+                };
+                if next.is_ascii_alphabetic() {
+                    let word_start = i;
+                    while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let word: String = chars[word_start..i].iter().collect();
+                    let number_start = i;
+                    if chars.get(i) == Some(&'-') {
+                        i += 1;
+                    }
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    let parameter: Option<i32> = chars[number_start..i]
+                        .iter()
+                        .collect::<String>()
+                        .parse()
+                        .ok();
+                    if chars.get(i) == Some(&' ') {
+                        i += 1;
+                    }
+
+                    let enabled = parameter != Some(0);
+                    match word.as_str() {
+                        "fonttbl" | "colortbl" | "stylesheet" | "info" | "pict" | "fldinst"
+                        | "header" | "footer" | "object" | "listtable" | "listoverridetable"
+                        | "generator" | "xmlnstbl" => group.skip = true,
+                        _ if group.skip => {}
+                        "b" => group.format.bold = enabled,
+                        "i" => group.format.italic = enabled,
+                        "ul" => group.format.underline = enabled,
+                        "ulnone" => group.format.underline = false,
+                        "strike" => group.format.strike = enabled,
+                        "plain" => group.format = RtfFormat::default(),
+                        "par" | "line" => {
+                            sync_rtf_spans(&mut spans, &mut started, group.format, out.chars);
+                            out.push('\n');
+                        }
+                        "tab" => out.push('\t'),
+                        "emdash" => out.push('—'),
+                        "endash" => out.push('–'),
+                        "bullet" => out.push('•'),
+                        "lquote" => out.push('‘'),
+                        "rquote" => out.push('’'),
+                        "ldblquote" => out.push('“'),
+                        "rdblquote" => out.push('”'),
+                        "uc" => group.unicode_skip = parameter.unwrap_or(1).max(0) as usize,
+                        "u" => {
+                            if let Some(code) = parameter {
+                                // Values above 32767 are written as negative numbers
+                                let code = if code < 0 { code + 65536 } else { code } as u32;
+                                sync_rtf_spans(&mut spans, &mut started, group.format, out.chars);
+                                out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                                fallback = group.unicode_skip;
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                i += 1;
+                let literal = match next {
+                    '\'' => {
+                        let hex: String = chars.get(i..i + 2).unwrap_or_default().iter().collect();
+                        match u8::from_str_radix(&hex, 16) {
+                            Ok(byte) => {
+                                i += 2;
+                                Some(byte as char)
+                            }
+                            Err(_) => None,
+                        }
+                    }
+                    '~' => Some('\u{a0}'),
+                    '_' => Some('‑'),
+                    '-' => None,
+                    '*' => {
+                        group.skip = true;
+                        None
+                    }
+                    '\n' | '\r' => Some('\n'),
+                    other => Some(other),
+                };
+                if let Some(ch) = literal {
+                    if fallback > 0 {
+                        fallback -= 1;
+                    } else if !group.skip {
+                        sync_rtf_spans(&mut spans, &mut started, group.format, out.chars);
+                        out.push(ch);
+                    }
+                }
+            }
+            '\r' | '\n' => i += 1,
+            ch => {
+                i += 1;
+                if fallback > 0 {
+                    fallback -= 1;
+                } else if !group.skip {
+                    sync_rtf_spans(&mut spans, &mut started, group.format, out.chars);
+                    out.push(ch);
+                }
+            }
+        }
+    }
+
+    sync_rtf_spans(&mut spans, &mut started, RtfFormat::default(), out.chars);
+    out.finish(spans)
+}
+
+/// Opens and closes spans so they match `format` from `offset` on.
+fn sync_rtf_spans(
+    spans: &mut Vec<StyledSpan>,
+    started: &mut [Option<usize>; 4],
+    format: RtfFormat,
+    offset: usize,
+) {
+    let active = [format.bold, format.italic, format.underline, format.strike];
+    let styles = [
+        TextStyle::Bold,
+        TextStyle::Italic,
+        TextStyle::Underline,
+        TextStyle::Strikethrough,
+    ];
+    for ((started, active), style) in started.iter_mut().zip(active).zip(styles) {
+        match (*started, active) {
+            (None, true) => *started = Some(offset),
+            (Some(start), false) => {
+                spans.push(StyledSpan {
+                    start,
+                    end: offset,
+                    style,
+                });
+                *started = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: usize, end: usize, style: TextStyle) -> StyledSpan {
+        StyledSpan { start, end, style }
+    }
+
+    fn styled(text: &StyledText, style: &TextStyle) -> Vec<String> {
+        text.spans
+            .iter()
+            .filter(|span| &span.style == style)
+            .map(|span| {
+                text.text
+                    .chars()
+                    .skip(span.start)
+                    .take(span.end - span.start)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_html_inline_formatting() {
+        let text = html_to_styled_text("<b>Bold</b> and <i>it<u>al</u>ic</i>");
+        assert_eq!(text.text, "Bold and italic");
+        assert_eq!(
+            text.spans,
+            vec![
+                span(0, 4, TextStyle::Bold),
+                span(9, 15, TextStyle::Italic),
+                span(11, 13, TextStyle::Underline),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_links_and_entities() {
+        let text = html_to_styled_text(
+            r#"See <a class=x href="https://example.com/?a=1&amp;b=2">the&nbsp;docs</a> &lt;here&gt; &#233;&#x263A;"#,
+        );
+        assert_eq!(text.text, "See the\u{a0}docs <here> é☺");
+        assert_eq!(
+            text.spans,
+            vec![span(
+                4,
+                12,
+                TextStyle::Link("https://example.com/?a=1&b=2".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_html_whitespace_and_blocks() {
+        let text = html_to_styled_text("<p>One\n   two</p><p>Three<br>four</p>");
+        assert_eq!(text.text, "One two\n\nThree\nfour");
+    }
+
+    #[test]
+    fn test_html_lists() {
+        let text = html_to_styled_text(
+            "<ul><li>Apple</li><li>Pear<ol start=3><li>x</li><li>y</li></ol></li></ul>After",
+        );
+        assert_eq!(text.text, "• Apple\n• Pear\n    3. x\n    4. y\n\nAfter");
+    }
+
+    #[test]
+    fn test_html_headings_and_code() {
+        let text = html_to_styled_text("<h2>Title</h2><pre>a  b\n c</pre>");
+        assert_eq!(text.text, "Title\n\na  b\n c");
+        assert_eq!(styled(&text, &TextStyle::Heading(2)), ["Title"]);
+        assert_eq!(styled(&text, &TextStyle::Monospace), ["a  b\n c"]);
+    }
+
+    #[test]
+    fn test_html_skips_scripts_comments_and_windows_header() {
+        let html = "Version:0.9\r\nStartHTML:0000000105\r\n<html><head><style>b{}</style></head>\
+                    <body><!--StartFragment--><script>alert(1)</script>Hi<!--EndFragment--></body></html>";
+        assert_eq!(html_to_styled_text(html).text, "Hi");
+    }
+
+    #[test]
+    fn test_html_unclosed_tags() {
+        let text = html_to_styled_text("<meta charset='utf-8'><b>Bold <i>both");
+        assert_eq!(text.text, "Bold both");
+        assert_eq!(styled(&text, &TextStyle::Bold), ["Bold both"]);
+        assert_eq!(styled(&text, &TextStyle::Italic), ["both"]);
+    }
+
+    #[test]
+    fn test_decode_entities_keeps_unknown() {
+        assert_eq!(
+            decode_entities("a & b &bogus; &#xZZ;"),
+            "a & b &bogus; &#xZZ;"
+        );
+    }
+
+    #[test]
+    fn test_attribute() {
+        assert_eq!(
+            attribute(r#" href='a b' id=x"#, "href"),
+            Some("a b".to_string())
+        );
+        assert_eq!(attribute(r#" HREF="a""#, "href"), Some("a".to_string()));
+        assert_eq!(attribute(" disabled id=x", "id"), Some("x".to_string()));
+        assert_eq!(attribute(" id=x", "href"), None);
+    }
+
+    #[test]
+    fn test_rtf_formatting() {
+        let rtf = r"{\rtf1\ansi{\fonttbl{\f0 Arial;}}{\colortbl;\red0\green0\blue0;}\f0 Plain \b bold\b0  {\i ital}\par \ul under\ulnone}";
+        let text = rtf_to_styled_text(rtf);
+        assert_eq!(text.text, "Plain bold ital\nunder");
+        assert_eq!(styled(&text, &TextStyle::Bold), ["bold"]);
+        assert_eq!(styled(&text, &TextStyle::Italic), ["ital"]);
+        assert_eq!(styled(&text, &TextStyle::Underline), ["under"]);
+    }
+
+    #[test]
+    fn test_rtf_escapes() {
+        let rtf = r"{\rtf1 caf\'e9 \u8364? {\*\generator Writer;}\{x\}\tab y\uc2\u-3913??}";
+        assert_eq!(rtf_to_styled_text(rtf).text, "café € {x}\ty\u{f0b7}");
+    }
+
+    #[test]
+    fn test_markup_to_styled_text_picks_parser() {
+        assert_eq!(
+            markup_to_styled_text("text/rtf", r"{\rtf1 \b x}")
+                .spans
+                .len(),
+            1
+        );
+        assert_eq!(markup_to_styled_text("text/html", "<b>x</b>").text, "x");
+        assert!(is_rtf_mime_type("application/rtf"));
+        assert!(!is_rtf_mime_type("text/html"));
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::rich_text::is_rtf_mime_type;
+use crate::snapshot::ClipboardSnapshot;
+use crate::ClipboardContentType;
+
+/// File extension, without the dot, for content of `mime_type`.
+pub fn extension_for_mime_type(mime_type: &str) -> &'static str {
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+    match essence {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/tiff" => "tiff",
+        "image/bmp" | "image/x-bmp" => "bmp",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "image/svg+xml" => "svg",
+        "text/html" => "html",
+        rtf if is_rtf_mime_type(rtf) => "rtf",
+        // X11 text targets have no MIME type
+        "UTF8_STRING" | "STRING" | "TEXT" => "txt",
+        text if text.starts_with("text/") => "txt",
+        _ => "bin",
+    }
+}
+
+/// A `YYYYMMDD-HHMMSS` timestamp (UTC) for `secs` since the Unix epoch.
+pub fn timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rest = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+/// The default file name for saving content of `mime_type`, like
+/// `clipboard-20250101-120000.png`.
+pub fn default_file_name(mime_type: &str, secs: u64) -> String {
+    format!(
+        "clipboard-{}.{}",
+        timestamp(secs),
+        extension_for_mime_type(mime_type)
+    )
+}
+
+/// Looks up `key` (like `XDG_PICTURES_DIR`) in the contents of a
+/// `user-dirs.dirs` file, expanding a leading `$HOME`.
+pub fn parse_user_dirs(contents: &str, key: &str, home: &Path) -> Option<PathBuf> {
+    contents.lines().find_map(|line| {
+        let (name, value) = line.trim().split_once('=')?;
+        if name.trim() != key {
+            return None;
+        }
+        let value = value.trim().trim_matches('"');
+        let path = match value.strip_prefix("$HOME") {
+            Some(rest) => home.join(rest.trim_start_matches('/')),
+            None => PathBuf::from(value),
+        };
+        // xdg-user-dirs disables a directory by pointing it at $HOME
+        (path.is_absolute() && path != home).then_some(path)
+    })
+}
+
+/// The XDG user directory `key`, from the environment or
+/// `$XDG_CONFIG_HOME/user-dirs.dirs`.
+pub fn user_dir(key: &str) -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(key).map(PathBuf::from) {
+        if dir.is_absolute() {
+            return Some(dir);
+        }
+    }
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| home.join(".config"));
+    let contents = fs::read_to_string(config_home.join("user-dirs.dirs")).ok()?;
+    parse_user_dirs(&contents, key, &home)
+}
+
+/// Where to save content by default: the pictures directory for images,
+/// the documents directory otherwise, falling back to the home directory.
+pub fn default_save_dir(content_type: ClipboardContentType) -> Option<PathBuf> {
+    let key = match content_type {
+        ClipboardContentType::Image => "XDG_PICTURES_DIR",
+        _ => "XDG_DOCUMENTS_DIR",
+    };
+    user_dir(key)
+        .filter(|dir| dir.is_dir())
+        .or_else(|| std::env::var_os("HOME").map(PathBuf::from))
+}
+
+/// Writes the snapshot's payload to `path`, or to a timestamped file in
+/// `path` when it is a directory, and returns the path written.
+pub fn save_snapshot(
+    snapshot: &ClipboardSnapshot,
+    path: &Path,
+    secs: u64,
+) -> Result<PathBuf, String> {
+    let mime_type = match (&snapshot.content_type, &snapshot.mime_type) {
+        (ClipboardContentType::Unsupported, _) | (_, None) => {
+            return Err("Clipboard content is not supported for saving.".to_string())
+        }
+        (_, Some(mime_type)) => mime_type,
+    };
+    if snapshot.payload.is_empty() {
+        return Err(format!("No {} data in clipboard.", mime_type));
+    }
+
+    let path = if path.is_dir() {
+        path.join(default_file_name(mime_type, secs))
+    } else {
+        path.to_path_buf()
+    };
+    fs::write(&path, &snapshot.payload)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_for_mime_type() {
+        assert_eq!(extension_for_mime_type("image/png"), "png");
+        assert_eq!(extension_for_mime_type("image/jpeg"), "jpg");
+        assert_eq!(extension_for_mime_type("image/svg+xml"), "svg");
+        assert_eq!(extension_for_mime_type("text/html"), "html");
+        assert_eq!(extension_for_mime_type("text/rtf"), "rtf");
+        assert_eq!(extension_for_mime_type("text/plain;charset=utf-8"), "txt");
+        assert_eq!(extension_for_mime_type("UTF8_STRING"), "txt");
+        assert_eq!(extension_for_mime_type("text/uri-list"), "txt");
+        assert_eq!(extension_for_mime_type("application/x-thing"), "bin");
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0), "19700101-000000");
+        // 2000-02-29 is a leap day
+        assert_eq!(timestamp(951_827_696), "20000229-123456");
+        assert_eq!(timestamp(1_735_689_599), "20241231-235959");
+        assert_eq!(
+            default_file_name("image/png", 1_735_689_600),
+            "clipboard-20250101-000000.png"
+        );
+    }
+
+    #[test]
+    fn test_parse_user_dirs() {
+        let contents = "# written by xdg-user-dirs-update\n\
+                        XDG_DESKTOP_DIR=\"$HOME/Desktop\"\n\
+                        XDG_PICTURES_DIR=\"$HOME/Pictures\"\n\
+                        XDG_DOCUMENTS_DIR=\"/srv/docs\"\n\
+                        XDG_MUSIC_DIR=\"$HOME/\"\n";
+        let home = Path::new("/home/user");
+        assert_eq!(
+            parse_user_dirs(contents, "XDG_PICTURES_DIR", home),
+            Some(PathBuf::from("/home/user/Pictures"))
+        );
+        assert_eq!(
+            parse_user_dirs(contents, "XDG_DOCUMENTS_DIR", home),
+            Some(PathBuf::from("/srv/docs"))
+        );
+        assert_eq!(parse_user_dirs(contents, "XDG_MUSIC_DIR", home), None);
+        assert_eq!(parse_user_dirs(contents, "XDG_VIDEOS_DIR", home), None);
+    }
+
+    fn snapshot(
+        content_type: ClipboardContentType,
+        mime: &str,
+        payload: &[u8],
+    ) -> ClipboardSnapshot {
+        ClipboardSnapshot {
+            types: mime.to_string(),
+            content_type,
+            mime_type: Some(mime.to_string()),
+            payload: payload.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_save_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = snapshot(ClipboardContentType::Image, "image/png", b"\x89PNG");
+
+        // A directory gets a timestamped file name
+        let saved = save_snapshot(&image, dir.path(), 0).unwrap();
+        assert_eq!(saved, dir.path().join("clipboard-19700101-000000.png"));
+        assert_eq!(fs::read(&saved).unwrap(), b"\x89PNG");
+
+        let text = snapshot(ClipboardContentType::Text, "text/plain", b"hello");
+        let path = dir.path().join("note.txt");
+        assert_eq!(save_snapshot(&text, &path, 0).unwrap(), path);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_save_snapshot_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = snapshot(ClipboardContentType::Text, "text/plain", b"");
+        assert_eq!(
+            save_snapshot(&empty, dir.path(), 0).unwrap_err(),
+            "No text/plain data in clipboard."
+        );
+        let unsupported = snapshot(ClipboardContentType::Unsupported, "x/y", b"data");
+        assert!(save_snapshot(&unsupported, dir.path(), 0).is_err());
+        let text = snapshot(ClipboardContentType::Text, "text/plain", b"hello");
+        assert!(save_snapshot(&text, &dir.path().join("missing/file.txt"), 0).is_err());
+    }
+}
@@ -0,0 +1,552 @@
+use std::fmt;
+
+use crate::cli::ViewerKind;
+use crate::color::parse_color;
+use crate::detect::DetectionRules;
+use crate::rich_text::is_rtf_mime_type;
+use crate::uri_list::{parse_uri_list, UriEntry};
+use crate::{ClipboardBackend, ClipboardContentType};
+
+/// Everything waypin read from the clipboard in one go: the offered types,
+/// how they were classified, and the payload of the MIME type it chose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardSnapshot {
+    pub types: String,
+    pub content_type: ClipboardContentType,
+    pub mime_type: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+impl ClipboardSnapshot {
+    /// Lists the clipboard types and reads the payload that the viewer will
+    /// need. `mime_type` and `viewer` carry the `--type` and
+    /// `--text`/`--image` overrides.
+    pub fn capture(
+        backend: &dyn ClipboardBackend,
+        mime_type: Option<&str>,
+        viewer: Option<ViewerKind>,
+    ) -> Result<Self, String> {
+        Self::capture_with_rules(backend, mime_type, viewer, &DetectionRules::default())
+    }
+
+    /// [`capture`](Self::capture), detecting the content with `rules`.
+    pub fn capture_with_rules(
+        backend: &dyn ClipboardBackend,
+        mime_type: Option<&str>,
+        viewer: Option<ViewerKind>,
+        rules: &DetectionRules,
+    ) -> Result<Self, String> {
+        let types = backend.list_types().unwrap_or_default();
+        if types.trim().is_empty() {
+            return Err("Could not retrieve clipboard types or clipboard is empty.".to_string());
+        }
+
+        let (content_type, mime_type) = rules.choose_payload(&types, mime_type, viewer);
+        let payload = mime_type
+            .as_deref()
+            .and_then(|mime| backend.read(mime).ok())
+            .unwrap_or_default();
+
+        Ok(Self {
+            types,
+            content_type,
+            mime_type,
+            payload,
+        })
+    }
+
+    /// A snapshot for every representation of the same listing, in the
+    /// order `rules` rank them. This snapshot's payload is reused; the
+    /// others are read from the backend, and left out if the read fails.
+    pub fn alternatives(
+        &self,
+        backend: &dyn ClipboardBackend,
+        rules: &DetectionRules,
+    ) -> Vec<Self> {
+        rules
+            .rank(&self.types)
+            .into_iter()
+            .filter_map(|content_type| {
+                let mime_type = rules.mime_type_for(&self.types, &content_type)?;
+                if content_type == self.content_type && self.mime_type.as_deref() == Some(mime_type)
+                {
+                    return Some(self.clone());
+                }
+                Some(Self {
+                    types: self.types.clone(),
+                    payload: backend.read(mime_type).ok()?,
+                    mime_type: Some(mime_type.to_string()),
+                    content_type,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Picks the content type and the MIME type to read for a type listing,
+/// with the default [`DetectionRules`].
+///
+/// An explicit MIME type selects the viewer from its family (`image/*`,
+/// HTML, RTF or text) unless a viewer is forced as well.
+pub fn choose_payload(
+    types: &str,
+    mime_type: Option<&str>,
+    viewer: Option<ViewerKind>,
+) -> (ClipboardContentType, Option<String>) {
+    DetectionRules::default().choose_payload(types, mime_type, viewer)
+}
+
+/// The MIME type read to show the listing as `content_type`, with the
+/// default [`DetectionRules`].
+pub fn mime_type_for_content_type<'a>(
+    types: &'a str,
+    content_type: &ClipboardContentType,
+) -> Option<&'a str> {
+    DetectionRules::default().mime_type_for(types, content_type)
+}
+
+/// The viewer to open, with the data it shows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewerRequest {
+    Text(String),
+    Image {
+        mime_type: String,
+        data: Vec<u8>,
+    },
+    /// HTML or RTF markup, shown formatted.
+    RichText {
+        mime_type: String,
+        markup: String,
+    },
+    Files(Vec<UriEntry>),
+    /// A color, shown as a swatch.
+    Color {
+        mime_type: String,
+        data: Vec<u8>,
+        rgba: [u8; 4],
+    },
+    /// Data of a type waypin has no viewer for, shown as a hex dump.
+    Binary {
+        mime_type: String,
+        data: Vec<u8>,
+    },
+}
+
+impl ViewerRequest {
+    /// A short name for the representation, used for tab titles.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ViewerRequest::Text(_) => "Text",
+            ViewerRequest::Image { .. } => "Image",
+            ViewerRequest::RichText { mime_type, .. } if is_rtf_mime_type(mime_type) => "RTF",
+            ViewerRequest::RichText { .. } => "HTML",
+            ViewerRequest::Files(_) => "Files",
+            ViewerRequest::Color { .. } => "Color",
+            ViewerRequest::Binary { .. } => "Raw",
+        }
+    }
+}
+
+/// Why a snapshot cannot be shown.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DispatchError {
+    EmptyPayload(ClipboardContentType),
+    Unsupported,
+}
+
+impl DispatchError {
+    /// Whether waypin should exit with a failure status; an empty payload is
+    /// reported but not treated as an error.
+    pub fn is_failure(&self) -> bool {
+        matches!(self, DispatchError::Unsupported)
+    }
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DispatchError::EmptyPayload(ClipboardContentType::Image) => write!(
+                f,
+                "No supported image found in clipboard or reading it failed."
+            ),
+            DispatchError::EmptyPayload(ClipboardContentType::File) => {
+                write!(f, "No files found in clipboard or reading them failed.")
+            }
+            DispatchError::EmptyPayload(ClipboardContentType::Unsupported) => {
+                write!(f, "No data found in clipboard or reading it failed.")
+            }
+            DispatchError::EmptyPayload(_) => {
+                write!(f, "No text found in clipboard or reading it failed.")
+            }
+            DispatchError::Unsupported => write!(
+                f,
+                "Clipboard does not contain supported image or text types."
+            ),
+        }
+    }
+}
+
+/// Decides which viewer a snapshot opens.
+pub fn dispatch(snapshot: &ClipboardSnapshot) -> Result<ViewerRequest, DispatchError> {
+    match (&snapshot.content_type, &snapshot.mime_type) {
+        (_, None) => Err(DispatchError::Unsupported),
+        (content_type, Some(_)) if snapshot.payload.is_empty() => {
+            Err(DispatchError::EmptyPayload(content_type.clone()))
+        }
+        (ClipboardContentType::Image, Some(mime_type)) => Ok(ViewerRequest::Image {
+            mime_type: mime_type.clone(),
+            data: snapshot.payload.clone(),
+        }),
+        (ClipboardContentType::Text, Some(_)) => Ok(ViewerRequest::Text(
+            String::from_utf8_lossy(&snapshot.payload).into_owned(),
+        )),
+        (ClipboardContentType::Html | ClipboardContentType::Rtf, Some(mime_type)) => {
+            Ok(ViewerRequest::RichText {
+                mime_type: mime_type.clone(),
+                markup: String::from_utf8_lossy(&snapshot.payload).into_owned(),
+            })
+        }
+        (ClipboardContentType::Color, Some(mime_type)) => {
+            Ok(match parse_color(mime_type, &snapshot.payload) {
+                Some(rgba) => ViewerRequest::Color {
+                    mime_type: mime_type.clone(),
+                    data: snapshot.payload.clone(),
+                    rgba,
+                },
+                // Data that isn't a color we understand is still shown raw
+                None => ViewerRequest::Binary {
+                    mime_type: mime_type.clone(),
+                    data: snapshot.payload.clone(),
+                },
+            })
+        }
+        (ClipboardContentType::Unsupported, Some(mime_type)) => Ok(ViewerRequest::Binary {
+            mime_type: mime_type.clone(),
+            data: snapshot.payload.clone(),
+        }),
+        (ClipboardContentType::File, Some(_)) => {
+            let entries = parse_uri_list(&String::from_utf8_lossy(&snapshot.payload));
+            if entries.is_empty() {
+                Err(DispatchError::EmptyPayload(ClipboardContentType::File))
+            } else {
+                Ok(ViewerRequest::Files(entries))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::FakeClipboard;
+
+    fn snapshot(
+        content_type: ClipboardContentType,
+        mime: Option<&str>,
+        payload: &[u8],
+    ) -> ClipboardSnapshot {
+        ClipboardSnapshot {
+            types: String::new(),
+            content_type,
+            mime_type: mime.map(str::to_string),
+            payload: payload.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_choose_payload_detects() {
+        assert_eq!(
+            choose_payload("image/jpeg\nimage/png", None, None),
+            (ClipboardContentType::Image, Some("image/png".to_string()))
+        );
+        assert_eq!(
+            choose_payload("text/plain\nUTF8_STRING", None, None),
+            (ClipboardContentType::Text, Some("UTF8_STRING".to_string()))
+        );
+        assert_eq!(
+            choose_payload("text/uri-list\ntext/plain", None, None),
+            (
+                ClipboardContentType::File,
+                Some("text/uri-list".to_string())
+            )
+        );
+        assert_eq!(
+            choose_payload("text/plain\ntext/html", None, None),
+            (ClipboardContentType::Html, Some("text/html".to_string()))
+        );
+        assert_eq!(
+            choose_payload("text/plain\napplication/rtf", None, None),
+            (
+                ClipboardContentType::Rtf,
+                Some("application/rtf".to_string())
+            )
+        );
+        assert_eq!(
+            choose_payload("TARGETS\napplication/pdf", None, None),
+            (
+                ClipboardContentType::Unsupported,
+                Some("application/pdf".to_string())
+            )
+        );
+        assert_eq!(
+            choose_payload("TARGETS\nTIMESTAMP", None, None),
+            (ClipboardContentType::Unsupported, None)
+        );
+    }
+
+    #[test]
+    fn test_choose_payload_forced_viewer() {
+        let types = "image/png\ntext/plain";
+        assert_eq!(
+            choose_payload(types, None, Some(ViewerKind::Text)),
+            (ClipboardContentType::Text, Some("text/plain".to_string()))
+        );
+        assert_eq!(
+            choose_payload("text/plain", None, Some(ViewerKind::Image)),
+            (ClipboardContentType::Image, None)
+        );
+    }
+
+    #[test]
+    fn test_choose_payload_forced_mime_type() {
+        assert_eq!(
+            choose_payload("text/html\ntext/plain", Some("text/html"), None),
+            (ClipboardContentType::Html, Some("text/html".to_string()))
+        );
+        assert_eq!(
+            choose_payload("text/rtf", Some("text/rtf"), None),
+            (ClipboardContentType::Rtf, Some("text/rtf".to_string()))
+        );
+        assert_eq!(
+            choose_payload("text/html", Some("text/html"), Some(ViewerKind::Text)),
+            (ClipboardContentType::Text, Some("text/html".to_string()))
+        );
+        assert_eq!(
+            choose_payload("image/webp", Some("image/webp"), None),
+            (ClipboardContentType::Image, Some("image/webp".to_string()))
+        );
+        assert_eq!(
+            choose_payload("image/png", Some("image/png"), Some(ViewerKind::Text)),
+            (ClipboardContentType::Text, Some("image/png".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_capture_reads_chosen_payload() {
+        let backend = FakeClipboard::new(
+            "text/plain\nimage/png",
+            &[("image/png", &b"png"[..]), ("text/plain", &b"alt"[..])],
+        );
+        let snapshot = ClipboardSnapshot::capture(&backend, None, None).unwrap();
+        assert_eq!(snapshot.content_type, ClipboardContentType::Image);
+        assert_eq!(snapshot.mime_type.as_deref(), Some("image/png"));
+        assert_eq!(snapshot.payload, b"png");
+        assert_eq!(snapshot.types, "text/plain\nimage/png");
+    }
+
+    #[test]
+    fn test_alternatives() {
+        let backend = FakeClipboard::new(
+            "text/plain\nimage/png",
+            &[("image/png", &b"png"[..]), ("text/plain", &b"alt"[..])],
+        );
+        let snapshot = ClipboardSnapshot::capture(&backend, None, None).unwrap();
+        let requests: Vec<_> = snapshot
+            .alternatives(&backend, &DetectionRules::default())
+            .iter()
+            .map(|alternative| dispatch(alternative).unwrap())
+            .collect();
+        assert_eq!(
+            requests,
+            [
+                ViewerRequest::Image {
+                    mime_type: "image/png".to_string(),
+                    data: b"png".to_vec(),
+                },
+                ViewerRequest::Text("alt".to_string()),
+                ViewerRequest::Binary {
+                    mime_type: "text/plain".to_string(),
+                    data: b"alt".to_vec(),
+                },
+            ]
+        );
+        let labels: Vec<_> = requests.iter().map(ViewerRequest::label).collect();
+        assert_eq!(labels, ["Image", "Text", "Raw"]);
+    }
+
+    #[test]
+    fn test_alternatives_skip_failed_reads() {
+        let backend = FakeClipboard::new("text/plain\nimage/png", &[("image/png", &b"png"[..])]);
+        let snapshot = ClipboardSnapshot::capture(&backend, None, None).unwrap();
+        let alternatives = snapshot.alternatives(&backend, &DetectionRules::default());
+        assert_eq!(alternatives, [snapshot]);
+    }
+
+    #[test]
+    fn test_capture_empty_clipboard() {
+        let backend = FakeClipboard::new("\n", &[]);
+        let err = ClipboardSnapshot::capture(&backend, None, None).unwrap_err();
+        assert!(err.contains("clipboard is empty"));
+    }
+
+    #[test]
+    fn test_capture_failed_read_leaves_payload_empty() {
+        let backend = FakeClipboard::new("text/plain", &[]);
+        let snapshot = ClipboardSnapshot::capture(&backend, None, None).unwrap();
+        assert!(snapshot.payload.is_empty());
+        assert_eq!(
+            dispatch(&snapshot),
+            Err(DispatchError::EmptyPayload(ClipboardContentType::Text))
+        );
+    }
+
+    #[test]
+    fn test_dispatch_text_and_image() {
+        let text = snapshot(
+            ClipboardContentType::Text,
+            Some("text/plain"),
+            "héllo".as_bytes(),
+        );
+        assert_eq!(
+            dispatch(&text),
+            Ok(ViewerRequest::Text("héllo".to_string()))
+        );
+
+        let image = snapshot(ClipboardContentType::Image, Some("image/gif"), b"GIF8");
+        assert_eq!(
+            dispatch(&image),
+            Ok(ViewerRequest::Image {
+                mime_type: "image/gif".to_string(),
+                data: b"GIF8".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_dispatch_rich_text() {
+        let html = snapshot(ClipboardContentType::Html, Some("text/html"), b"<b>x</b>");
+        assert_eq!(
+            dispatch(&html),
+            Ok(ViewerRequest::RichText {
+                mime_type: "text/html".to_string(),
+                markup: "<b>x</b>".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_dispatch_file_list() {
+        let files = snapshot(
+            ClipboardContentType::File,
+            Some("text/uri-list"),
+            b"# comment\r\nfile:///a\r\n",
+        );
+        assert_eq!(
+            dispatch(&files),
+            Ok(ViewerRequest::Files(vec![UriEntry::parse("file:///a")]))
+        );
+
+        let comments_only = snapshot(
+            ClipboardContentType::File,
+            Some("text/uri-list"),
+            b"# nothing\r\n",
+        );
+        let err = dispatch(&comments_only).unwrap_err();
+        assert_eq!(err, DispatchError::EmptyPayload(ClipboardContentType::File));
+        assert!(!err.is_failure());
+    }
+
+    #[test]
+    fn test_dispatch_errors() {
+        let unsupported = snapshot(ClipboardContentType::Unsupported, None, b"");
+        assert_eq!(dispatch(&unsupported), Err(DispatchError::Unsupported));
+        assert!(DispatchError::Unsupported.is_failure());
+
+        let no_image = snapshot(ClipboardContentType::Image, None, b"");
+        assert_eq!(dispatch(&no_image), Err(DispatchError::Unsupported));
+
+        let empty_binary = snapshot(
+            ClipboardContentType::Unsupported,
+            Some("application/pdf"),
+            b"",
+        );
+        assert_eq!(
+            dispatch(&empty_binary),
+            Err(DispatchError::EmptyPayload(
+                ClipboardContentType::Unsupported
+            ))
+        );
+    }
+
+    #[test]
+    fn test_dispatch_color() {
+        let color = snapshot(
+            ClipboardContentType::Color,
+            Some("text/x-color"),
+            b"#3584e4",
+        );
+        assert_eq!(
+            dispatch(&color),
+            Ok(ViewerRequest::Color {
+                mime_type: "text/x-color".to_string(),
+                data: b"#3584e4".to_vec(),
+                rgba: [0x35, 0x84, 0xe4, 0xff],
+            })
+        );
+        let not_a_color = snapshot(ClipboardContentType::Color, Some("text/x-color"), b"blue");
+        assert!(matches!(
+            dispatch(&not_a_color),
+            Ok(ViewerRequest::Binary { .. })
+        ));
+    }
+
+    #[test]
+    fn test_capture_with_rules() {
+        let backend = FakeClipboard::new(
+            "text/plain\nimage/png",
+            &[("image/png", &b"png"[..]), ("text/plain", &b"alt"[..])],
+        );
+        let rules = DetectionRules {
+            priority: vec![ClipboardContentType::Text],
+            ..DetectionRules::default()
+        };
+        let snapshot = ClipboardSnapshot::capture_with_rules(&backend, None, None, &rules).unwrap();
+        assert_eq!(snapshot.content_type, ClipboardContentType::Text);
+        assert_eq!(snapshot.payload, b"alt");
+        let labels: Vec<_> = snapshot
+            .alternatives(&backend, &rules)
+            .iter()
+            .map(|alternative| dispatch(alternative).unwrap().label())
+            .collect();
+        assert_eq!(labels, ["Text", "Image", "Raw"]);
+    }
+
+    #[test]
+    fn test_dispatch_binary() {
+        let binary = snapshot(
+            ClipboardContentType::Unsupported,
+            Some("application/pdf"),
+            b"%PDF-1.7",
+        );
+        assert_eq!(
+            dispatch(&binary),
+            Ok(ViewerRequest::Binary {
+                mime_type: "application/pdf".to_string(),
+                data: b"%PDF-1.7".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_dispatch_error_messages() {
+        assert!(DispatchError::EmptyPayload(ClipboardContentType::File)
+            .to_string()
+            .contains("files"));
+        assert!(DispatchError::EmptyPayload(ClipboardContentType::Image)
+            .to_string()
+            .contains("image"));
+        assert!(DispatchError::EmptyPayload(ClipboardContentType::Text)
+            .to_string()
+            .contains("text"));
+    }
+}
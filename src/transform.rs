@@ -0,0 +1,167 @@
+use crate::annotate::{clip_rect, Point};
+use crate::raster::RgbaImage;
+
+/// A change to the geometry of an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    /// Keeps the area between two corners, clipped to the image.
+    Crop {
+        from: Point,
+        to: Point,
+    },
+    RotateClockwise,
+    RotateCounterclockwise,
+    Rotate180,
+    /// Mirrors left and right.
+    FlipHorizontal,
+    /// Mirrors top and bottom.
+    FlipVertical,
+}
+
+impl Transform {
+    pub fn apply(&self, image: &RgbaImage) -> RgbaImage {
+        match self {
+            Transform::Crop { from, to } => match clip_rect(image, *from, *to) {
+                Some((x, y, width, height)) => crop(image, x, y, width, height),
+                None => image.clone(),
+            },
+            Transform::RotateClockwise => rotate_clockwise(image),
+            Transform::RotateCounterclockwise => rotate_counterclockwise(image),
+            Transform::Rotate180 => rotate_180(image),
+            Transform::FlipHorizontal => flip_horizontal(image),
+            Transform::FlipVertical => flip_vertical(image),
+        }
+    }
+}
+
+/// Builds a `width` x `height` image where each pixel is copied from the
+/// source pixel `source(x, y)` returns.
+fn remap(
+    width: u32,
+    height: u32,
+    image: &RgbaImage,
+    source: impl Fn(u32, u32) -> (u32, u32),
+) -> RgbaImage {
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let (sx, sy) = source(x, y);
+            data.extend_from_slice(&image.pixel(sx, sy));
+        }
+    }
+    RgbaImage {
+        width,
+        height,
+        data,
+    }
+}
+
+/// The `width` x `height` area at `(x, y)`, clipped to the image.
+pub fn crop(image: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> RgbaImage {
+    let x = x.min(image.width);
+    let y = y.min(image.height);
+    let width = width.min(image.width - x);
+    let height = height.min(image.height - y);
+    remap(width, height, image, |cx, cy| (x + cx, y + cy))
+}
+
+pub fn rotate_clockwise(image: &RgbaImage) -> RgbaImage {
+    let height = image.height;
+    remap(image.height, image.width, image, |x, y| (y, height - 1 - x))
+}
+
+pub fn rotate_counterclockwise(image: &RgbaImage) -> RgbaImage {
+    let width = image.width;
+    remap(image.height, image.width, image, |x, y| (width - 1 - y, x))
+}
+
+pub fn rotate_180(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = (image.width, image.height);
+    remap(width, height, image, |x, y| (width - 1 - x, height - 1 - y))
+}
+
+pub fn flip_horizontal(image: &RgbaImage) -> RgbaImage {
+    let width = image.width;
+    remap(image.width, image.height, image, |x, y| (width - 1 - x, y))
+}
+
+pub fn flip_vertical(image: &RgbaImage) -> RgbaImage {
+    let height = image.height;
+    remap(image.width, image.height, image, |x, y| (x, height - 1 - y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3x2 image whose pixels hold their own index in the red channel:
+    ///
+    /// ```text
+    /// 0 1 2
+    /// 3 4 5
+    /// ```
+    fn numbered() -> RgbaImage {
+        let mut image = RgbaImage::new(3, 2);
+        for y in 0..2 {
+            for x in 0..3 {
+                image.set_pixel(x, y, [(y * 3 + x) as u8, 0, 0, 255]);
+            }
+        }
+        image
+    }
+
+    fn rows(image: &RgbaImage) -> Vec<Vec<u8>> {
+        (0..image.height)
+            .map(|y| (0..image.width).map(|x| image.pixel(x, y)[0]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_crop() {
+        let image = numbered();
+        assert_eq!(rows(&crop(&image, 1, 0, 2, 2)), [[1, 2], [4, 5]]);
+        // Clipped to the image
+        assert_eq!(rows(&crop(&image, 2, 1, 5, 5)), [[5]]);
+        assert_eq!(crop(&image, 9, 9, 1, 1).data.len(), 0);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let image = numbered();
+        assert_eq!(rows(&rotate_clockwise(&image)), [[3, 0], [4, 1], [5, 2]]);
+        assert_eq!(
+            rows(&rotate_counterclockwise(&image)),
+            [[2, 5], [1, 4], [0, 3]]
+        );
+        assert_eq!(rows(&rotate_180(&image)), [[5, 4, 3], [2, 1, 0]]);
+        assert_eq!(rotate_counterclockwise(&rotate_clockwise(&image)), image);
+        assert_eq!(
+            rotate_clockwise(&rotate_clockwise(&image)),
+            rotate_180(&image)
+        );
+    }
+
+    #[test]
+    fn test_flip() {
+        let image = numbered();
+        assert_eq!(rows(&flip_horizontal(&image)), [[2, 1, 0], [5, 4, 3]]);
+        assert_eq!(rows(&flip_vertical(&image)), [[3, 4, 5], [0, 1, 2]]);
+        assert_eq!(flip_vertical(&flip_horizontal(&image)), rotate_180(&image));
+    }
+
+    #[test]
+    fn test_transform_crop_uses_corners() {
+        let image = numbered();
+        let crop = Transform::Crop {
+            from: Point::new(3.0, 2.0),
+            to: Point::new(1.0, 0.0),
+        };
+        assert_eq!(rows(&crop.apply(&image)), [[1, 2], [4, 5]]);
+
+        let empty = Transform::Crop {
+            from: Point::new(1.0, 1.0),
+            to: Point::new(1.0, 1.0),
+        };
+        assert_eq!(empty.apply(&image), image);
+    }
+}
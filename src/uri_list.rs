@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+
+/// One URI from a `text/uri-list` payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UriEntry {
+    /// The URI exactly as it appeared in the list.
+    pub uri: String,
+    /// The decoded local path, for `file:` URIs.
+    pub path: Option<PathBuf>,
+    /// The host of a `file://host/` URI, when it is not the local machine.
+    pub host: Option<String>,
+}
+
+impl UriEntry {
+    pub fn parse(uri: &str) -> Self {
+        let (path, host) = match file_uri_to_path(uri) {
+            Some((path, host)) => (Some(path), host),
+            None => (None, None),
+        };
+        Self {
+            uri: uri.to_string(),
+            path,
+            host,
+        }
+    }
+
+    /// Whether the entry points at a file on this machine.
+    pub fn is_local(&self) -> bool {
+        self.path.is_some() && self.host.is_none()
+    }
+
+    /// The path when there is one, otherwise the URI.
+    pub fn display_name(&self) -> String {
+        match (&self.path, &self.host) {
+            (Some(path), None) => path.display().to_string(),
+            (Some(path), Some(host)) => format!("{}:{}", host, path.display()),
+            (None, _) => self.uri.clone(),
+        }
+    }
+}
+
+/// Parses a `text/uri-list` payload (RFC 2483): one URI per line, with
+/// `#` comment lines and blank lines ignored.
+pub fn parse_uri_list(text: &str) -> Vec<UriEntry> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(UriEntry::parse)
+        .collect()
+}
+
+/// Builds a `text/uri-list` payload from URIs, with the CRLF line endings
+/// the format asks for.
+pub fn format_uri_list<'a>(uris: impl IntoIterator<Item = &'a str>) -> String {
+    uris.into_iter().map(|uri| format!("{}\r\n", uri)).collect()
+}
+
+/// Decodes `%XX` escapes. Malformed escapes are kept literally.
+pub fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).and_then(|hex| {
+                std::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            });
+            if let Some(byte) = hex {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    decoded
+}
+
+/// Converts a `file:` URI into a path and, for `file://host/path` with a
+/// host other than `localhost`, that host. Accepts `file:///path`,
+/// `file://localhost/path`, `file://host/path` and the short `file:/path`.
+pub fn file_uri_to_path(uri: &str) -> Option<(PathBuf, Option<String>)> {
+    let scheme_end = uri.find(':')?;
+    if !uri[..scheme_end].eq_ignore_ascii_case("file") {
+        return None;
+    }
+    let rest = &uri[scheme_end + 1..];
+    // Fragments and queries are not part of the path
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+
+    let (host, path) = match rest.strip_prefix("//") {
+        Some(authority_and_path) => {
+            let slash = authority_and_path.find('/')?;
+            let host = &authority_and_path[..slash];
+            (host, &authority_and_path[slash..])
+        }
+        None if rest.starts_with('/') => ("", rest),
+        None => return None,
+    };
+    let host = match host {
+        "" | "localhost" => None,
+        host if host.eq_ignore_ascii_case("localhost") => None,
+        host => Some(host.to_string()),
+    };
+
+    Some((bytes_to_path(percent_decode(path)), host))
+}
+
+/// Builds a `file://` URI for an absolute `path`, percent-encoding every
+/// byte outside the unreserved set and `/`.
+pub fn path_to_file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path_to_bytes(path).iter() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+#[cfg(unix)]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Formats a byte count for display, e.g. `512 B` or `1.5 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_uri_list_skips_comments_and_blank_lines() {
+        let entries = parse_uri_list("# copied\r\nfile:///tmp/a.txt\r\n\r\nfile:///tmp/b\r\n");
+        let uris: Vec<_> = entries.iter().map(|entry| entry.uri.as_str()).collect();
+        assert_eq!(uris, ["file:///tmp/a.txt", "file:///tmp/b"]);
+        assert_eq!(entries[0].path.as_deref(), Some(Path::new("/tmp/a.txt")));
+        assert!(entries[0].is_local());
+    }
+
+    #[test]
+    fn test_parse_uri_list_keeps_other_schemes() {
+        let entries = parse_uri_list("https://example.com/a%20b");
+        assert_eq!(entries[0].path, None);
+        assert!(!entries[0].is_local());
+        assert_eq!(entries[0].display_name(), "https://example.com/a%20b");
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b"), b"a b");
+        assert_eq!(percent_decode("%C3%A9t%c3%a9"), "été".as_bytes());
+        assert_eq!(percent_decode("100%"), b"100%");
+        assert_eq!(percent_decode("%zz%4"), b"%zz%4");
+    }
+
+    #[test]
+    fn test_file_uri_forms() {
+        let local = Some((PathBuf::from("/home/me/My File.txt"), None));
+        assert_eq!(file_uri_to_path("file:///home/me/My%20File.txt"), local);
+        assert_eq!(
+            file_uri_to_path("file://localhost/home/me/My%20File.txt"),
+            local
+        );
+        assert_eq!(file_uri_to_path("FILE:/home/me/My%20File.txt"), local);
+        assert_eq!(
+            file_uri_to_path("file://nas/share/x"),
+            Some((PathBuf::from("/share/x"), Some("nas".to_string())))
+        );
+        assert_eq!(
+            file_uri_to_path("file:///tmp/a#frag"),
+            Some((PathBuf::from("/tmp/a"), None))
+        );
+        assert_eq!(file_uri_to_path("file:relative"), None);
+        assert_eq!(file_uri_to_path("file://host-only"), None);
+        assert_eq!(file_uri_to_path("http://x/y"), None);
+    }
+
+    #[test]
+    fn test_remote_file_display_name() {
+        let entry = UriEntry::parse("file://nas/share/x");
+        assert!(!entry.is_local());
+        assert_eq!(entry.display_name(), "nas:/share/x");
+    }
+
+    #[test]
+    fn test_format_uri_list_round_trip() {
+        let list = format_uri_list(["file:///a", "file:///b%20c"]);
+        assert_eq!(list, "file:///a\r\nfile:///b%20c\r\n");
+        assert_eq!(parse_uri_list(&list).len(), 2);
+    }
+
+    #[test]
+    fn test_path_to_file_uri() {
+        let uri = path_to_file_uri(Path::new("/home/user/My Photo #1.png"));
+        assert_eq!(uri, "file:///home/user/My%20Photo%20%231.png");
+        let (path, host) = file_uri_to_path(&uri).unwrap();
+        assert_eq!(path, PathBuf::from("/home/user/My Photo #1.png"));
+        assert_eq!(host, None);
+        assert_eq!(
+            path_to_file_uri(Path::new("/tmp/résumé.txt")),
+            "file:///tmp/r%C3%A9sum%C3%A9.txt"
+        );
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
@@ -0,0 +1,313 @@
+//! The zoomable image area of the image viewer.
+
+use gtk::prelude::*;
+use gtk::Adjustment;
+use gtk::{Image, ScrolledWindow};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use waypin_lib::annotate::Point;
+use waypin_lib::zoom::{
+    anchored_offset, clamp_zoom, scaled_size, uses_nearest_neighbor, zoom_in, zoom_out, Zoom,
+};
+
+use crate::pixbuf::load_image;
+
+/// The zoomable image area of the viewer: the frame being shown, how it is
+/// zoomed, and the widgets it is drawn into.
+pub struct ImageCanvas {
+    image: Image,
+    pub scrolled: ScrolledWindow,
+    frame: RefCell<gtk::gdk_pixbuf::Pixbuf>,
+    /// SVG source, rendered at each size instead of scaling pixels
+    svg_data: RefCell<Option<Vec<u8>>>,
+    zoom: Cell<Zoom>,
+    /// Size of the area a fitted image fills
+    area: Cell<(i32, i32)>,
+    /// Size of the last render, so relayouts don't scale the image again
+    rendered: Cell<Option<(i32, i32)>>,
+    /// Pointer position and scroll offsets where a pan started
+    pan_start: Cell<Option<(f64, f64, f64, f64)>>,
+    /// Scale when a pinch started
+    pinch_start: Cell<f64>,
+    pinch: RefCell<Option<gtk::GestureZoom>>,
+    on_zoom_changed: RefCell<Option<ZoomCallback>>,
+    /// Receives drags on the image instead of panning, while editing
+    pointer_handler: RefCell<Option<PointerHandler>>,
+    /// Whether the pointer handler saw the press of the current drag
+    pointer_down: Cell<bool>,
+}
+
+type ZoomCallback = std::boxed::Box<dyn Fn(f64)>;
+type PointerHandler = Rc<dyn Fn(PointerEvent, Point)>;
+
+/// A step of a drag on the image, passed to the pointer handler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerEvent {
+    Press,
+    Motion,
+    Release,
+}
+
+impl ImageCanvas {
+    pub fn new(frame: gtk::gdk_pixbuf::Pixbuf, svg_data: Option<Vec<u8>>) -> Rc<Self> {
+        let image = Image::new();
+        image.set_hexpand(true);
+        image.set_vexpand(true);
+
+        // The event box receives the clicks for panning and the scrolls
+        // for zooming
+        let event_box = gtk::EventBox::new();
+        event_box.add_events(
+            gtk::gdk::EventMask::BUTTON_PRESS_MASK
+                | gtk::gdk::EventMask::BUTTON_RELEASE_MASK
+                | gtk::gdk::EventMask::POINTER_MOTION_MASK
+                | gtk::gdk::EventMask::SCROLL_MASK
+                | gtk::gdk::EventMask::SMOOTH_SCROLL_MASK,
+        );
+        event_box.add(&image);
+
+        // Scrolled window for panning
+        let scrolled = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+        scrolled.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+        scrolled.add(&event_box);
+
+        let area = (frame.width(), frame.height());
+        let canvas = Rc::new(Self {
+            image,
+            scrolled,
+            frame: RefCell::new(frame),
+            svg_data: RefCell::new(svg_data),
+            zoom: Cell::new(Zoom::Fit),
+            area: Cell::new(area),
+            rendered: Cell::new(None),
+            pan_start: Cell::new(None),
+            pinch_start: Cell::new(1.0),
+            pinch: RefCell::new(None),
+            on_zoom_changed: RefCell::new(None),
+            pointer_handler: RefCell::new(None),
+            pointer_down: Cell::new(false),
+        });
+
+        // Ctrl+scroll zooms around the pointer
+        let canvas_for_scroll = canvas.clone();
+        event_box.connect_scroll_event(move |_, event| {
+            if !event.state().contains(gtk::gdk::ModifierType::CONTROL_MASK) {
+                return false.into();
+            }
+            let zooming_in = match event.direction() {
+                gtk::gdk::ScrollDirection::Up => true,
+                gtk::gdk::ScrollDirection::Down => false,
+                gtk::gdk::ScrollDirection::Smooth if event.delta().1 != 0.0 => {
+                    event.delta().1 < 0.0
+                }
+                _ => return true.into(),
+            };
+            let scale = canvas_for_scroll.scale();
+            let scale = if zooming_in {
+                zoom_in(scale)
+            } else {
+                zoom_out(scale)
+            };
+            canvas_for_scroll.set_zoom(Zoom::Scale(scale), Some(event.position()));
+            true.into()
+        });
+
+        // Dragging pans when the image is larger than the window; otherwise
+        // the press reaches the window, which moves itself
+        let canvas_for_press = canvas.clone();
+        event_box.connect_button_press_event(move |_, event| {
+            if event.button() != 1 {
+                return false.into();
+            }
+            if canvas_for_press.send_pointer(PointerEvent::Press, event.position()) {
+                canvas_for_press.pointer_down.set(true);
+                return true.into();
+            }
+            if !canvas_for_press.can_pan() {
+                return false.into();
+            }
+            let (x, y) = event.root();
+            canvas_for_press.pan_start.set(Some((
+                x,
+                y,
+                canvas_for_press.scrolled.hadjustment().value(),
+                canvas_for_press.scrolled.vadjustment().value(),
+            )));
+            true.into()
+        });
+        let canvas_for_motion = canvas.clone();
+        event_box.connect_motion_notify_event(move |_, event| {
+            if canvas_for_motion.pointer_down.get() {
+                canvas_for_motion.send_pointer(PointerEvent::Motion, event.position());
+                return true.into();
+            }
+            if let Some((start_x, start_y, h, v)) = canvas_for_motion.pan_start.get() {
+                let (x, y) = event.root();
+                canvas_for_motion
+                    .scrolled
+                    .hadjustment()
+                    .set_value(h - (x - start_x));
+                canvas_for_motion
+                    .scrolled
+                    .vadjustment()
+                    .set_value(v - (y - start_y));
+            }
+            false.into()
+        });
+        let canvas_for_release = canvas.clone();
+        event_box.connect_button_release_event(move |_, event| {
+            if canvas_for_release.pointer_down.replace(false) {
+                canvas_for_release.send_pointer(PointerEvent::Release, event.position());
+                return true.into();
+            }
+            canvas_for_release.pan_start.set(None);
+            false.into()
+        });
+
+        // Pinch to zoom on touchpads and touchscreens
+        let pinch = gtk::GestureZoom::new(&canvas.scrolled);
+        let canvas_for_pinch_begin = canvas.clone();
+        pinch.connect_begin(move |_, _| {
+            canvas_for_pinch_begin
+                .pinch_start
+                .set(canvas_for_pinch_begin.scale());
+        });
+        let canvas_for_pinch = canvas.clone();
+        pinch.connect_scale_changed(move |_, scale| {
+            let start = canvas_for_pinch.pinch_start.get();
+            canvas_for_pinch.set_zoom(Zoom::Scale(start * scale), None);
+        });
+        *canvas.pinch.borrow_mut() = Some(pinch);
+
+        canvas
+    }
+
+    pub fn connect_zoom_changed(&self, callback: impl Fn(f64) + 'static) {
+        *self.on_zoom_changed.borrow_mut() = Some(std::boxed::Box::new(callback));
+    }
+
+    /// Sends drags on the image to `handler` instead of panning, or goes
+    /// back to panning with `None`.
+    pub fn set_pointer_handler(&self, handler: Option<PointerHandler>) {
+        *self.pointer_handler.borrow_mut() = handler;
+        self.pointer_down.set(false);
+    }
+
+    /// Passes a pointer event at `position` (event box coordinates) to the
+    /// pointer handler, returning false when there is none.
+    fn send_pointer(&self, event: PointerEvent, position: (f64, f64)) -> bool {
+        let Some(handler) = self.pointer_handler.borrow().clone() else {
+            return false;
+        };
+        handler(event, self.to_image_point(position));
+        true
+    }
+
+    /// Converts event box coordinates to a position in frame pixels. The
+    /// rendered image is centred in the image widget's allocation.
+    fn to_image_point(&self, (x, y): (f64, f64)) -> Point {
+        let frame = self.frame_size();
+        let rendered = self.rendered.get().unwrap_or(frame);
+        let allocation = self.image.allocation();
+        let left = allocation.x() as f64 + (allocation.width() - rendered.0) as f64 / 2.0;
+        let top = allocation.y() as f64 + (allocation.height() - rendered.1) as f64 / 2.0;
+        Point::new(
+            (x - left) * frame.0 as f64 / rendered.0 as f64,
+            (y - top) * frame.1 as f64 / rendered.1 as f64,
+        )
+    }
+
+    fn frame_size(&self) -> (i32, i32) {
+        let frame = self.frame.borrow();
+        (frame.width(), frame.height())
+    }
+
+    /// The scale the image is currently shown at.
+    pub fn scale(&self) -> f64 {
+        let size = self.frame_size();
+        clamp_zoom(self.zoom.get().scale(size, self.area.get()), size)
+    }
+
+    fn can_pan(&self) -> bool {
+        let h = self.scrolled.hadjustment();
+        let v = self.scrolled.vadjustment();
+        h.upper() > h.page_size() + 0.5 || v.upper() > v.page_size() + 0.5
+    }
+
+    /// Shows a new frame. Frames set here are raster (animation frames or
+    /// edits), so an SVG source is no longer re-rendered.
+    pub fn set_frame(&self, frame: gtk::gdk_pixbuf::Pixbuf) {
+        *self.frame.borrow_mut() = frame;
+        self.svg_data.borrow_mut().take();
+        self.rendered.set(None);
+        self.render();
+    }
+
+    /// Records the window size; a fitted image follows it.
+    pub fn set_area(&self, area: (i32, i32)) {
+        self.area.set(area);
+        if self.zoom.get() == Zoom::Fit {
+            self.render();
+        }
+    }
+
+    /// Changes the zoom, keeping the point at `anchor` (in image widget
+    /// coordinates, or the centre of the view) under the pointer.
+    pub fn set_zoom(&self, zoom: Zoom, anchor: Option<(f64, f64)>) {
+        let old_scale = self.scale();
+        let zoom = match zoom {
+            Zoom::Scale(scale) => Zoom::Scale(clamp_zoom(scale, self.frame_size())),
+            Zoom::Fit => Zoom::Fit,
+        };
+        self.zoom.set(zoom);
+        let new_scale = self.scale();
+
+        let h = self.scrolled.hadjustment();
+        let v = self.scrolled.vadjustment();
+        let (anchor_x, anchor_y) = match anchor {
+            Some((x, y)) => (x - h.value(), y - v.value()),
+            None => (h.page_size() / 2.0, v.page_size() / 2.0),
+        };
+        let h_value = anchored_offset(h.value(), anchor_x, old_scale, new_scale);
+        let v_value = anchored_offset(v.value(), anchor_y, old_scale, new_scale);
+        self.render();
+
+        // The adjustments only grow once the resized image is allocated
+        gtk::glib::idle_add_local_once(move || {
+            h.set_value(h_value);
+            v.set_value(v_value);
+        });
+    }
+
+    pub fn render(&self) {
+        let scale = self.scale();
+        let size = scaled_size(self.frame_size(), scale);
+        if self.rendered.get() == Some(size) {
+            return;
+        }
+        self.rendered.set(Some(size));
+
+        let rendered = match self.svg_data.borrow().as_ref() {
+            // Re-render the vector image instead of scaling pixels
+            Some(svg_data) => load_image(svg_data, "image/svg+xml", Some(size)),
+            None => {
+                let interpolation = if uses_nearest_neighbor(scale) {
+                    gtk::gdk_pixbuf::InterpType::Nearest
+                } else {
+                    gtk::gdk_pixbuf::InterpType::Bilinear
+                };
+                self.frame
+                    .borrow()
+                    .scale_simple(size.0, size.1, interpolation)
+                    .ok_or_else(|| "Failed to scale image.".to_string())
+            }
+        };
+        match rendered {
+            Ok(pixbuf) => self.image.set_from_pixbuf(Some(&pixbuf)),
+            Err(e) => eprintln!("{}", e),
+        }
+        if let Some(callback) = self.on_zoom_changed.borrow().as_ref() {
+            callback(scale);
+        }
+    }
+}
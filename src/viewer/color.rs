@@ -0,0 +1,114 @@
+//! The color swatch viewer.
+
+use gtk::prelude::*;
+use gtk::{Application, Box, Button, Orientation};
+use std::rc::Rc;
+use waypin_lib::color::format_hex;
+use waypin_lib::{copy_to_clipboard, ClipboardBackend, Selection, WindowConfig};
+
+use super::{accept_dropped_files, set_margins, viewer_window};
+
+pub fn show_color(
+    backend: Rc<dyn ClipboardBackend>,
+    selection: Selection,
+    config: WindowConfig,
+    mime_type: &str,
+    data: &[u8],
+    rgba: [u8; 4],
+) {
+    let mime_type = mime_type.to_string();
+    let data = data.to_vec();
+    let app = Application::new(None, Default::default());
+    app.connect_activate(move |app| {
+        let window = viewer_window(app, &format!("{} Color", selection.label()), &config);
+        window.set_default_size(config.color_size.0, config.color_size.1);
+
+        let page = color_page(&backend, &config, &mime_type, &data, rgba);
+        accept_dropped_files(&window, &backend, selection);
+        window.add(&page);
+        window.show_all();
+        window.present();
+    });
+    app.run();
+}
+
+/// A color swatch with its hex and RGB values, and buttons to copy it as
+/// text or back as the original type.
+pub fn color_page(
+    backend: &Rc<dyn ClipboardBackend>,
+    config: &WindowConfig,
+    mime_type: &str,
+    data: &[u8],
+    rgba: [u8; 4],
+) -> Box {
+    let mime_type = mime_type.to_string();
+    let data = data.to_vec();
+    let vbox = Box::new(Orientation::Vertical, 10);
+    set_margins(&vbox, config.margin);
+
+    let swatch = gtk::DrawingArea::new();
+    swatch.set_size_request(160, 120);
+    swatch.connect_draw(move |area, cr| {
+        let (width, height) = (
+            area.allocated_width() as f64,
+            area.allocated_height() as f64,
+        );
+        // A checkerboard behind the color shows its transparency
+        let square = 8.0;
+        for row in 0..(height / square).ceil() as i32 {
+            for column in 0..(width / square).ceil() as i32 {
+                let shade = if (row + column) % 2 == 0 { 0.8 } else { 0.6 };
+                cr.set_source_rgb(shade, shade, shade);
+                cr.rectangle(column as f64 * square, row as f64 * square, square, square);
+                let _ = cr.fill();
+            }
+        }
+        let [r, g, b, a] = rgba.map(|channel| channel as f64 / 255.0);
+        cr.set_source_rgba(r, g, b, a);
+        cr.rectangle(0.0, 0.0, width, height);
+        let _ = cr.fill();
+        false.into()
+    });
+    vbox.pack_start(&swatch, true, true, 0);
+
+    let hex = format_hex(rgba);
+    let [r, g, b, a] = rgba;
+    let values = if a == 255 {
+        format!("rgb({}, {}, {})", r, g, b)
+    } else {
+        format!("rgba({}, {}, {}, {:.2})", r, g, b, a as f64 / 255.0)
+    };
+    let label = gtk::Label::new(Some(&format!("{}    {}", hex, values)));
+    label.set_selectable(true);
+    vbox.pack_start(&label, false, false, 0);
+
+    let button_box = Box::new(Orientation::Horizontal, 6);
+    button_box.set_margin_top(10);
+
+    let copy_text_btn = Button::with_label("Copy as Text");
+    copy_text_btn.set_tooltip_text(Some(&format!("Copy {} as text", hex)));
+    let backend_for_text = backend.clone();
+    copy_text_btn.connect_clicked(move |_| {
+        let contents = [
+            ("text/plain;charset=utf-8", hex.as_bytes()),
+            ("UTF8_STRING", hex.as_bytes()),
+        ];
+        if let Err(e) = copy_to_clipboard(&*backend_for_text, &contents) {
+            eprintln!("Failed to copy color: {}", e);
+        }
+    });
+    button_box.pack_start(&copy_text_btn, false, false, 0);
+
+    let copy_btn = Button::with_label("Copy");
+    copy_btn.set_tooltip_text(Some(&format!("Copy back as {}", mime_type)));
+    let backend_for_copy = backend.clone();
+    copy_btn.connect_clicked(move |_| {
+        if let Err(e) = copy_to_clipboard(&*backend_for_copy, &[(&mime_type, &data)]) {
+            eprintln!("Failed to copy color: {}", e);
+        }
+    });
+    button_box.pack_end(&copy_btn, false, false, 0);
+
+    vbox.pack_start(&button_box, false, false, 0);
+    vbox
+}
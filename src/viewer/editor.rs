@@ -0,0 +1,359 @@
+//! The image viewer's edit mode.
+
+use gtk::prelude::*;
+use gtk::{Box, Button, Orientation};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use waypin_lib::annotate::{Annotation, Point};
+use waypin_lib::edit::{apply_edits, Edit, EditHistory};
+use waypin_lib::raster::RgbaImage;
+use waypin_lib::transform::Transform;
+
+use super::canvas::{ImageCanvas, PointerEvent};
+use crate::pixbuf::{pixbuf_to_rgba, rgba_to_pixbuf};
+
+/// Tools of the edit toolbar that work by dragging on the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EditTool {
+    Rectangle,
+    Arrow,
+    Pen,
+    Text,
+    Pixelate,
+    Blur,
+    Crop,
+}
+
+// Annotation sizes in screen pixels, so marks look the same at any zoom
+const STROKE_WIDTH: f64 = 3.0;
+const TEXT_SIZE: f64 = 20.0;
+const PIXELATE_BLOCK: f64 = 12.0;
+const BLUR_RADIUS: f64 = 8.0;
+
+/// Edit mode of the image viewer: the toolbar, the selected tool and the
+/// annotations and transforms applied to the decoded image. Editing
+/// happens in waypin_lib on an RGBA copy, and the result replaces the
+/// canvas frame.
+pub struct ImageEditor {
+    canvas: Rc<ImageCanvas>,
+    original: gtk::gdk_pixbuf::Pixbuf,
+    /// The original as RGBA, converted when editing first starts
+    base: RefCell<Option<RgbaImage>>,
+    history: RefCell<EditHistory>,
+    /// `base` with the edits in `history` applied
+    edited: RefCell<Option<RgbaImage>>,
+    /// The edit being dragged out
+    draft: RefCell<Option<Edit>>,
+    tool: Cell<EditTool>,
+    pub toolbar: Box,
+    color: gtk::ColorButton,
+    text_entry: gtk::Entry,
+}
+
+impl ImageEditor {
+    pub fn new(canvas: Rc<ImageCanvas>, original: gtk::gdk_pixbuf::Pixbuf) -> Rc<Self> {
+        // Drawing tools on the first row, transforms and history below
+        let toolbar = Box::new(Orientation::Vertical, 6);
+        toolbar.set_margin_bottom(10);
+        toolbar.set_halign(gtk::Align::Center);
+        toolbar.set_valign(gtk::Align::End);
+        let tools_row = Box::new(Orientation::Horizontal, 6);
+        let actions_row = Box::new(Orientation::Horizontal, 6);
+        actions_row.set_halign(gtk::Align::Center);
+        toolbar.pack_start(&tools_row, false, false, 0);
+        toolbar.pack_start(&actions_row, false, false, 0);
+
+        let color = gtk::ColorButton::with_rgba(&gtk::gdk::RGBA::new(1.0, 0.0, 0.0, 1.0));
+        color.set_tooltip_text(Some("Color"));
+        let text_entry = gtk::Entry::new();
+        text_entry.set_placeholder_text(Some("Text, then click the image"));
+        text_entry.set_width_chars(20);
+
+        let editor = Rc::new(Self {
+            canvas,
+            original,
+            base: RefCell::new(None),
+            history: RefCell::new(EditHistory::default()),
+            edited: RefCell::new(None),
+            draft: RefCell::new(None),
+            tool: Cell::new(EditTool::Rectangle),
+            toolbar: toolbar.clone(),
+            color: color.clone(),
+            text_entry: text_entry.clone(),
+        });
+
+        let tools = [
+            (EditTool::Rectangle, "Box"),
+            (EditTool::Arrow, "Arrow"),
+            (EditTool::Pen, "Pen"),
+            (EditTool::Text, "Text"),
+            (EditTool::Pixelate, "Pixelate"),
+            (EditTool::Blur, "Blur"),
+            (EditTool::Crop, "Crop"),
+        ];
+        let mut first: Option<gtk::RadioButton> = None;
+        for (tool, label) in tools {
+            let button = match &first {
+                Some(first) => gtk::RadioButton::with_label_from_widget(first, label),
+                None => gtk::RadioButton::with_label(label),
+            };
+            // Draw as toggle buttons rather than radio circles
+            button.set_mode(false);
+            let editor_for_tool = editor.clone();
+            button.connect_toggled(move |button| {
+                if button.is_active() {
+                    editor_for_tool.tool.set(tool);
+                }
+            });
+            tools_row.pack_start(&button, false, false, 0);
+            first.get_or_insert(button);
+        }
+        tools_row.pack_start(&text_entry, false, false, 0);
+        tools_row.pack_start(&color, false, false, 0);
+
+        let transforms = [
+            (Transform::RotateCounterclockwise, "Rotate Left"),
+            (Transform::RotateClockwise, "Rotate Right"),
+            (Transform::Rotate180, "Rotate 180°"),
+            (Transform::FlipHorizontal, "Flip Horizontal"),
+            (Transform::FlipVertical, "Flip Vertical"),
+        ];
+        for (transform, label) in transforms {
+            let button = Button::with_label(label);
+            let editor_for_transform = editor.clone();
+            button.connect_clicked(move |_| {
+                editor_for_transform.push(Edit::Transform(transform));
+            });
+            actions_row.pack_start(&button, false, false, 0);
+        }
+
+        let undo_btn = Button::with_label("Undo");
+        undo_btn.set_tooltip_text(Some("Undo (Ctrl+Z)"));
+        let editor_for_undo = editor.clone();
+        undo_btn.connect_clicked(move |_| editor_for_undo.undo());
+        let redo_btn = Button::with_label("Redo");
+        redo_btn.set_tooltip_text(Some("Redo (Ctrl+Shift+Z)"));
+        let editor_for_redo = editor.clone();
+        redo_btn.connect_clicked(move |_| editor_for_redo.redo());
+        actions_row.pack_start(&undo_btn, false, false, 0);
+        actions_row.pack_start(&redo_btn, false, false, 0);
+
+        // Hidden until editing starts; show_all on the window skips it
+        toolbar.show_all();
+        toolbar.set_no_show_all(true);
+        toolbar.hide();
+
+        editor
+    }
+
+    /// Starts or stops editing. Stopping keeps the edits, so the edited
+    /// image can still be zoomed, panned and copied.
+    pub fn set_active(self: &Rc<Self>, active: bool) {
+        self.toolbar.set_visible(active);
+        if !active {
+            self.canvas.set_pointer_handler(None);
+            return;
+        }
+        if self.base.borrow().is_none() {
+            match pixbuf_to_rgba(&self.original) {
+                Ok(base) => {
+                    *self.edited.borrow_mut() = Some(base.clone());
+                    *self.base.borrow_mut() = Some(base);
+                }
+                Err(e) => {
+                    eprintln!("Failed to prepare image for editing: {}", e);
+                    self.toolbar.hide();
+                    return;
+                }
+            }
+        }
+        let editor = self.clone();
+        self.canvas
+            .set_pointer_handler(Some(Rc::new(move |event, point| {
+                editor.handle_pointer(event, point);
+            })));
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.toolbar.is_visible()
+    }
+
+    fn selected_color(&self) -> [u8; 4] {
+        let rgba = self.color.rgba();
+        [rgba.red(), rgba.green(), rgba.blue(), rgba.alpha()]
+            .map(|channel| (channel * 255.0).round().clamp(0.0, 255.0) as u8)
+    }
+
+    fn handle_pointer(&self, event: PointerEvent, point: Point) {
+        // Keep sizes constant on screen by dividing by the zoom
+        let scale = self.canvas.scale();
+        match event {
+            PointerEvent::Press => {
+                let color = self.selected_color();
+                let annotation = match self.tool.get() {
+                    EditTool::Rectangle => Annotation::Rectangle {
+                        from: point,
+                        to: point,
+                        color,
+                        width: STROKE_WIDTH / scale,
+                    },
+                    EditTool::Arrow => Annotation::Arrow {
+                        from: point,
+                        to: point,
+                        color,
+                        width: STROKE_WIDTH / scale,
+                    },
+                    EditTool::Pen => Annotation::Freehand {
+                        points: vec![point],
+                        color,
+                        width: STROKE_WIDTH / scale,
+                    },
+                    EditTool::Text => {
+                        let text = self.text_entry.text().to_string();
+                        if text.is_empty() {
+                            self.text_entry.grab_focus();
+                            return;
+                        }
+                        let Some(bitmap) =
+                            render_text_bitmap(&self.text_entry, &text, color, TEXT_SIZE / scale)
+                        else {
+                            eprintln!("Failed to render annotation text.");
+                            return;
+                        };
+                        Annotation::Text {
+                            at: point,
+                            text,
+                            bitmap,
+                        }
+                    }
+                    EditTool::Pixelate => Annotation::Pixelate {
+                        from: point,
+                        to: point,
+                        block: (PIXELATE_BLOCK / scale).round().max(2.0) as u32,
+                    },
+                    EditTool::Blur => Annotation::Blur {
+                        from: point,
+                        to: point,
+                        radius: (BLUR_RADIUS / scale).round().max(1.0) as u32,
+                    },
+                    EditTool::Crop => {
+                        let crop = Transform::Crop {
+                            from: point,
+                            to: point,
+                        };
+                        *self.draft.borrow_mut() = Some(Edit::Transform(crop));
+                        return;
+                    }
+                };
+                *self.draft.borrow_mut() = Some(Edit::Annotate(annotation));
+            }
+            PointerEvent::Motion => {
+                if let Some(draft) = self.draft.borrow_mut().as_mut() {
+                    draft.drag_to(point);
+                }
+            }
+            PointerEvent::Release => {
+                let draft = self.draft.borrow_mut().take();
+                if let Some(draft) = draft.filter(|draft| !draft.is_empty()) {
+                    self.push(draft);
+                    return;
+                }
+            }
+        }
+        self.redraw();
+    }
+
+    fn push(&self, edit: Edit) {
+        self.history.borrow_mut().push(edit);
+        self.reapply();
+    }
+
+    pub fn undo(&self) {
+        if self.history.borrow_mut().undo() {
+            self.reapply();
+        }
+    }
+
+    pub fn redo(&self) {
+        if self.history.borrow_mut().redo() {
+            self.reapply();
+        }
+    }
+
+    fn reapply(&self) {
+        if let Some(base) = self.base.borrow().as_ref() {
+            let edited = apply_edits(base, self.history.borrow().edits());
+            *self.edited.borrow_mut() = Some(edited);
+        }
+        self.redraw();
+    }
+
+    /// Shows the edited image with the draft on top. Pixelate, blur and
+    /// crop drafts show their area as an outline until the drag ends.
+    fn redraw(&self) {
+        let Some(mut image) = self.edited.borrow().clone() else {
+            return;
+        };
+        match self.draft.borrow().as_ref() {
+            Some(
+                Edit::Annotate(
+                    Annotation::Pixelate { from, to, .. } | Annotation::Blur { from, to, .. },
+                )
+                | Edit::Transform(Transform::Crop { from, to }),
+            ) => Annotation::Rectangle {
+                from: *from,
+                to: *to,
+                color: [128, 128, 128, 255],
+                width: 1.0 / self.canvas.scale(),
+            }
+            .draw(&mut image),
+            Some(draft) => image = draft.apply(image),
+            None => {}
+        }
+        self.canvas.set_frame(rgba_to_pixbuf(&image));
+    }
+
+    /// The edited image, or `None` when nothing has been changed.
+    pub fn edited_image(&self) -> Option<RgbaImage> {
+        if self.history.borrow().is_empty() {
+            return None;
+        }
+        self.edited.borrow().clone()
+    }
+}
+
+/// Lays out `text` with Pango at `size` pixels in `color` and returns it as
+/// an RGBA bitmap with a transparent background.
+fn render_text_bitmap(
+    widget: &impl IsA<gtk::Widget>,
+    text: &str,
+    color: [u8; 4],
+    size: f64,
+) -> Option<RgbaImage> {
+    let layout = widget.create_pango_layout(Some(text));
+    let mut font = gtk::pango::FontDescription::from_string("Sans Bold");
+    font.set_absolute_size(size * gtk::pango::SCALE as f64);
+    layout.set_font_description(Some(&font));
+    let attributes = gtk::pango::AttrList::new();
+    let channel = |value: u8| value as u16 * 257;
+    attributes.insert(gtk::pango::AttrColor::new_foreground(
+        channel(color[0]),
+        channel(color[1]),
+        channel(color[2]),
+    ));
+    attributes.insert(gtk::pango::AttrInt::new_foreground_alpha(channel(color[3])));
+    layout.set_attributes(Some(&attributes));
+
+    let (width, height) = layout.pixel_size();
+    let mut surface =
+        gtk::cairo::ImageSurface::create(gtk::cairo::Format::ARgb32, width.max(1), height.max(1))
+            .ok()?;
+    {
+        let cr = gtk::cairo::Context::new(&surface).ok()?;
+        gtk::render_layout(&widget.style_context(), &cr, 0.0, 0.0, &layout);
+    }
+    surface.flush();
+    let (width, height, stride) = (surface.width(), surface.height(), surface.stride());
+    let data = surface.data().ok()?;
+    RgbaImage::from_premultiplied_argb32(width as u32, height as u32, stride as usize, &data).ok()
+}
@@ -0,0 +1,200 @@
+//! The file list viewer.
+
+use gtk::prelude::*;
+use gtk::Adjustment;
+use gtk::{Application, Box, Button, Image, Orientation, ScrolledWindow};
+use std::cell::RefCell;
+use std::rc::Rc;
+use waypin_lib::uri_list::{format_size, format_uri_list, UriEntry};
+use waypin_lib::{ClipboardBackend, Selection, WindowConfig};
+
+use super::{accept_dropped_files, set_margins, viewer_window};
+
+fn file_row(entry: &UriEntry) -> Box {
+    let row_box = Box::new(Orientation::Horizontal, 10);
+    row_box.set_margin_top(4);
+    row_box.set_margin_bottom(4);
+    row_box.set_margin_start(6);
+    row_box.set_margin_end(6);
+
+    let metadata = entry
+        .path
+        .as_ref()
+        .filter(|_| entry.is_local())
+        .and_then(|path| std::fs::metadata(path).ok());
+    let content_type = match (&entry.path, &metadata) {
+        (_, Some(metadata)) if metadata.is_dir() => "inode/directory".into(),
+        (Some(path), _) => gtk::gio::content_type_guess(Some(path), &[]).0,
+        (None, _) => "text/x-uri".into(),
+    };
+
+    let icon = Image::from_gicon(
+        &gtk::gio::content_type_get_icon(&content_type),
+        gtk::IconSize::Dnd,
+    );
+    row_box.pack_start(&icon, false, false, 0);
+
+    let details = Box::new(Orientation::Vertical, 2);
+    let name = gtk::Label::new(Some(&entry.display_name()));
+    name.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+    name.set_xalign(0.0);
+    details.pack_start(&name, false, false, 0);
+
+    let mime_type = gtk::gio::content_type_get_mime_type(&content_type)
+        .map(|mime| mime.to_string())
+        .unwrap_or_else(|| content_type.to_string());
+    let summary = match &metadata {
+        Some(metadata) if metadata.is_dir() => format!("Folder · {}", mime_type),
+        Some(metadata) => format!("{} · {}", format_size(metadata.len()), mime_type),
+        None if entry.is_local() => "Missing".to_string(),
+        None => "Not a local file".to_string(),
+    };
+    let summary = gtk::Label::new(Some(&summary));
+    summary.set_xalign(0.0);
+    summary.style_context().add_class("dim-label");
+    details.pack_start(&summary, false, false, 0);
+    row_box.pack_start(&details, true, true, 0);
+
+    if entry.is_local() && metadata.is_none() {
+        let missing = Image::from_icon_name(Some("dialog-warning"), gtk::IconSize::Button);
+        missing.set_tooltip_text(Some("File does not exist"));
+        row_box.pack_end(&missing, false, false, 0);
+    }
+
+    row_box
+}
+
+pub fn show_file_list(
+    backend: Rc<dyn ClipboardBackend>,
+    selection: Selection,
+    config: WindowConfig,
+    entries: Vec<UriEntry>,
+) {
+    let app = Application::new(None, Default::default());
+    app.connect_activate(move |app| {
+        let window = viewer_window(app, &format!("{} Files", selection.label()), &config);
+        window.set_default_size(config.file_list_size.0, config.file_list_size.1);
+
+        let page = file_list_page(&backend, &config, &entries);
+        accept_dropped_files(&window, &backend, selection);
+        window.add(&page);
+        window.show_all();
+        window.present();
+    });
+    app.run();
+}
+
+/// The file list viewer: a row per file, with buttons to open its folder,
+/// remove it, and copy the list back.
+pub fn file_list_page(
+    backend: &Rc<dyn ClipboardBackend>,
+    config: &WindowConfig,
+    entries: &[UriEntry],
+) -> Box {
+    let vbox = Box::new(Orientation::Vertical, 10);
+    set_margins(&vbox, config.margin);
+
+    // Rows and entries are kept in the same order
+    let entries = Rc::new(RefCell::new(entries.to_vec()));
+    let list = gtk::ListBox::new();
+    list.set_selection_mode(gtk::SelectionMode::Browse);
+    for entry in entries.borrow().iter() {
+        list.add(&file_row(entry));
+    }
+    if let Some(first) = list.row_at_index(0) {
+        list.select_row(Some(&first));
+    }
+
+    let scrolled = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+    scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+    scrolled.set_min_content_height(200);
+    scrolled.add(&list);
+    vbox.pack_start(&scrolled, true, true, 0);
+
+    let button_box = Box::new(Orientation::Horizontal, 6);
+    button_box.set_margin_top(10);
+
+    let open_btn = Button::with_label("Open Folder");
+    let list_for_open = list.clone();
+    let entries_for_open = entries.clone();
+    open_btn.connect_clicked(move |_| {
+        let Some(row) = list_for_open.selected_row() else {
+            return;
+        };
+        let entries = entries_for_open.borrow();
+        let Some(entry) = entries.get(row.index() as usize).filter(|e| e.is_local()) else {
+            return;
+        };
+        let Some(folder) = entry.path.as_ref().and_then(|path| path.parent()) else {
+            return;
+        };
+        let uri = gtk::gio::File::for_path(folder).uri();
+        if let Err(e) =
+            gtk::gio::AppInfo::launch_default_for_uri(&uri, None::<&gtk::gio::AppLaunchContext>)
+        {
+            eprintln!("Failed to open folder: {}", e);
+        }
+    });
+    button_box.pack_start(&open_btn, false, false, 0);
+
+    let remove_btn = Button::with_label("Remove");
+    let list_for_remove = list.clone();
+    let entries_for_remove = entries.clone();
+    remove_btn.connect_clicked(move |_| {
+        let Some(row) = list_for_remove.selected_row() else {
+            return;
+        };
+        let index = row.index();
+        entries_for_remove.borrow_mut().remove(index as usize);
+        list_for_remove.remove(&row);
+        let next = list_for_remove
+            .row_at_index(index)
+            .or_else(|| list_for_remove.row_at_index(index - 1));
+        list_for_remove.select_row(next.as_ref());
+    });
+    button_box.pack_start(&remove_btn, false, false, 0);
+
+    let copy_list_btn = Button::with_label("Copy List");
+    if !backend.offers_multiple_types() {
+        copy_list_btn.set_tooltip_text(Some(
+            "Copies the URI list only: this clipboard tool offers one type at a time",
+        ));
+    }
+    let backend_for_list = backend.clone();
+    let entries_for_list = entries.clone();
+    copy_list_btn.connect_clicked(move |_| {
+        let entries = entries_for_list.borrow();
+        let uri_list = format_uri_list(entries.iter().map(|entry| entry.uri.as_str()));
+        let paths = file_paths_text(&entries);
+        let contents = [
+            ("text/uri-list", uri_list.as_bytes()),
+            ("text/plain;charset=utf-8", paths.as_bytes()),
+        ];
+        if let Err(e) = backend_for_list.write(&contents) {
+            eprintln!("Failed to copy file list: {}", e);
+        }
+    });
+    button_box.pack_end(&copy_list_btn, false, false, 0);
+
+    let copy_paths_btn = Button::with_label("Copy Paths as Text");
+    let backend_for_paths = backend.clone();
+    let entries_for_paths = entries.clone();
+    copy_paths_btn.connect_clicked(move |_| {
+        let paths = file_paths_text(&entries_for_paths.borrow());
+        if let Err(e) = backend_for_paths.write(&[("text/plain;charset=utf-8", paths.as_bytes())]) {
+            eprintln!("Failed to copy paths: {}", e);
+        }
+    });
+    button_box.pack_end(&copy_paths_btn, false, false, 0);
+
+    vbox.pack_start(&button_box, false, false, 0);
+    vbox
+}
+
+fn file_paths_text(entries: &[UriEntry]) -> String {
+    entries
+        .iter()
+        .map(UriEntry::display_name)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,237 @@
+//! The hex viewer for content no other viewer shows.
+
+use gtk::prelude::*;
+use gtk::Adjustment;
+use gtk::{Application, ApplicationWindow, Box, Button, Orientation, ScrolledWindow, TextView};
+use std::cell::Cell;
+use std::rc::Rc;
+use waypin_lib::hex::{
+    ascii_column, find_bytes, hex_column, hex_dump, page_count, page_range, parse_byte_pattern,
+    BYTES_PER_LINE, PAGE_SIZE,
+};
+use waypin_lib::uri_list::format_size;
+use waypin_lib::{
+    copy_to_clipboard, ClipboardBackend, ClipboardContentType, Selection, WindowConfig,
+};
+
+use super::{
+    accept_dropped_files, choose_save_path, connect_page_keys, is_save_shortcut, set_margins,
+    viewer_window,
+};
+
+/// One page of a hex dump at a time, with the current search match
+/// highlighted.
+struct HexPager {
+    data: Vec<u8>,
+    view: TextView,
+    page_label: gtk::Label,
+    page: Cell<usize>,
+    /// Byte range of the current search match.
+    found: Cell<Option<(usize, usize)>>,
+}
+
+impl HexPager {
+    fn new(data: Vec<u8>) -> Rc<Self> {
+        let view = TextView::new();
+        view.set_editable(false);
+        view.set_cursor_visible(false);
+        view.set_monospace(true);
+        let tag = gtk::TextTag::new(Some("match"));
+        tag.set_background(Some("#f6d32d"));
+        tag.set_foreground(Some("#000000"));
+        view.buffer().unwrap().tag_table().unwrap().add(&tag);
+
+        let pager = Rc::new(Self {
+            data,
+            view,
+            page_label: gtk::Label::new(None),
+            page: Cell::new(0),
+            found: Cell::new(None),
+        });
+        pager.show(0);
+        pager
+    }
+
+    fn page_count(&self) -> usize {
+        page_count(self.data.len())
+    }
+
+    fn show(&self, page: usize) {
+        let page = page.min(self.page_count() - 1);
+        self.page.set(page);
+        self.page_label
+            .set_text(&format!("Page {} of {}", page + 1, self.page_count()));
+
+        let range = page_range(page, self.data.len());
+        let buffer = self.view.buffer().unwrap();
+        buffer.set_text(&hex_dump(&self.data[range.clone()], range.start));
+
+        let Some((start, end)) = self.found.get() else {
+            return;
+        };
+        let mut first = None;
+        for offset in start.max(range.start)..end.min(range.end) {
+            let local = offset - range.start;
+            let line = (local / BYTES_PER_LINE) as i32;
+            let index = local % BYTES_PER_LINE;
+            for (column, width) in [(hex_column(index), 2), (ascii_column(index), 1)] {
+                let from = buffer.iter_at_line_offset(line, column as i32);
+                let to = buffer.iter_at_line_offset(line, (column + width) as i32);
+                buffer.apply_tag_by_name("match", &from, &to);
+            }
+            first.get_or_insert(line);
+        }
+        if let Some(mark) =
+            first.and_then(|line| buffer.create_mark(None, &buffer.iter_at_line(line), true))
+        {
+            self.view.scroll_to_mark(&mark, 0.1, true, 0.0, 0.3);
+        }
+    }
+
+    /// Moves to the next occurrence of `pattern` after the current match,
+    /// wrapping around to the start. Returns whether one was found.
+    fn find_next(&self, pattern: &[u8]) -> bool {
+        let from = self.found.get().map_or(0, |(start, _)| start + 1);
+        let found =
+            find_bytes(&self.data, pattern, from).or_else(|| find_bytes(&self.data, pattern, 0));
+        self.found
+            .set(found.map(|start| (start, start + pattern.len())));
+        match found {
+            Some(start) => self.show(start / PAGE_SIZE),
+            None => self.show(self.page.get()),
+        }
+        found.is_some()
+    }
+}
+
+pub fn show_hex_viewer(
+    backend: Rc<dyn ClipboardBackend>,
+    selection: Selection,
+    config: WindowConfig,
+    mime_type: &str,
+    data: &[u8],
+) {
+    let mime_type = mime_type.to_string();
+    let data = data.to_vec();
+    let app = Application::new(None, Default::default());
+    app.connect_activate(move |app| {
+        let window = viewer_window(app, &format!("{} Data", selection.label()), &config);
+        window.set_default_size(config.hex_size.0, config.hex_size.1);
+
+        let page = hex_page(&window, &backend, &config, &mime_type, &data);
+        accept_dropped_files(&window, &backend, selection);
+        window.add(&page);
+        window.show_all();
+        window.present();
+    });
+    app.run();
+}
+
+/// The hex viewer: one page of the dump at a time, with byte search, Save
+/// As… and a button to copy the data back as the same type.
+pub fn hex_page(
+    window: &ApplicationWindow,
+    backend: &Rc<dyn ClipboardBackend>,
+    config: &WindowConfig,
+    mime_type: &str,
+    data: &[u8],
+) -> Box {
+    let mime_type = mime_type.to_string();
+    let data = data.to_vec();
+    let vbox = Box::new(Orientation::Vertical, 10);
+    set_margins(&vbox, config.margin);
+
+    let summary = gtk::Label::new(Some(&format!(
+        "{} ({}, {} bytes)",
+        mime_type,
+        format_size(data.len() as u64),
+        data.len()
+    )));
+    summary.set_xalign(0.0);
+    vbox.pack_start(&summary, false, false, 0);
+
+    let pager = HexPager::new(data.clone());
+
+    let search_box = Box::new(Orientation::Horizontal, 6);
+    let search = gtk::SearchEntry::new();
+    search.set_placeholder_text(Some("Find bytes: 89 50 4e 47, 0xCAFE or text"));
+    search_box.pack_start(&search, true, true, 0);
+    let status = gtk::Label::new(None);
+    status.style_context().add_class("dim-label");
+    search_box.pack_start(&status, false, false, 0);
+    vbox.pack_start(&search_box, false, false, 0);
+    search.connect_map(|search| search.grab_focus());
+
+    let pager_for_search = pager.clone();
+    search.connect_activate(move |search| match parse_byte_pattern(&search.text()) {
+        Ok(pattern) => {
+            let found = pager_for_search.find_next(&pattern);
+            status.set_text(if found { "" } else { "Not found" });
+        }
+        Err(e) => status.set_text(&e),
+    });
+
+    let scrolled = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+    scrolled.add(&pager.view);
+    vbox.pack_start(&scrolled, true, true, 0);
+
+    let button_box = Box::new(Orientation::Horizontal, 6);
+    button_box.set_margin_top(10);
+
+    let prev_btn = Button::with_label("Previous Page");
+    let pager_for_prev = pager.clone();
+    prev_btn.connect_clicked(move |_| {
+        let page = pager_for_prev.page.get();
+        pager_for_prev.show(page.saturating_sub(1));
+    });
+    button_box.pack_start(&prev_btn, false, false, 0);
+    button_box.pack_start(&pager.page_label, false, false, 0);
+    let next_btn = Button::with_label("Next Page");
+    let pager_for_next = pager.clone();
+    next_btn.connect_clicked(move |_| {
+        let page = pager_for_next.page.get();
+        pager_for_next.show(page + 1);
+    });
+    button_box.pack_start(&next_btn, false, false, 0);
+
+    let save_data = {
+        let window = window.clone();
+        let mime_type = mime_type.clone();
+        let data = data.clone();
+        move || {
+            let Some(path) =
+                choose_save_path(&window, ClipboardContentType::Unsupported, &mime_type)
+            else {
+                return;
+            };
+            if let Err(e) = std::fs::write(&path, &data) {
+                eprintln!("Failed to write {}: {}", path.display(), e);
+            }
+        }
+    };
+    let save_data = Rc::new(save_data);
+    let save_btn = Button::with_label("Save As…");
+    let save_data_for_button = save_data.clone();
+    save_btn.connect_clicked(move |_| save_data_for_button());
+    connect_page_keys(window, &vbox, move |_, event| {
+        if is_save_shortcut(event) {
+            save_data();
+            return true.into();
+        }
+        false.into()
+    });
+
+    let copy_btn = Button::with_label("Copy");
+    copy_btn.set_tooltip_text(Some(&format!("Copy back as {}", mime_type)));
+    let backend_for_copy = backend.clone();
+    copy_btn.connect_clicked(move |_| {
+        if let Err(e) = copy_to_clipboard(&*backend_for_copy, &[(&mime_type, &data)]) {
+            eprintln!("Failed to copy data: {}", e);
+        }
+    });
+    button_box.pack_end(&copy_btn, false, false, 0);
+    button_box.pack_end(&save_btn, false, false, 0);
+
+    vbox.pack_start(&button_box, false, false, 0);
+    vbox
+}
@@ -0,0 +1,210 @@
+//! `waypin history`: the recorded entries, to copy one back.
+
+use gtk::gdk_pixbuf::PixbufLoader;
+use gtk::prelude::*;
+use gtk::Adjustment;
+use gtk::{Application, Box, Image, Orientation, ScrolledWindow};
+use std::rc::Rc;
+use waypin_lib::history::{
+    format_age, matches_query, now_millis, restore_entry, text_snippet, HistoryEntry, HistoryStore,
+};
+use waypin_lib::rich_text::markup_to_styled_text;
+use waypin_lib::{ClipboardBackend, ClipboardContentType, WindowConfig};
+
+use super::{set_margins, viewer_window};
+
+fn load_thumbnail(data: &[u8], max_width: i32, max_height: i32) -> Option<gtk::gdk_pixbuf::Pixbuf> {
+    let loader = PixbufLoader::new();
+    loader.write(data).ok()?;
+    loader.close().ok()?;
+    let pixbuf = loader.pixbuf()?;
+    let scale = f64::min(
+        1.0,
+        f64::min(
+            max_width as f64 / pixbuf.width() as f64,
+            max_height as f64 / pixbuf.height() as f64,
+        ),
+    );
+    pixbuf.scale_simple(
+        ((pixbuf.width() as f64 * scale).round() as i32).max(1),
+        ((pixbuf.height() as f64 * scale).round() as i32).max(1),
+        gtk::gdk_pixbuf::InterpType::Bilinear,
+    )
+}
+
+pub fn show_history(
+    backend: Rc<dyn ClipboardBackend>,
+    config: WindowConfig,
+    store: HistoryStore,
+    entries: Vec<HistoryEntry>,
+) {
+    let store = Rc::new(store);
+    let app = Application::new(None, Default::default());
+    app.connect_activate(move |app| {
+        let window = viewer_window(app, "Clipboard History", &config);
+        window.set_default_size(config.history_size.0, config.history_size.1);
+
+        let vbox = Box::new(Orientation::Vertical, 10);
+        set_margins(&vbox, config.margin);
+
+        let search = gtk::SearchEntry::new();
+        search.set_placeholder_text(Some("Search text entries"));
+        vbox.pack_start(&search, false, false, 0);
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::Browse);
+        list.set_activate_on_single_click(false);
+
+        // Text of each row, by row index, for the search filter
+        let mut row_texts: Vec<Option<String>> = Vec::new();
+        let now = now_millis();
+        for entry in &entries {
+            let row_box = Box::new(Orientation::Horizontal, 10);
+            row_box.set_margin_top(4);
+            row_box.set_margin_bottom(4);
+            row_box.set_margin_start(6);
+            row_box.set_margin_end(6);
+
+            let payload = entry
+                .mime_type
+                .as_deref()
+                .and_then(|mime| store.payload(entry, mime).ok())
+                .unwrap_or_default();
+            let mut row_text = None;
+            match entry.content_type {
+                ClipboardContentType::Image => {
+                    let thumbnail = Image::new();
+                    if let Some(pixbuf) = load_thumbnail(&payload, 96, 64) {
+                        thumbnail.set_from_pixbuf(Some(&pixbuf));
+                    }
+                    row_box.pack_start(&thumbnail, false, false, 0);
+                }
+                ClipboardContentType::Text
+                | ClipboardContentType::Html
+                | ClipboardContentType::Rtf => {
+                    let mut text = String::from_utf8_lossy(&payload).into_owned();
+                    if entry.content_type != ClipboardContentType::Text {
+                        let mime_type = entry.mime_type.as_deref().unwrap_or_default();
+                        text = markup_to_styled_text(mime_type, &text).text;
+                    }
+                    let label = gtk::Label::new(Some(&text_snippet(&text, 80)));
+                    label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                    label.set_xalign(0.0);
+                    row_box.pack_start(&label, true, true, 0);
+                    row_text = Some(text);
+                }
+                ClipboardContentType::File
+                | ClipboardContentType::Color
+                | ClipboardContentType::Unsupported => {
+                    let description = entry.types.first().map(String::as_str).unwrap_or("Empty");
+                    let label = gtk::Label::new(Some(description));
+                    label.set_xalign(0.0);
+                    row_box.pack_start(&label, true, true, 0);
+                }
+            }
+
+            let age = gtk::Label::new(Some(&format_age(now, entry.timestamp)));
+            age.style_context().add_class("dim-label");
+            row_box.pack_end(&age, false, false, 0);
+
+            list.add(&row_box);
+            row_texts.push(row_text);
+        }
+
+        // Only text entries can match a query; everything shows without one
+        let search_for_filter = search.clone();
+        list.set_filter_func(Some(std::boxed::Box::new(move |row| {
+            let query = search_for_filter.text();
+            if query.trim().is_empty() {
+                return true;
+            }
+            row_texts
+                .get(row.index() as usize)
+                .and_then(Option::as_ref)
+                .is_some_and(|text| matches_query(text, &query))
+        })));
+
+        let list_for_search = list.clone();
+        search.connect_search_changed(move |_| {
+            list_for_search.invalidate_filter();
+            let first_visible = (0..)
+                .map_while(|index| list_for_search.row_at_index(index))
+                .find(|row| row.is_child_visible());
+            list_for_search.select_row(first_visible.as_ref());
+        });
+
+        // Enter in the search box or on a row copies the selected entry back
+        let entries = Rc::new(entries.clone());
+        let copy_selected = {
+            let list = list.clone();
+            let window = window.clone();
+            let backend = backend.clone();
+            let store = store.clone();
+            let entries = entries.clone();
+            move || {
+                let Some(row) = list.selected_row() else {
+                    return;
+                };
+                if let Some(entry) = entries.get(row.index() as usize) {
+                    match restore_entry(&*backend, &store, entry) {
+                        Ok(_) => window.close(),
+                        Err(e) => eprintln!("Failed to copy history entry: {}", e),
+                    }
+                }
+            }
+        };
+        let copy_on_activate = copy_selected.clone();
+        search.connect_activate(move |_| copy_on_activate());
+        list.connect_row_activated(move |_, _| copy_selected());
+
+        // Arrow keys in the search box move through the list
+        let list_for_keys = list.clone();
+        search.connect_key_press_event(move |_, event| {
+            let key = event.keyval();
+            if key == gtk::gdk::keys::constants::Down || key == gtk::gdk::keys::constants::Up {
+                if let Some(row) = list_for_keys.selected_row() {
+                    row.grab_focus();
+                } else if let Some(row) = list_for_keys.row_at_index(0) {
+                    list_for_keys.select_row(Some(&row));
+                    row.grab_focus();
+                }
+                return true.into();
+            }
+            false.into()
+        });
+
+        let scrolled = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        if entries.is_empty() {
+            let empty = gtk::Label::new(Some(
+                "No clipboard history yet. Run `waypin daemon` to record it.",
+            ));
+            empty.set_line_wrap(true);
+            scrolled.add(&empty);
+        } else {
+            scrolled.add(&list);
+        }
+        vbox.pack_start(&scrolled, true, true, 0);
+
+        if !backend.offers_multiple_types() && !entries.is_empty() {
+            let note = gtk::Label::new(Some(
+                "Entries are restored as the type they were shown as only: \
+                 this clipboard tool offers one type at a time.",
+            ));
+            note.set_line_wrap(true);
+            note.set_xalign(0.0);
+            note.style_context().add_class("dim-label");
+            vbox.pack_start(&note, false, false, 0);
+        }
+
+        if let Some(first) = list.row_at_index(0) {
+            list.select_row(Some(&first));
+        }
+
+        window.add(&vbox);
+        window.show_all();
+        search.grab_focus();
+        window.present();
+    });
+    app.run();
+}
@@ -0,0 +1,549 @@
+//! The image viewer, and the player for animated images.
+
+use gtk::prelude::*;
+use gtk::{Application, ApplicationWindow, Box, Button, Orientation};
+use std::cell::RefCell;
+use std::rc::Rc;
+use waypin_lib::convert::{encode_image, transcode_image, ImageFormat, DEFAULT_JPEG_QUALITY};
+use waypin_lib::zoom::{initial_window_size, zoom_in, zoom_label, zoom_out, Zoom};
+use waypin_lib::{
+    copy_to_clipboard, is_svg_mime_type, ClipboardBackend, ClipboardContentType, Selection,
+    WindowConfig,
+};
+
+use super::canvas::ImageCanvas;
+use super::editor::ImageEditor;
+use super::{
+    accept_dropped_files, begin_move, choose_save_path, connect_page_keys, is_save_shortcut,
+    is_typing, pin_overlay, screen_size, viewer_window,
+};
+use crate::pixbuf::{decode_image, PixbufCodec};
+
+pub fn show_clipboard_image(
+    backend: Rc<dyn ClipboardBackend>,
+    selection: Selection,
+    config: WindowConfig,
+    img_data: &[u8],
+    mime_type: &str,
+) {
+    let app = Application::new(None, Default::default());
+    let img_data_owned = img_data.to_vec();
+    let mime_type_owned = mime_type.to_string();
+
+    app.connect_activate(move |app| {
+        let window = viewer_window(app, &format!("{} Image", selection.label()), &config);
+        window.set_resizable(true);
+        window.set_decorated(false); // Remove titlebar
+
+        // Add drag functionality and motion tracking
+        window.add_events(
+            gtk::gdk::EventMask::BUTTON_PRESS_MASK
+                | gtk::gdk::EventMask::POINTER_MOTION_MASK
+                | gtk::gdk::EventMask::ENTER_NOTIFY_MASK
+                | gtk::gdk::EventMask::LEAVE_NOTIFY_MASK,
+        );
+
+        // A pinned overlay is dragged by its margins instead
+        let pinned = pin_overlay(&window, &config);
+        window.connect_button_press_event(move |window, event| {
+            if event.button() == 1 && !pinned {
+                // Left mouse button
+                begin_move(window, event);
+            }
+            false.into()
+        });
+
+        let Some((page, (orig_width, orig_height))) = image_page(
+            &window,
+            &backend,
+            selection,
+            &config,
+            &img_data_owned,
+            &mime_type_owned,
+        ) else {
+            return;
+        };
+        accept_dropped_files(&window, &backend, selection);
+        window.add(&page);
+
+        // Open at the image's own size, unless it doesn't fit the screen
+        let (width, height) = match screen_size(&window) {
+            Some(screen) => initial_window_size((orig_width, orig_height), screen),
+            None => (orig_width, orig_height),
+        };
+        window.set_default_size(width, height);
+        window.set_size_request(100, 100); // allow smaller resizing
+
+        window.show_all();
+        window.present();
+    });
+    app.run();
+}
+
+/// The image viewer: the zoomable image with its overlaid controls, and
+/// the image's own size. `None` when the image cannot be decoded.
+pub fn image_page(
+    window: &ApplicationWindow,
+    backend: &Rc<dyn ClipboardBackend>,
+    selection: Selection,
+    config: &WindowConfig,
+    img_data: &[u8],
+    mime_type: &str,
+) -> Option<(gtk::Overlay, (i32, i32))> {
+    let img_data_owned = img_data.to_vec();
+    let mime_type_owned = mime_type.to_string();
+    let loader = match decode_image(&img_data_owned, &mime_type_owned, None) {
+        Ok(loader) => loader,
+        Err(e) => {
+            eprintln!("{}", e);
+            return None;
+        }
+    };
+    let Some(orig_pixbuf) = loader.pixbuf() else {
+        eprintln!("Failed to decode image data.");
+        return None;
+    };
+    let animation = loader
+        .animation()
+        .filter(|animation| !animation.is_static_image());
+    // Use Overlay to place button on top of image
+    let overlay = gtk::Overlay::new();
+
+    // Image area, zoomed and panned inside a scrolled window
+    let svg_data = is_svg_mime_type(&mime_type_owned).then(|| img_data_owned.clone());
+    let canvas = ImageCanvas::new(orig_pixbuf.clone(), svg_data);
+
+    // Add scrolled window as the main child of overlay
+    overlay.add(&canvas.scrolled);
+
+    // Annotation and transform tools, for still images only
+    let editor = animation
+        .is_none()
+        .then(|| ImageEditor::new(canvas.clone(), orig_pixbuf.clone()));
+
+    // Edit, save and copy buttons as overlay
+    let actions = Box::new(Orientation::Horizontal, 6);
+    actions.set_margin_top(10);
+    actions.set_margin_end(10);
+    actions.set_halign(gtk::Align::End);
+    actions.set_valign(gtk::Align::Start);
+    if let Some(editor) = &editor {
+        let edit_btn = gtk::ToggleButton::with_label("Edit");
+        let editor_for_toggle = editor.clone();
+        edit_btn.connect_toggled(move |button| {
+            editor_for_toggle.set_active(button.is_active());
+        });
+        actions.pack_start(&edit_btn, false, false, 0);
+        overlay.add_overlay(&editor.toolbar);
+    }
+
+    // Format to copy as, with the JPEG quality
+    let format_combo = gtk::ComboBoxText::new();
+    format_combo.append(None, "Original");
+    for format in ImageFormat::ALL {
+        format_combo.append(Some(format.mime_type()), format.label());
+    }
+    format_combo.set_active(Some(0));
+    format_combo.set_tooltip_text(Some(
+        "Format to copy or save as; edited images default to PNG",
+    ));
+    let quality_spin = gtk::SpinButton::with_range(1.0, 100.0, 5.0);
+    quality_spin.set_value(DEFAULT_JPEG_QUALITY as f64);
+    quality_spin.set_tooltip_text(Some("JPEG quality"));
+    quality_spin.set_sensitive(false);
+    let quality_for_combo = quality_spin.clone();
+    format_combo.connect_changed(move |combo| {
+        quality_for_combo.set_sensitive(combo.active_id().as_deref() == Some("image/jpeg"));
+    });
+    actions.pack_start(&format_combo, false, false, 0);
+    actions.pack_start(&quality_spin, false, false, 0);
+
+    let save_btn = Button::with_label("Save As…");
+    save_btn.set_tooltip_text(Some("Save to a file (Ctrl+S)"));
+    actions.pack_start(&save_btn, false, false, 0);
+    let copy_btn = Button::with_label(&format!("Copy to {}", selection.label()));
+    actions.pack_start(&copy_btn, false, false, 0);
+
+    // The image to copy or save as `(mime_type, data)` pairs, the one to
+    // save first: the original data unless a format is chosen, and the
+    // original after the chosen format so the pasting app can pick. An
+    // edited image is always encoded, as PNG by default
+    let img_data_clone = img_data_owned.clone();
+    let mime_type_clone = mime_type_owned.clone();
+    let editor_for_output = editor.clone();
+    let output = Rc::new(move || -> Result<Vec<(String, Vec<u8>)>, String> {
+        let format = format_combo.active_id().and_then(|mime_type| {
+            ImageFormat::from_mime_type(&mime_type, quality_spin.value_as_int() as u8)
+        });
+        let edited = editor_for_output
+            .as_ref()
+            .and_then(|editor| editor.edited_image());
+        let mut contents = match (edited, format) {
+            (Some(image), format) => {
+                return encode_image(&PixbufCodec, &image, &[format.unwrap_or(ImageFormat::Png)])
+            }
+            (None, Some(format)) => {
+                transcode_image(&PixbufCodec, &mime_type_clone, &img_data_clone, &[format])?
+            }
+            (None, None) => Vec::new(),
+        };
+        if !contents
+            .iter()
+            .any(|(mime_type, _)| *mime_type == mime_type_clone)
+        {
+            contents.push((mime_type_clone.clone(), img_data_clone.clone()));
+        }
+        Ok(contents)
+    });
+
+    let backend_clone = backend.clone();
+    let output_for_copy = output.clone();
+    copy_btn.connect_clicked(move |_| {
+        let result = output_for_copy().and_then(|contents| {
+            let contents: Vec<(&str, &[u8])> = contents
+                .iter()
+                .map(|(mime_type, data)| (mime_type.as_str(), data.as_slice()))
+                .collect();
+            copy_to_clipboard(&*backend_clone, &contents)
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to copy image: {}", e);
+        }
+    });
+
+    // Save As… and Ctrl+S write the same image to a file
+    let save_image = {
+        let window = window.clone();
+        move || {
+            let result = output().and_then(|mut contents| {
+                let (mime_type, data) = contents.remove(0);
+                let Some(path) = choose_save_path(&window, ClipboardContentType::Image, &mime_type)
+                else {
+                    return Ok(());
+                };
+                std::fs::write(&path, data)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+            });
+            if let Err(e) = result {
+                eprintln!("Failed to save image: {}", e);
+            }
+        }
+    };
+    let save_image = Rc::new(save_image);
+    let save_image_for_button = save_image.clone();
+    save_btn.connect_clicked(move |_| save_image_for_button());
+    connect_page_keys(window, &overlay, move |window, event| {
+        if is_save_shortcut(event) && !is_typing(window) {
+            save_image();
+            return true.into();
+        }
+        false.into()
+    });
+
+    // Add buttons as overlay
+    overlay.add_overlay(&actions);
+
+    // Zoom controls and indicator
+    let zoom_box = Box::new(Orientation::Horizontal, 6);
+    zoom_box.set_margin_top(10);
+    zoom_box.set_margin_start(10);
+    zoom_box.set_halign(gtk::Align::Start);
+    zoom_box.set_valign(gtk::Align::Start);
+    let fit_btn = Button::with_label("Fit");
+    fit_btn.set_tooltip_text(Some("Fit to window (0)"));
+    let actual_size_btn = Button::with_label("1:1");
+    actual_size_btn.set_tooltip_text(Some("Actual size (1)"));
+    let zoom_indicator = gtk::Label::new(Some(&zoom_label(1.0)));
+    zoom_indicator.set_margin_start(4);
+    zoom_box.pack_start(&fit_btn, false, false, 0);
+    zoom_box.pack_start(&actual_size_btn, false, false, 0);
+    zoom_box.pack_start(&zoom_indicator, false, false, 0);
+    overlay.add_overlay(&zoom_box);
+
+    // Widgets that fade out together when the pointer is idle
+    let mut fading: Vec<gtk::Widget> = vec![actions.clone().upcast(), zoom_box.clone().upcast()];
+    if let Some(editor) = &editor {
+        fading.push(editor.toolbar.clone().upcast());
+
+        // Ctrl+Z undoes, Ctrl+Shift+Z or Ctrl+Y redoes
+        let editor_for_keys = editor.clone();
+        connect_page_keys(window, &overlay, move |window, event| {
+            use gtk::gdk::keys::constants as key;
+            if !editor_for_keys.is_active()
+                || !event.state().contains(gtk::gdk::ModifierType::CONTROL_MASK)
+                || is_typing(window)
+            {
+                return false.into();
+            }
+            match event.keyval() {
+                key::z => editor_for_keys.undo(),
+                key::Z | key::y => editor_for_keys.redo(),
+                _ => return false.into(),
+            }
+            true.into()
+        });
+    }
+
+    if let Some(animation) = &animation {
+        let canvas_for_player = canvas.clone();
+        let player = AnimationPlayer::new(animation, move |frame| {
+            canvas_for_player.set_frame(frame.clone());
+        });
+
+        let controls = Box::new(Orientation::Horizontal, 6);
+        controls.set_margin_bottom(10);
+        controls.set_halign(gtk::Align::Center);
+        controls.set_valign(gtk::Align::End);
+        let play_btn = Button::with_label("Pause");
+        let step_btn = Button::with_label("Next Frame");
+        controls.pack_start(&play_btn, false, false, 0);
+        controls.pack_start(&step_btn, false, false, 0);
+        overlay.add_overlay(&controls);
+        fading.push(controls.clone().upcast());
+
+        let player_for_play = player.clone();
+        play_btn.connect_clicked(move |button| {
+            player_for_play.toggle();
+            button.set_label(if player_for_play.is_playing() {
+                "Pause"
+            } else {
+                "Play"
+            });
+        });
+        let player_for_step = player.clone();
+        let play_btn_for_step = play_btn.clone();
+        step_btn.connect_clicked(move |_| {
+            player_for_step.step_once();
+            play_btn_for_step.set_label("Play");
+        });
+
+        // Space toggles playback, Right steps one frame
+        let player_for_keys = player.clone();
+        connect_page_keys(window, &overlay, move |_, event| {
+            let key = event.keyval();
+            if key == gtk::gdk::keys::constants::space {
+                player_for_keys.toggle();
+                play_btn.set_label(if player_for_keys.is_playing() {
+                    "Pause"
+                } else {
+                    "Play"
+                });
+                return true.into();
+            }
+            if key == gtk::gdk::keys::constants::Right {
+                player_for_keys.step_once();
+                play_btn.set_label("Play");
+                return true.into();
+            }
+            false.into()
+        });
+
+        player.play();
+    }
+
+    // Button fade functionality
+    let fade_timeout = Rc::new(RefCell::new(None::<gtk::glib::SourceId>));
+    let timer_active = Rc::new(RefCell::new(false));
+    let fading_for_timer = fading.clone();
+    let fade_timeout_clone = fade_timeout.clone();
+    let timer_active_clone = timer_active.clone();
+    let fade_seconds = config.fade_seconds;
+
+    // Function to start fade timer
+    let start_fade_timer = move || {
+        // Cancel existing timer if active
+        if *timer_active_clone.borrow() {
+            if let Some(timeout_id) = fade_timeout_clone.borrow_mut().take() {
+                timeout_id.remove();
+            }
+            *timer_active_clone.borrow_mut() = false;
+        }
+
+        // A fade of 0 seconds keeps the controls shown
+        if fade_seconds == 0 {
+            return;
+        }
+        let fading_for_timeout = fading_for_timer.clone();
+        let timer_active_for_timeout = timer_active_clone.clone();
+        let timeout_id = gtk::glib::timeout_add_seconds_local(fade_seconds, move || {
+            for widget in &fading_for_timeout {
+                widget.set_opacity(0.0);
+            }
+            *timer_active_for_timeout.borrow_mut() = false;
+            gtk::glib::ControlFlow::Break
+        });
+        *fade_timeout_clone.borrow_mut() = Some(timeout_id);
+        *timer_active_clone.borrow_mut() = true;
+    };
+
+    // Function to show button
+    let show_button = {
+        let fading_show = fading.clone();
+        let start_timer = start_fade_timer.clone();
+        move || {
+            for widget in &fading_show {
+                widget.set_opacity(1.0);
+            }
+            start_timer();
+        }
+    };
+
+    // Motion event handler
+    let show_button_motion = show_button.clone();
+    window.connect_motion_notify_event(move |_window, _event| {
+        show_button_motion();
+        false.into()
+    });
+
+    // Add motion tracking to overlay as well
+    let show_button_overlay_motion = show_button.clone();
+    overlay.connect_enter_notify_event(move |_overlay, _event| {
+        show_button_overlay_motion();
+        false.into()
+    });
+
+    // Enter/Leave notify handlers
+    let show_button_enter = show_button.clone();
+    window.connect_enter_notify_event(move |_window, _event| {
+        show_button_enter();
+        false.into()
+    });
+
+    let show_button_focus = show_button.clone();
+    window.connect_focus_in_event(move |_window, _event| {
+        show_button_focus();
+        false.into()
+    });
+
+    // Zoom changes update the indicator and bring the overlay back
+    let show_button_zoom = show_button.clone();
+    canvas.connect_zoom_changed(move |scale| {
+        zoom_indicator.set_text(&zoom_label(scale));
+        show_button_zoom();
+    });
+    let canvas_for_fit = canvas.clone();
+    fit_btn.connect_clicked(move |_| canvas_for_fit.set_zoom(Zoom::Fit, None));
+    let canvas_for_actual = canvas.clone();
+    actual_size_btn.connect_clicked(move |_| canvas_for_actual.set_zoom(Zoom::Scale(1.0), None));
+
+    // +/- zoom around the centre, 1 shows actual size, 0 fits
+    let canvas_for_keys = canvas.clone();
+    connect_page_keys(window, &overlay, move |window, event| {
+        use gtk::gdk::keys::constants as key;
+        if is_typing(window) {
+            return false.into();
+        }
+        let zoom = match event.keyval() {
+            key::plus | key::equal | key::KP_Add => Zoom::Scale(zoom_in(canvas_for_keys.scale())),
+            key::minus | key::KP_Subtract => Zoom::Scale(zoom_out(canvas_for_keys.scale())),
+            key::_1 | key::KP_1 => Zoom::Scale(1.0),
+            key::_0 | key::KP_0 => Zoom::Fit,
+            _ => return false.into(),
+        };
+        canvas_for_keys.set_zoom(zoom, None);
+        true.into()
+    });
+
+    // Start initial fade timer
+    start_fade_timer();
+
+    let canvas_for_resize = canvas.clone();
+    overlay.connect_size_allocate(move |_, alloc| {
+        let w = alloc.width();
+        let h = alloc.height();
+        if w > 0 && h > 0 {
+            canvas_for_resize.set_area((w, h));
+        }
+    });
+
+    // Set initial image at original size
+    canvas.render();
+
+    Some((overlay, (orig_pixbuf.width(), orig_pixbuf.height())))
+}
+
+/// Plays an animated image frame by frame. Time only moves while playing
+/// or stepping, so pausing holds the current frame.
+struct AnimationPlayer {
+    iter: gtk::gdk_pixbuf::PixbufAnimationIter,
+    clock: std::cell::Cell<std::time::SystemTime>,
+    playing: std::cell::Cell<bool>,
+    timer: RefCell<Option<gtk::glib::SourceId>>,
+    on_frame: std::boxed::Box<dyn Fn(&gtk::gdk_pixbuf::Pixbuf)>,
+}
+
+impl AnimationPlayer {
+    fn new(
+        animation: &gtk::gdk_pixbuf::PixbufAnimation,
+        on_frame: impl Fn(&gtk::gdk_pixbuf::Pixbuf) + 'static,
+    ) -> Rc<Self> {
+        let start = std::time::SystemTime::now();
+        Rc::new(Self {
+            iter: animation.iter(Some(start)),
+            clock: std::cell::Cell::new(start),
+            playing: std::cell::Cell::new(false),
+            timer: RefCell::new(None),
+            on_frame: std::boxed::Box::new(on_frame),
+        })
+    }
+
+    fn is_playing(&self) -> bool {
+        self.playing.get()
+    }
+
+    fn play(self: &Rc<Self>) {
+        self.playing.set(true);
+        self.schedule();
+    }
+
+    fn pause(&self) {
+        self.playing.set(false);
+        if let Some(timer) = self.timer.borrow_mut().take() {
+            timer.remove();
+        }
+    }
+
+    fn toggle(self: &Rc<Self>) {
+        if self.is_playing() {
+            self.pause();
+        } else {
+            self.play();
+        }
+    }
+
+    /// Pauses and shows the next frame.
+    fn step_once(&self) {
+        self.pause();
+        self.advance();
+    }
+
+    fn advance(&self) {
+        // A frame without a delay is shown forever
+        let Some(delay) = self.iter.delay_time() else {
+            return;
+        };
+        self.clock.set(self.clock.get() + delay);
+        self.iter.advance(self.clock.get());
+        (self.on_frame)(&self.iter.pixbuf());
+    }
+
+    fn schedule(self: &Rc<Self>) {
+        let Some(delay) = self.iter.delay_time() else {
+            return;
+        };
+        let player = self.clone();
+        let timer = gtk::glib::timeout_add_local(delay, move || {
+            // The source ends when this returns, so forget it before
+            // scheduling the next one
+            player.timer.borrow_mut().take();
+            player.advance();
+            if player.is_playing() {
+                player.schedule();
+            }
+            gtk::glib::ControlFlow::Break
+        });
+        if let Some(previous) = self.timer.borrow_mut().replace(timer) {
+            previous.remove();
+        }
+    }
+}
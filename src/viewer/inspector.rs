@@ -0,0 +1,169 @@
+//! `waypin inspect`: every offered MIME type with a preview.
+
+use gtk::prelude::*;
+use gtk::Adjustment;
+use gtk::{Application, Box, Button, Orientation, ScrolledWindow, TextView};
+use std::rc::Rc;
+use waypin_lib::inspect::{preview, InspectedType, Preview, PREVIEW_LEN};
+use waypin_lib::uri_list::format_size;
+use waypin_lib::{copy_to_clipboard, ClipboardBackend, Selection, WindowConfig};
+
+use super::{choose_save_path, set_margins, viewer_window};
+
+pub fn show_inspector(
+    backend: Rc<dyn ClipboardBackend>,
+    selection: Selection,
+    config: WindowConfig,
+    types: Vec<InspectedType>,
+) {
+    let app = Application::new(None, Default::default());
+    app.connect_activate(move |app| {
+        let window = viewer_window(app, &format!("{} Types", selection.label()), &config);
+        window.set_default_size(config.inspector_size.0, config.inspector_size.1);
+
+        let vbox = Box::new(Orientation::Vertical, 10);
+        set_margins(&vbox, config.margin);
+
+        // Rows and types are kept in the same order
+        let types = Rc::new(types.clone());
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::Browse);
+        for inspected in types.iter() {
+            let row_box = Box::new(Orientation::Horizontal, 10);
+            row_box.set_margin_top(4);
+            row_box.set_margin_bottom(4);
+            row_box.set_margin_start(6);
+            row_box.set_margin_end(6);
+
+            let name = gtk::Label::new(Some(&inspected.mime_type));
+            name.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+            name.set_xalign(0.0);
+            row_box.pack_start(&name, true, true, 0);
+
+            let size = match inspected.size() {
+                Some(size) => format_size(size as u64),
+                None => "unreadable".to_string(),
+            };
+            let size = gtk::Label::new(Some(&size));
+            size.style_context().add_class("dim-label");
+            row_box.pack_end(&size, false, false, 0);
+
+            list.add(&row_box);
+        }
+
+        let list_scrolled = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+        list_scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        list_scrolled.set_size_request(280, -1);
+        list_scrolled.add(&list);
+
+        let summary = gtk::Label::new(None);
+        summary.set_xalign(0.0);
+        summary.style_context().add_class("dim-label");
+
+        let preview_view = TextView::new();
+        preview_view.set_editable(false);
+        preview_view.set_cursor_visible(false);
+        preview_view.set_monospace(true);
+        let preview_scrolled = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+        preview_scrolled.add(&preview_view);
+
+        let preview_box = Box::new(Orientation::Vertical, 6);
+        preview_box.pack_start(&summary, false, false, 0);
+        preview_box.pack_start(&preview_scrolled, true, true, 0);
+
+        let paned = gtk::Paned::new(Orientation::Horizontal);
+        paned.pack1(&list_scrolled, false, false);
+        paned.pack2(&preview_box, true, false);
+        vbox.pack_start(&paned, true, true, 0);
+
+        let types_for_preview = types.clone();
+        list.connect_row_selected(move |_, row| {
+            let Some(inspected) = row.and_then(|row| types_for_preview.get(row.index() as usize))
+            else {
+                return;
+            };
+            let (description, text) = match &inspected.data {
+                Ok(data) => {
+                    let (kind, text) = match preview(data, PREVIEW_LEN) {
+                        Preview::Text(text) => ("text", text),
+                        Preview::Hex(dump) => ("hex", dump),
+                    };
+                    let mut description = format!(
+                        "{}, {} ({} bytes)",
+                        inspected.content_type.label(),
+                        kind,
+                        data.len()
+                    );
+                    if data.len() > PREVIEW_LEN {
+                        description.push_str(&format!(
+                            ", first {} shown",
+                            format_size(PREVIEW_LEN as u64)
+                        ));
+                    }
+                    (description, text)
+                }
+                Err(e) => ("Could not read this type".to_string(), e.clone()),
+            };
+            summary.set_text(&description);
+            preview_view.buffer().unwrap().set_text(&text);
+        });
+
+        let button_box = Box::new(Orientation::Horizontal, 6);
+        button_box.set_margin_top(10);
+
+        let selected_data = {
+            let list = list.clone();
+            let types = types.clone();
+            move || {
+                let row = list.selected_row()?;
+                let inspected = types.get(row.index() as usize)?;
+                let data = inspected.data.as_ref().ok()?;
+                Some((inspected.clone(), data.clone()))
+            }
+        };
+
+        let save_btn = Button::with_label("Save This Type…");
+        let window_for_save = window.clone();
+        let selected_for_save = selected_data.clone();
+        save_btn.connect_clicked(move |_| {
+            let Some((inspected, data)) = selected_for_save() else {
+                return;
+            };
+            let Some(path) = choose_save_path(
+                &window_for_save,
+                inspected.content_type,
+                &inspected.mime_type,
+            ) else {
+                return;
+            };
+            if let Err(e) = std::fs::write(&path, data) {
+                eprintln!("Failed to write {}: {}", path.display(), e);
+            }
+        });
+        button_box.pack_start(&save_btn, false, false, 0);
+
+        let copy_btn = Button::with_label("Copy This Type Only");
+        let backend_for_copy = backend.clone();
+        copy_btn.connect_clicked(move |_| {
+            let Some((inspected, data)) = selected_data() else {
+                return;
+            };
+            if let Err(e) = copy_to_clipboard(&*backend_for_copy, &[(&inspected.mime_type, &data)])
+            {
+                eprintln!("Failed to copy {}: {}", inspected.mime_type, e);
+            }
+        });
+        button_box.pack_end(&copy_btn, false, false, 0);
+
+        vbox.pack_start(&button_box, false, false, 0);
+
+        if let Some(first) = list.row_at_index(0) {
+            list.select_row(Some(&first));
+        }
+
+        window.add(&vbox);
+        window.show_all();
+        window.present();
+    });
+    app.run();
+}
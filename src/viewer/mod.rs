@@ -0,0 +1,357 @@
+//! The GTK windows, one module per viewer, and what they share.
+
+mod canvas;
+mod color;
+mod editor;
+mod file_list;
+mod hex;
+mod history;
+mod image;
+mod inspector;
+mod rich_text;
+mod text;
+
+pub use color::show_color;
+pub use file_list::show_file_list;
+pub use hex::show_hex_viewer;
+pub use history::show_history;
+pub use image::show_clipboard_image;
+pub use inspector::show_inspector;
+pub use rich_text::show_rich_text;
+pub use text::show_clipboard_text;
+
+use gtk::prelude::*;
+use gtk::{Application, ApplicationWindow};
+#[cfg(feature = "layer-shell")]
+use std::cell::Cell;
+use std::rc::Rc;
+use waypin_lib::cli::{config_args, parse_args};
+use waypin_lib::history::now_millis;
+use waypin_lib::load::load_files;
+#[cfg(feature = "layer-shell")]
+use waypin_lib::overlay::dragged_margins;
+use waypin_lib::save::{default_file_name, default_save_dir};
+use waypin_lib::snapshot::ViewerRequest;
+use waypin_lib::uri_list::UriEntry;
+use waypin_lib::{ClipboardBackend, ClipboardContentType, Selection, WindowConfig};
+
+#[cfg(feature = "layer-shell")]
+use crate::layer_shell;
+use crate::Backends;
+use color::color_page;
+use file_list::file_list_page;
+use hex::hex_page;
+use image::image_page;
+use rich_text::rich_text_page;
+use text::text_page;
+
+/// A window for a viewer, titled `title`, with the hints every viewer
+/// shares and Escape to close it.
+pub fn viewer_window(app: &Application, title: &str, config: &WindowConfig) -> ApplicationWindow {
+    let window = ApplicationWindow::new(app);
+    window.set_title(title);
+    apply_window_hints(&window, config);
+
+    window.add_events(gtk::gdk::EventMask::KEY_PRESS_MASK);
+    window.connect_key_press_event(|window, event| {
+        if event.keyval() == gtk::gdk::keys::constants::Escape {
+            window.close();
+        }
+        false.into()
+    });
+    window
+}
+
+/// Connects a key handler for one viewer page to its window. The handler
+/// is skipped while the page is hidden, so a tab only answers its own
+/// shortcuts.
+pub fn connect_page_keys<F>(window: &ApplicationWindow, page: &impl IsA<gtk::Widget>, handler: F)
+where
+    F: Fn(&ApplicationWindow, &gtk::gdk::EventKey) -> gtk::glib::Propagation + 'static,
+{
+    let page = page.upcast_ref::<gtk::Widget>().downgrade();
+    window.connect_key_press_event(move |window, event| {
+        if !page.upgrade().is_some_and(|page| page.is_mapped()) {
+            return false.into();
+        }
+        handler(window, event)
+    });
+}
+
+/// Dialog hints every viewer window shares. Wayland lets no client keep
+/// itself above other windows, so `keep_above` only has an effect on X11.
+fn apply_window_hints(window: &ApplicationWindow, config: &WindowConfig) {
+    window.set_type_hint(gtk::gdk::WindowTypeHint::Dialog);
+    window.set_keep_above(config.keep_above);
+    window.set_modal(config.modal);
+}
+
+/// Starts moving `window` with the pointer that pressed `event`. Passing
+/// the event's device lets GTK hand the compositor the right seat on
+/// Wayland, where the root coordinates are ignored.
+pub fn begin_move(window: &ApplicationWindow, event: &gtk::gdk::EventButton) {
+    let (x, y) = event.root();
+    let button = event.button() as i32;
+    match (window.window(), event.device()) {
+        (Some(gdk_window), Some(device)) => {
+            gdk_window.begin_move_drag_for_device(&device, button, x as i32, y as i32, event.time())
+        }
+        _ => window.begin_move_drag(button, x as i32, y as i32, event.time()),
+    }
+}
+
+/// Pins `window` as a layer-shell overlay when `[overlay]` asks for it and
+/// the compositor supports it, and lets it be dragged around by changing
+/// its margins. Returns whether it was pinned; must be called before the
+/// window is shown.
+#[cfg(feature = "layer-shell")]
+pub fn pin_overlay(window: &ApplicationWindow, config: &WindowConfig) -> bool {
+    if !config.overlay.enabled {
+        return false;
+    }
+    if !layer_shell::is_supported() {
+        eprintln!("The compositor does not support layer-shell; opening a normal window.");
+        return false;
+    }
+    let (corner, margins) = config.overlay.placement();
+    layer_shell::pin(window, corner, margins);
+
+    // The surface follows the pointer, so the pointer stays where the drag
+    // started relative to it, and each motion is an offset from there
+    let margins = Rc::new(Cell::new(margins));
+    let drag_start = Rc::new(Cell::new(None::<(f64, f64)>));
+    window.add_events(
+        gtk::gdk::EventMask::BUTTON_PRESS_MASK
+            | gtk::gdk::EventMask::BUTTON_RELEASE_MASK
+            | gtk::gdk::EventMask::POINTER_MOTION_MASK,
+    );
+    let drag_start_for_press = drag_start.clone();
+    window.connect_button_press_event(move |_, event| {
+        if event.button() == 1 {
+            drag_start_for_press.set(Some(event.root()));
+        }
+        false.into()
+    });
+    let drag_start_for_release = drag_start.clone();
+    window.connect_button_release_event(move |_, _| {
+        drag_start_for_release.set(None);
+        false.into()
+    });
+    window.connect_motion_notify_event(move |window, event| {
+        if let Some(start) = drag_start.get() {
+            let (x, y) = event.root();
+            let delta = ((x - start.0) as i32, (y - start.1) as i32);
+            let moved = dragged_margins(corner, margins.get(), delta);
+            margins.set(moved);
+            layer_shell::set_margins(window, corner, moved);
+        }
+        false.into()
+    });
+    true
+}
+
+#[cfg(not(feature = "layer-shell"))]
+pub fn pin_overlay(_window: &ApplicationWindow, config: &WindowConfig) -> bool {
+    if config.overlay.enabled {
+        eprintln!("waypin was built without the layer-shell feature; opening a normal window.");
+    }
+    false
+}
+
+/// The work area of the monitor new windows open on. Wayland has no
+/// primary monitor, so the first one stands in.
+pub fn screen_size(window: &ApplicationWindow) -> Option<(i32, i32)> {
+    let display = WidgetExt::display(window);
+    let monitor = display.primary_monitor().or_else(|| display.monitor(0))?;
+    let area = monitor.workarea();
+    Some((area.width(), area.height()))
+}
+
+pub fn set_margins(widget: &impl IsA<gtk::Widget>, margin: i32) {
+    widget.set_margin_top(margin);
+    widget.set_margin_bottom(margin);
+    widget.set_margin_start(margin);
+    widget.set_margin_end(margin);
+}
+
+/// Whether the key event is Ctrl+S.
+pub fn is_save_shortcut(event: &gtk::gdk::EventKey) -> bool {
+    event.state().contains(gtk::gdk::ModifierType::CONTROL_MASK)
+        && matches!(
+            event.keyval(),
+            gtk::gdk::keys::constants::s | gtk::gdk::keys::constants::S
+        )
+}
+
+/// Asks where to save content of `mime_type`, starting in the default
+/// directory for `content_type` with a timestamped file name.
+pub fn choose_save_path(
+    window: &ApplicationWindow,
+    content_type: ClipboardContentType,
+    mime_type: &str,
+) -> Option<std::path::PathBuf> {
+    let dialog = gtk::FileChooserDialog::with_buttons(
+        Some("Save As"),
+        Some(window),
+        gtk::FileChooserAction::Save,
+        &[
+            ("_Cancel", gtk::ResponseType::Cancel),
+            ("_Save", gtk::ResponseType::Accept),
+        ],
+    );
+    dialog.set_do_overwrite_confirmation(true);
+    if let Some(dir) = default_save_dir(content_type) {
+        dialog.set_current_folder(dir);
+    }
+    dialog.set_current_name(&default_file_name(mime_type, now_millis() / 1000));
+    let path = match dialog.run() {
+        gtk::ResponseType::Accept => dialog.filename(),
+        _ => None,
+    };
+    dialog.close();
+    path
+}
+
+/// Whether keys should go to a focused text entry rather than shortcuts.
+pub fn is_typing(window: &ApplicationWindow) -> bool {
+    window
+        .focused_widget()
+        .is_some_and(|widget| widget.is::<gtk::Entry>())
+}
+
+/// The page showing `request`, for a tab of the tabbed viewer.
+fn viewer_page(
+    window: &ApplicationWindow,
+    backends: &Backends,
+    selection: Selection,
+    config: &WindowConfig,
+    request: &ViewerRequest,
+) -> Option<gtk::Widget> {
+    let backend = backends.get(selection);
+    let page = match request {
+        ViewerRequest::Text(text) => text_page(window, backends, config, text).upcast(),
+        ViewerRequest::Image { mime_type, data } => {
+            image_page(window, backend, selection, config, data, mime_type)?
+                .0
+                .upcast()
+        }
+        ViewerRequest::RichText { mime_type, markup } => {
+            rich_text_page(backend, config, mime_type, markup).upcast()
+        }
+        ViewerRequest::Files(entries) => file_list_page(backend, config, entries).upcast(),
+        ViewerRequest::Color {
+            mime_type,
+            data,
+            rgba,
+        } => color_page(backend, config, mime_type, data, *rgba).upcast(),
+        ViewerRequest::Binary { mime_type, data } => {
+            hex_page(window, backend, config, mime_type, data).upcast()
+        }
+    };
+    Some(page)
+}
+
+/// One window with a tab per representation, the first one showing.
+pub fn show_tabbed(
+    backends: Backends,
+    selection: Selection,
+    config: WindowConfig,
+    requests: Vec<ViewerRequest>,
+) {
+    let app = Application::new(None, Default::default());
+    app.connect_activate(move |app| {
+        let window = viewer_window(app, &format!("{} Contents", selection.label()), &config);
+        window.set_default_size(config.text_width, config.text_height);
+
+        // Pinned like the image and text viewers when it has one of their pages
+        if requests.iter().any(|request| {
+            matches!(
+                request,
+                ViewerRequest::Text(_) | ViewerRequest::Image { .. }
+            )
+        }) {
+            pin_overlay(&window, &config);
+        }
+
+        // Pointer motion for the image overlay
+        window.add_events(
+            gtk::gdk::EventMask::POINTER_MOTION_MASK
+                | gtk::gdk::EventMask::ENTER_NOTIFY_MASK
+                | gtk::gdk::EventMask::LEAVE_NOTIFY_MASK,
+        );
+
+        let notebook = gtk::Notebook::new();
+        notebook.set_scrollable(true);
+        for request in &requests {
+            if let Some(page) = viewer_page(&window, &backends, selection, &config, request) {
+                notebook.append_page(&page, Some(&gtk::Label::new(Some(request.label()))));
+            }
+        }
+
+        accept_dropped_files(&window, backends.get(selection), selection);
+        window.add(&notebook);
+        window.show_all();
+        window.present();
+    });
+    app.run();
+}
+
+/// Lets files be dropped on `window`: they are loaded into the clipboard
+/// and a fresh viewer replaces this one to show them.
+pub fn accept_dropped_files(
+    window: &ApplicationWindow,
+    backend: &Rc<dyn ClipboardBackend>,
+    selection: Selection,
+) {
+    window.drag_dest_set(
+        gtk::DestDefaults::ALL,
+        &[gtk::TargetEntry::new(
+            "text/uri-list",
+            gtk::TargetFlags::OTHER_APP,
+            0,
+        )],
+        gtk::gdk::DragAction::COPY,
+    );
+    let backend = backend.clone();
+    window.connect_drag_data_received(move |window, _, _, _, data, _, _| {
+        let paths: Vec<std::path::PathBuf> = data
+            .uris()
+            .iter()
+            .map(|uri| UriEntry::parse(uri))
+            .filter(UriEntry::is_local)
+            .filter_map(|entry| entry.path)
+            .collect();
+        if paths.is_empty() {
+            return;
+        }
+        match load_files(&*backend, &paths) {
+            Ok(_) => {
+                open_viewer(selection);
+                window.close();
+            }
+            Err(e) => eprintln!("Failed to load dropped files: {}", e),
+        }
+    });
+}
+
+/// Starts another waypin to view the new clipboard contents, with the
+/// `--config` and `--set` arguments this one was started with.
+fn open_viewer(selection: Selection) {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("Failed to find waypin: {}", e);
+            return;
+        }
+    };
+    let mut command = std::process::Command::new(exe);
+    if selection == Selection::Primary {
+        command.arg("--primary");
+    }
+    if let Ok(options) = parse_args(std::env::args().skip(1)) {
+        command.args(config_args(&options));
+    }
+    if let Err(e) = command.spawn() {
+        eprintln!("Failed to open viewer: {}", e);
+    }
+}
@@ -0,0 +1,215 @@
+//! The HTML and RTF viewer.
+
+use gtk::prelude::*;
+use gtk::{Application, Box, Button, Orientation, ScrolledWindow, TextView};
+use std::cell::RefCell;
+use std::rc::Rc;
+use waypin_lib::rich_text::{is_rtf_mime_type, markup_to_styled_text, StyledText, TextStyle};
+use waypin_lib::{ClipboardBackend, Selection, WindowConfig};
+
+use super::{accept_dropped_files, set_margins, viewer_window};
+
+/// Fills `buffer` with converted text and applies its spans as tags,
+/// returning the link ranges so clicks can be mapped back to targets.
+fn set_styled_text(buffer: &gtk::TextBuffer, styled: &StyledText) -> Vec<(i32, i32, String)> {
+    buffer.set_text(&styled.text);
+    let mut links = Vec::new();
+    let Some(tag_table) = buffer.tag_table() else {
+        return links;
+    };
+    for span in &styled.spans {
+        let tag_name = match &span.style {
+            TextStyle::Bold => "bold".to_string(),
+            TextStyle::Italic => "italic".to_string(),
+            TextStyle::Underline => "underline".to_string(),
+            TextStyle::Strikethrough => "strikethrough".to_string(),
+            TextStyle::Monospace => "monospace".to_string(),
+            TextStyle::Heading(level) => format!("heading{}", level),
+            TextStyle::Link(target) => {
+                links.push((span.start as i32, span.end as i32, target.clone()));
+                "link".to_string()
+            }
+        };
+        if tag_table.lookup(&tag_name).is_none() {
+            let tag = gtk::TextTag::new(Some(&tag_name));
+            match &span.style {
+                TextStyle::Bold => tag.set_weight(700),
+                TextStyle::Italic => tag.set_style(gtk::pango::Style::Italic),
+                TextStyle::Underline => tag.set_underline(gtk::pango::Underline::Single),
+                TextStyle::Strikethrough => tag.set_strikethrough(true),
+                TextStyle::Monospace => tag.set_family(Some("monospace")),
+                TextStyle::Heading(level) => {
+                    tag.set_weight(700);
+                    tag.set_scale(match level {
+                        1 => 1.6,
+                        2 => 1.4,
+                        3 => 1.2,
+                        _ => 1.0,
+                    });
+                }
+                TextStyle::Link(_) => {
+                    tag.set_foreground(Some("#3584e4"));
+                    tag.set_underline(gtk::pango::Underline::Single);
+                }
+            }
+            tag_table.add(&tag);
+        }
+        let start = buffer.iter_at_offset(span.start as i32);
+        let end = buffer.iter_at_offset(span.end as i32);
+        buffer.apply_tag_by_name(&tag_name, &start, &end);
+    }
+    links
+}
+
+pub fn show_rich_text(
+    backend: Rc<dyn ClipboardBackend>,
+    selection: Selection,
+    config: WindowConfig,
+    mime_type: &str,
+    markup: &str,
+) {
+    let app = Application::new(None, Default::default());
+    let mime_type = mime_type.to_string();
+    let markup = markup.to_string();
+    app.connect_activate(move |app| {
+        let format_name = if is_rtf_mime_type(&mime_type) {
+            "RTF"
+        } else {
+            "HTML"
+        };
+
+        let window = viewer_window(
+            app,
+            &format!("{} {}", selection.label(), format_name),
+            &config,
+        );
+        window.set_default_size(config.rich_text_size.0, config.rich_text_size.1);
+
+        let page = rich_text_page(&backend, &config, &mime_type, &markup);
+        accept_dropped_files(&window, &backend, selection);
+        window.add(&page);
+        window.show_all();
+        window.present();
+    });
+    app.run();
+}
+
+/// The rich text viewer: the formatted text with a markup toggle and
+/// copy buttons.
+pub fn rich_text_page(
+    backend: &Rc<dyn ClipboardBackend>,
+    config: &WindowConfig,
+    mime_type: &str,
+    markup: &str,
+) -> Box {
+    let mime_type = mime_type.to_string();
+    let markup = markup.to_string();
+    let styled = markup_to_styled_text(&mime_type, &markup);
+    let format_name = if is_rtf_mime_type(&mime_type) {
+        "RTF"
+    } else {
+        "HTML"
+    };
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    set_margins(&vbox, config.margin);
+
+    let scrolled = ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    scrolled.set_min_content_height(240);
+    scrolled.set_min_content_width(400);
+    let text_view = TextView::new();
+    text_view.set_wrap_mode(gtk::WrapMode::Word);
+    text_view.set_editable(false);
+    let links = Rc::new(RefCell::new(Vec::new()));
+    if let Some(buffer) = text_view.buffer() {
+        *links.borrow_mut() = set_styled_text(&buffer, &styled);
+    }
+    scrolled.add(&text_view);
+    vbox.pack_start(&scrolled, true, true, 0);
+
+    // Clicking a link opens it
+    let links_for_click = links.clone();
+    text_view.connect_button_release_event(move |text_view, event| {
+        if event.button() != 1 {
+            return false.into();
+        }
+        let (x, y) = event.position();
+        let (x, y) =
+            text_view.window_to_buffer_coords(gtk::TextWindowType::Widget, x as i32, y as i32);
+        if let Some(iter) = text_view.iter_at_location(x, y) {
+            let offset = iter.offset();
+            let links = links_for_click.borrow();
+            if let Some((_, _, target)) = links
+                .iter()
+                .find(|(start, end, _)| (*start..*end).contains(&offset))
+            {
+                if let Err(e) = gtk::gio::AppInfo::launch_default_for_uri(
+                    target,
+                    None::<&gtk::gio::AppLaunchContext>,
+                ) {
+                    eprintln!("Failed to open link: {}", e);
+                }
+            }
+        }
+        false.into()
+    });
+
+    let button_box = Box::new(Orientation::Horizontal, 6);
+    button_box.set_margin_top(10);
+
+    // Switches between the formatted view and the markup it came from
+    let raw_toggle = gtk::ToggleButton::with_label("Show Markup");
+    let text_view_for_toggle = text_view.clone();
+    let markup_for_toggle = markup.clone();
+    let links_for_toggle = links.clone();
+    raw_toggle.connect_toggled(move |toggle| {
+        let Some(buffer) = text_view_for_toggle.buffer() else {
+            return;
+        };
+        if toggle.is_active() {
+            buffer.set_text(&markup_for_toggle);
+            text_view_for_toggle.set_monospace(true);
+            links_for_toggle.borrow_mut().clear();
+        } else {
+            text_view_for_toggle.set_monospace(false);
+            *links_for_toggle.borrow_mut() = set_styled_text(&buffer, &styled);
+        }
+    });
+    button_box.pack_start(&raw_toggle, false, false, 0);
+
+    let copy_plain_btn = Button::with_label("Copy as Plain Text");
+    let backend_for_plain = backend.clone();
+    let plain_text = markup_to_styled_text(&mime_type, &markup).text;
+    copy_plain_btn.connect_clicked(move |_| {
+        let contents = [("text/plain;charset=utf-8", plain_text.as_bytes())];
+        if let Err(e) = backend_for_plain.write(&contents) {
+            eprintln!("Failed to copy text: {}", e);
+        }
+    });
+    button_box.pack_end(&copy_plain_btn, false, false, 0);
+
+    let copy_markup_btn = Button::with_label(&format!("Copy as {}", format_name));
+    if !backend.offers_multiple_types() {
+        copy_markup_btn.set_tooltip_text(Some(&format!(
+            "Copies the {} source only: this clipboard tool offers one type at a time",
+            format_name
+        )));
+    }
+    let backend_for_markup = backend.clone();
+    let mime_for_markup = mime_type.clone();
+    let markup_for_copy = markup.clone();
+    copy_markup_btn.connect_clicked(move |_| {
+        let plain_text = markup_to_styled_text(&mime_for_markup, &markup_for_copy).text;
+        let contents = [
+            (mime_for_markup.as_str(), markup_for_copy.as_bytes()),
+            ("text/plain;charset=utf-8", plain_text.as_bytes()),
+        ];
+        if let Err(e) = backend_for_markup.write(&contents) {
+            eprintln!("Failed to copy {}: {}", format_name, e);
+        }
+    });
+    button_box.pack_end(&copy_markup_btn, false, false, 0);
+
+    vbox.pack_start(&button_box, false, false, 0);
+    vbox
+}
@@ -0,0 +1,116 @@
+//! The text viewer.
+
+use gtk::prelude::*;
+use gtk::{Application, ApplicationWindow, Box, Button, Orientation, ScrolledWindow, TextView};
+use std::rc::Rc;
+use waypin_lib::{ClipboardContentType, Selection, WindowConfig};
+
+use super::{
+    accept_dropped_files, choose_save_path, connect_page_keys, is_save_shortcut, pin_overlay,
+    set_margins, viewer_window,
+};
+use crate::Backends;
+
+pub fn show_clipboard_text(
+    backends: Backends,
+    selection: Selection,
+    config: WindowConfig,
+    text: &str,
+) {
+    let app = Application::new(None, Default::default());
+    let text_owned = text.to_string();
+    app.connect_activate(move |app| {
+        let window = viewer_window(app, &format!("{} Text", selection.label()), &config);
+        window.set_default_size(config.text_width, config.text_height);
+        pin_overlay(&window, &config);
+
+        let page = text_page(&window, &backends, &config, &text_owned);
+        accept_dropped_files(&window, backends.get(selection), selection);
+        window.add(&page);
+        window.show_all();
+        window.present();
+    });
+    app.run();
+}
+
+/// The text viewer: the text, editable, with Save As… and a copy button
+/// per selection.
+pub fn text_page(
+    window: &ApplicationWindow,
+    backends: &Backends,
+    config: &WindowConfig,
+    text: &str,
+) -> Box {
+    let vbox = Box::new(Orientation::Vertical, 10);
+    set_margins(&vbox, config.margin);
+
+    let scrolled = ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    scrolled.set_min_content_height(180);
+    scrolled.set_min_content_width(350);
+    let text_view = TextView::new();
+    text_view.set_wrap_mode(gtk::WrapMode::Word);
+    if let Some(buffer) = text_view.buffer() {
+        buffer.set_text(text);
+    }
+    scrolled.add(&text_view);
+    vbox.pack_start(&scrolled, true, true, 0);
+
+    // One button per selection, so text can be moved between them
+    let button_box = Box::new(Orientation::Horizontal, 6);
+    button_box.set_margin_top(10);
+    button_box.set_halign(gtk::Align::End);
+
+    // Save As… and Ctrl+S write the edited text to a file
+    let save_text = {
+        let window = window.clone();
+        let text_view = text_view.clone();
+        move || {
+            let Some(buffer) = text_view.buffer() else {
+                return;
+            };
+            let text = buffer
+                .text(&buffer.start_iter(), &buffer.end_iter(), false)
+                .unwrap_or_default();
+            let Some(path) = choose_save_path(&window, ClipboardContentType::Text, "text/plain")
+            else {
+                return;
+            };
+            if let Err(e) = std::fs::write(&path, text.as_bytes()) {
+                eprintln!("Failed to write {}: {}", path.display(), e);
+            }
+        }
+    };
+    let save_text = Rc::new(save_text);
+    let save_btn = Button::with_label("Save As…");
+    let save_text_for_button = save_text.clone();
+    save_btn.connect_clicked(move |_| save_text_for_button());
+    button_box.pack_start(&save_btn, false, false, 0);
+    connect_page_keys(window, &vbox, move |_, event| {
+        if is_save_shortcut(event) {
+            save_text();
+            return true.into();
+        }
+        false.into()
+    });
+    for target in [Selection::Clipboard, Selection::Primary] {
+        let copy_btn = Button::with_label(&format!("Copy to {}", target.label()));
+        let text_view_clone = text_view.clone();
+        let backend_clone = backends.get(target).clone();
+        copy_btn.connect_clicked(move |_| {
+            let Some(buffer) = text_view_clone.buffer() else {
+                return;
+            };
+            let start = buffer.start_iter();
+            let end = buffer.end_iter();
+            if let Some(text_to_copy) = buffer.text(&start, &end, false) {
+                let contents = [("text/plain;charset=utf-8", text_to_copy.as_bytes())];
+                if let Err(e) = backend_clone.write(&contents) {
+                    eprintln!("Failed to copy text: {}", e);
+                }
+            }
+        });
+        button_box.pack_start(&copy_btn, false, false, 0);
+    }
+    vbox.pack_start(&button_box, false, false, 0);
+    vbox
+}
@@ -0,0 +1,181 @@
+/// How the image viewer sizes the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zoom {
+    /// Scale to fit the window (the default).
+    Fit,
+    /// A fixed scale, where 1.0 shows one image pixel per screen pixel.
+    Scale(f64),
+}
+
+impl Zoom {
+    /// The scale this zoom ends up at for an image shown in `area`.
+    pub fn scale(self, image: (i32, i32), area: (i32, i32)) -> f64 {
+        match self {
+            Zoom::Fit => fit_scale(image, area),
+            Zoom::Scale(scale) => scale,
+        }
+    }
+}
+
+/// Steps used by zooming in and out, so repeated zooming lands on round
+/// percentages.
+pub const ZOOM_STEPS: [f64; 19] = [
+    0.05, 0.1, 0.25, 0.33, 0.5, 0.67, 0.75, 1.0, 1.25, 1.5, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0,
+    24.0, 32.0,
+];
+
+pub const MIN_ZOOM: f64 = ZOOM_STEPS[0];
+pub const MAX_ZOOM: f64 = ZOOM_STEPS[ZOOM_STEPS.len() - 1];
+
+/// Largest scaled image, in pixels, the viewer renders; beyond this a
+/// zoomed pixbuf would take hundreds of megabytes.
+pub const MAX_RENDERED_PIXELS: f64 = 32.0 * 1024.0 * 1024.0;
+
+/// The next step above `scale`.
+pub fn zoom_in(scale: f64) -> f64 {
+    ZOOM_STEPS
+        .into_iter()
+        .find(|&step| step > scale * 1.001)
+        .unwrap_or(MAX_ZOOM)
+}
+
+/// The next step below `scale`.
+pub fn zoom_out(scale: f64) -> f64 {
+    ZOOM_STEPS
+        .into_iter()
+        .rev()
+        .find(|&step| step < scale / 1.001)
+        .unwrap_or(MIN_ZOOM)
+}
+
+/// Keeps `scale` within the zoom range and small enough that the scaled
+/// image stays under [`MAX_RENDERED_PIXELS`].
+pub fn clamp_zoom(scale: f64, image: (i32, i32)) -> f64 {
+    let pixels = image.0.max(1) as f64 * image.1.max(1) as f64;
+    let limit = (MAX_RENDERED_PIXELS / pixels).sqrt().max(MIN_ZOOM);
+    scale.clamp(MIN_ZOOM, MAX_ZOOM.min(limit))
+}
+
+/// The scale at which `image` fits inside `area`.
+pub fn fit_scale(image: (i32, i32), area: (i32, i32)) -> f64 {
+    if image.0 <= 0 || image.1 <= 0 {
+        return 1.0;
+    }
+    f64::min(
+        area.0 as f64 / image.0 as f64,
+        area.1 as f64 / image.1 as f64,
+    )
+}
+
+/// The size of `image` at `scale`, at least one pixel each way.
+pub fn scaled_size(image: (i32, i32), scale: f64) -> (i32, i32) {
+    (
+        ((image.0 as f64 * scale).round() as i32).max(1),
+        ((image.1 as f64 * scale).round() as i32).max(1),
+    )
+}
+
+/// Share of the screen a new image window may cover.
+pub const MAX_WINDOW_SHARE: f64 = 0.9;
+
+/// The size to open an image window at: the image's own size, shrunk to
+/// fit [`MAX_WINDOW_SHARE`] of `screen` when it is larger. Compositors
+/// don't move or resize a window that is too big for the screen on
+/// Wayland, so it has to start out fitting.
+pub fn initial_window_size(image: (i32, i32), screen: (i32, i32)) -> (i32, i32) {
+    let bounds = scaled_size(screen, MAX_WINDOW_SHARE);
+    scaled_size(image, fit_scale(image, bounds).min(1.0))
+}
+
+/// Whether to scale with nearest-neighbour sampling, which keeps pixels
+/// sharp when magnifying.
+pub fn uses_nearest_neighbor(scale: f64) -> bool {
+    scale > 1.0
+}
+
+/// The scroll offset that keeps the point under `anchor` (relative to the
+/// visible area) in place when the scale changes from `old` to `new`.
+pub fn anchored_offset(offset: f64, anchor: f64, old: f64, new: f64) -> f64 {
+    if old <= 0.0 {
+        return offset;
+    }
+    ((offset + anchor) * new / old - anchor).max(0.0)
+}
+
+/// The zoom indicator text, e.g. `150%`.
+pub fn zoom_label(scale: f64) -> String {
+    format!("{}%", (scale * 100.0).round())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_steps() {
+        assert_eq!(zoom_in(1.0), 1.25);
+        assert_eq!(zoom_in(1.1), 1.25);
+        assert_eq!(zoom_out(1.0), 0.75);
+        assert_eq!(zoom_out(0.8), 0.75);
+        assert_eq!(zoom_in(MAX_ZOOM), MAX_ZOOM);
+        assert_eq!(zoom_out(MIN_ZOOM), MIN_ZOOM);
+    }
+
+    #[test]
+    fn test_zoom_steps_from_fit_scale() {
+        // A fitted image at 0.8 zooms to the surrounding steps
+        assert_eq!(zoom_in(0.8), 1.0);
+        assert_eq!(zoom_out(0.8), 0.75);
+    }
+
+    #[test]
+    fn test_clamp_zoom() {
+        assert_eq!(clamp_zoom(100.0, (10, 10)), MAX_ZOOM);
+        assert_eq!(clamp_zoom(0.0, (10, 10)), MIN_ZOOM);
+        // 4000x4000 may only grow to about 1.45x
+        let limit = clamp_zoom(8.0, (4000, 4000));
+        assert!(limit > 1.4 && limit < 1.5);
+        let (w, h) = scaled_size((4000, 4000), limit);
+        assert!(w as f64 * h as f64 <= MAX_RENDERED_PIXELS * 1.001);
+    }
+
+    #[test]
+    fn test_fit_scale_and_scaled_size() {
+        assert_eq!(fit_scale((200, 100), (100, 100)), 0.5);
+        assert_eq!(fit_scale((50, 50), (100, 200)), 2.0);
+        assert_eq!(fit_scale((0, 50), (100, 200)), 1.0);
+        assert_eq!(scaled_size((200, 100), 0.5), (100, 50));
+        assert_eq!(scaled_size((3, 3), 0.01), (1, 1));
+        assert_eq!(Zoom::Fit.scale((200, 100), (100, 100)), 0.5);
+        assert_eq!(Zoom::Scale(3.0).scale((200, 100), (100, 100)), 3.0);
+    }
+
+    #[test]
+    fn test_initial_window_size() {
+        assert_eq!(initial_window_size((400, 300), (1920, 1080)), (400, 300));
+        assert_eq!(initial_window_size((4000, 1000), (2000, 1000)), (1800, 450));
+        assert_eq!(initial_window_size((1000, 3000), (1920, 1000)), (300, 900));
+    }
+
+    #[test]
+    fn test_uses_nearest_neighbor_above_100_percent() {
+        assert!(!uses_nearest_neighbor(0.5));
+        assert!(!uses_nearest_neighbor(1.0));
+        assert!(uses_nearest_neighbor(1.25));
+    }
+
+    #[test]
+    fn test_anchored_offset() {
+        // Doubling around the point 50px into the view at offset 100
+        assert_eq!(anchored_offset(100.0, 50.0, 1.0, 2.0), 250.0);
+        assert_eq!(anchored_offset(250.0, 50.0, 2.0, 1.0), 100.0);
+        assert_eq!(anchored_offset(0.0, 10.0, 2.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_zoom_label() {
+        assert_eq!(zoom_label(1.0), "100%");
+        assert_eq!(zoom_label(0.333), "33%");
+        assert_eq!(zoom_label(12.0), "1200%");
+    }
+}
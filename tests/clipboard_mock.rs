@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use waypin_lib::ClipboardBackend;
 
+#[derive(Default)]
 pub struct MockClipboard {
     content: Arc<Mutex<HashMap<String, Vec<u8>>>>,
     types: Arc<Mutex<Vec<String>>>,
@@ -78,6 +80,44 @@ impl MockClipboard {
     }
 }
 
+impl ClipboardBackend for MockClipboard {
+    fn list_types(&self) -> Result<String, String> {
+        Ok(self.get_types().join("\n"))
+    }
+
+    fn read(&self, mime_type: &str) -> Result<Vec<u8>, String> {
+        self.get_content(mime_type)
+            .ok_or_else(|| format!("No {} content", mime_type))
+    }
+
+    fn write(&self, contents: &[(&str, &[u8])]) -> Result<(), String> {
+        if contents.is_empty() {
+            return Err("Nothing to copy".to_string());
+        }
+
+        let mut content = self.content.lock().unwrap();
+        let mut types = self.types.lock().unwrap();
+
+        content.clear();
+        types.clear();
+        for (mime_type, data) in contents {
+            content.insert(mime_type.to_string(), data.to_vec());
+            types.push(mime_type.to_string());
+        }
+
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        MockClipboard::clear(self);
+        Ok(())
+    }
+
+    fn offers_multiple_types(&self) -> bool {
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,4 +210,163 @@ mod tests {
             assert_eq!(retrieved_data, data);
         }
     }
+
+    #[test]
+    fn test_backend_list_and_read() {
+        let clipboard = MockClipboard::new();
+        clipboard
+            .write(&[("text/plain", b"backend text"), ("UTF8_STRING", b"backend text")])
+            .unwrap();
+
+        let types = clipboard.list_types().unwrap();
+        assert_eq!(
+            waypin_lib::detect_clipboard_content_type(&types),
+            waypin_lib::ClipboardContentType::Text
+        );
+
+        let mime = waypin_lib::get_text_format_from_types(&types).unwrap();
+        assert_eq!(clipboard.read(mime).unwrap(), b"backend text");
+        assert!(clipboard.read("image/png").is_err());
+    }
+
+    #[test]
+    fn test_backend_write_multiple_types() {
+        let clipboard = MockClipboard::new();
+        let contents: [(&str, &[u8]); 2] = [("text/html", b"<b>hi</b>"), ("text/plain", b"hi")];
+        clipboard.write(&contents).unwrap();
+
+        assert_eq!(
+            waypin_lib::offered_types(&clipboard, &contents),
+            ["text/html", "text/plain"]
+        );
+        assert_eq!(clipboard.get_types(), vec!["text/html", "text/plain"]);
+        assert_eq!(clipboard.read("text/plain").unwrap(), b"hi");
+    }
+
+    #[test]
+    fn test_backend_clear() {
+        let clipboard = MockClipboard::new();
+        clipboard.set_text("test");
+        ClipboardBackend::clear(&clipboard).unwrap();
+
+        assert!(clipboard.list_types().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_copy_image_to_clipboard_through_backend() {
+        let clipboard = MockClipboard::new();
+        let png_data = vec![0x89, 0x50, 0x4E, 0x47];
+
+        waypin_lib::copy_image_to_clipboard(&clipboard, "image/png", &png_data).unwrap();
+        assert_eq!(clipboard.get_content("image/png").unwrap(), png_data);
+
+        let result = waypin_lib::copy_image_to_clipboard(&clipboard, "text/plain", &png_data);
+        assert_eq!(result.unwrap_err(), "Invalid image MIME type");
+    }
+
+    #[test]
+    fn test_copy_image_as_offers_every_format() {
+        use waypin_lib::convert::{copy_image_as, ImageCodec, ImageFormat};
+        use waypin_lib::raster::RgbaImage;
+
+        // Encodes each format as its label
+        struct LabelCodec;
+        impl ImageCodec for LabelCodec {
+            fn decode(&self, _: &str, _: &[u8]) -> Result<RgbaImage, String> {
+                Ok(RgbaImage::new(1, 1))
+            }
+            fn encode(&self, _: &RgbaImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+                Ok(format.label().as_bytes().to_vec())
+            }
+        }
+
+        let clipboard = MockClipboard::new();
+        let jpeg_data = vec![0xFF, 0xD8, 0xFF];
+        let formats = [ImageFormat::Png, ImageFormat::Jpeg { quality: 80 }];
+        let offered =
+            copy_image_as(&clipboard, &LabelCodec, "image/jpeg", &jpeg_data, &formats).unwrap();
+
+        assert_eq!(offered, ["image/png", "image/jpeg"]);
+        assert_eq!(clipboard.get_types(), vec!["image/png", "image/jpeg"]);
+        assert_eq!(clipboard.get_content("image/png").unwrap(), b"PNG");
+        assert_eq!(clipboard.get_content("image/jpeg").unwrap(), jpeg_data);
+    }
+
+    #[test]
+    fn test_snapshot_dispatches_text_viewer() {
+        let clipboard = MockClipboard::new();
+        clipboard
+            .write(&[("text/plain", b"Hello, World!"), ("UTF8_STRING", b"Hello, World!")])
+            .unwrap();
+
+        let snapshot = waypin_lib::ClipboardSnapshot::capture(&clipboard, None, None).unwrap();
+        assert_eq!(
+            waypin_lib::dispatch(&snapshot),
+            Ok(waypin_lib::ViewerRequest::Text("Hello, World!".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_snapshot_dispatches_image_viewer() {
+        let clipboard = MockClipboard::new();
+        let png_data = vec![0x89, 0x50, 0x4E, 0x47];
+        clipboard.set_image("image/png", png_data.clone());
+
+        let snapshot = waypin_lib::ClipboardSnapshot::capture(&clipboard, None, None).unwrap();
+        assert_eq!(
+            waypin_lib::dispatch(&snapshot),
+            Ok(waypin_lib::ViewerRequest::Image {
+                mime_type: "image/png".to_string(),
+                data: png_data,
+            })
+        );
+    }
+
+    #[test]
+    fn test_snapshot_dispatches_file_list_viewer() {
+        let clipboard = MockClipboard::new();
+        clipboard.set_file_list(vec!["file:///tmp/a%20b.txt", "file:///tmp/c"]);
+
+        let snapshot = waypin_lib::ClipboardSnapshot::capture(&clipboard, None, None).unwrap();
+        let Ok(waypin_lib::ViewerRequest::Files(entries)) = waypin_lib::dispatch(&snapshot) else {
+            panic!("expected the file list viewer");
+        };
+        let paths: Vec<_> = entries.iter().map(|entry| entry.display_name()).collect();
+        assert_eq!(paths, ["/tmp/a b.txt", "/tmp/c"]);
+    }
+
+    #[test]
+    fn test_snapshot_of_empty_clipboard_fails() {
+        let clipboard = MockClipboard::new();
+        assert!(waypin_lib::ClipboardSnapshot::capture(&clipboard, None, None).is_err());
+    }
+
+    #[test]
+    fn test_record_clipboard_into_history() {
+        use waypin_lib::history::{record_clipboard, AddOutcome, HistoryStore};
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open(dir.path()).unwrap();
+        let clipboard = MockClipboard::new();
+        let rules = waypin_lib::DetectionRules::default();
+
+        assert_eq!(record_clipboard(&clipboard, &store, &rules).unwrap(), None);
+
+        clipboard
+            .write(&[("text/plain", b"first"), ("UTF8_STRING", b"first")])
+            .unwrap();
+        let (outcome, _) = record_clipboard(&clipboard, &store, &rules).unwrap().unwrap();
+        assert_eq!(outcome, AddOutcome::Added);
+        let (outcome, _) = record_clipboard(&clipboard, &store, &rules).unwrap().unwrap();
+        assert_eq!(outcome, AddOutcome::Unchanged);
+
+        clipboard.set_image("image/png", vec![0x89, 0x50, 0x4E, 0x47]);
+        record_clipboard(&clipboard, &store, &rules).unwrap();
+
+        let entries = store.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].content_type, waypin_lib::ClipboardContentType::Image);
+        assert_eq!(entries[1].types, vec!["text/plain", "UTF8_STRING"]);
+        assert_eq!(store.payload(&entries[1], "UTF8_STRING").unwrap(), b"first");
+    }
 }
@@ -2,32 +2,135 @@ use std::process::Command;
 use std::io::Write;
 use tempfile::NamedTempFile;
 
+fn run_waypin(args: &[&str]) -> std::process::Output {
+    Command::new("cargo")
+        .args(["run", "--"])
+        .args(args)
+        .env("DISPLAY", "")
+        .env("WAYLAND_DISPLAY", "")
+        .output()
+        .expect("Failed to execute waypin")
+}
+
+/// Asserts that waypin got past argument parsing and then stopped because
+/// no clipboard can be reached without a display.
+fn assert_no_clipboard(output: &std::process::Output) {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert_eq!(
+        stderr.trim_end(),
+        "Could not retrieve clipboard types or clipboard is empty."
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).is_empty());
+}
+
 #[test]
 fn test_waypin_help_message() {
-    let output = Command::new("cargo")
-        .args(&["run", "--", "--help"])
-        .output()
-        .expect("Failed to execute waypin");
-    
+    let output = run_waypin(&["--help"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("Usage:"));
+    assert!(stdout.contains("list-types"));
+}
+
+#[test]
+fn test_waypin_short_help_message() {
+    let output = run_waypin(&["-h"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Usage:"));
+}
+
+#[test]
+fn test_waypin_version() {
+    let output = run_waypin(&["--version"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert_eq!(stdout.trim(), format!("waypin {}", env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn test_waypin_unknown_argument_exits_with_error() {
+    let output = run_waypin(&["invalid-arg"]);
+
     let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("Unexpected argument: invalid-arg"));
     assert!(stderr.contains("Usage:"));
 }
 
 #[test]
-fn test_waypin_with_args_exits_with_error() {
-    let output = Command::new("cargo")
-        .args(&["run", "--", "invalid-arg"])
-        .output()
-        .expect("Failed to execute waypin");
-    
+fn test_waypin_unknown_option_exits_with_error() {
+    let output = run_waypin(&["--bogus"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown option: --bogus"));
+}
+
+#[test]
+fn test_waypin_type_requires_value() {
+    let output = run_waypin(&["--type"]);
+
     assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--type requires a MIME type"));
+}
+
+#[test]
+fn test_waypin_type_is_accepted() {
+    let output = run_waypin(&["--type", "text/html"]);
+
+    assert_no_clipboard(&output);
+}
+
+#[test]
+fn test_waypin_text_and_image_conflict() {
+    let output = run_waypin(&["--text", "--image"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.starts_with("--text and --image cannot be used together"));
+    assert!(stderr.contains("Usage:"));
+}
+
+#[test]
+fn test_waypin_forced_viewers_are_accepted() {
+    for flag in ["--text", "--image"] {
+        let output = run_waypin(&[flag]);
+        assert_no_clipboard(&output);
+    }
+}
+
+#[test]
+fn test_waypin_list_types_without_clipboard() {
+    let output = run_waypin(&["list-types"]);
+
+    // No clipboard tool can reach a display here, so listing must fail cleanly
+    assert_no_clipboard(&output);
+}
+
+#[test]
+fn test_waypin_primary_flag_is_accepted() {
+    let output = run_waypin(&["--primary"]);
+
+    assert_no_clipboard(&output);
+}
+
+#[test]
+fn test_waypin_primary_with_extra_args_exits_with_error() {
+    let output = run_waypin(&["--primary", "invalid-arg"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.starts_with("Unexpected argument: invalid-arg"));
 }
 
 #[test]
 fn test_empty_clipboard_handling() {
     // Mock empty clipboard by running waypin with no clipboard data
     let output = Command::new("cargo")
-        .args(&["run"])
+        .args(["run"])
         .env("DISPLAY", "") // Remove display to simulate no clipboard
         .output();
     
@@ -130,7 +233,7 @@ fn test_wl_copy_command_structure() {
     
     for (mime_type, _description) in test_cases {
         // Simulate the command that would be run
-        let args = vec!["wl-copy", "--type", mime_type];
+        let args = ["wl-copy", "--type", mime_type];
         
         // Verify command structure
         assert_eq!(args[0], "wl-copy");